@@ -0,0 +1,70 @@
+// Compares normalizing by rewriting the term as a tree against lambda lifting
+// it and running it on the graph reducer in src/graph.rs, on Church numeral
+// arithmetic and on the self-interpreter running quoted arithmetic. Run with `cargo bench --bench evaluators`.
+use rs_lambda::encodings::church_nat;
+use rs_lambda::graph;
+use rs_lambda::quote::{quote, self_interpreter};
+use rs_lambda::rewrite::{Beta, Rewriter, Strategy};
+use rs_lambda::{app, apps, parse, LambdaTerm};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 5;
+const LIMIT: usize = 10_000_000;
+
+// The mean time of one run of `f`.
+fn measure<T>(mut f: impl FnMut() -> T) -> Duration {
+    black_box(f());
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+    start.elapsed() / ROUNDS
+}
+
+fn main() {
+    let plus = parse("λm. λn. λf. λx. m f (n f x)").unwrap();
+    let mult = parse("λm. λn. λf. m (n f)").unwrap();
+    let exp = parse("λm. λn. n m").unwrap();
+    let pred = parse("λn. λf. λx. n (λg. λh. h (g f)) (λu. x) (λu. u)").unwrap();
+    let workloads: Vec<(&str, LambdaTerm)> = vec![
+        (
+            "plus 200 200",
+            apps(plus, [church_nat(200), church_nat(200)]),
+        ),
+        (
+            "mult 20 20",
+            apps(mult.clone(), [church_nat(20), church_nat(20)]),
+        ),
+        ("exp 2 8", apps(exp, [church_nat(2), church_nat(8)])),
+        ("pred 100", apps(pred.clone(), [church_nat(100)])),
+        (
+            "E ⌜pred 5⌝",
+            app(self_interpreter(), quote(&apps(pred, [church_nat(5)]))),
+        ),
+        (
+            "E ⌜mult 3 3⌝",
+            app(
+                self_interpreter(),
+                quote(&apps(mult, [church_nat(3), church_nat(3)])),
+            ),
+        ),
+    ];
+    let rewriter = Rewriter::new(Strategy::TopDown, LIMIT).rule(Beta);
+    println!("{:<14} {:>12} {:>12}", "", "rewriting", "graph");
+    for (name, term) in &workloads {
+        assert!(rewriter
+            .normalize(term)
+            .unwrap()
+            .alpha_equivalent(&graph::evaluate(term, LIMIT).unwrap()));
+        let rewriting = measure(|| rewriter.normalize(term));
+        let graph = measure(|| graph::evaluate(term, LIMIT));
+        println!(
+            "{:<14} {:>12.2?} {:>12.2?}   {:.2}x",
+            name,
+            rewriting,
+            graph,
+            rewriting.as_secs_f64() / graph.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Compares terms with String names against the same terms with SmallName
+// names, timing the operations that copy or hash names and counting the heap
+// allocations each makes. Run with `cargo bench --bench names`.
+use rs_lambda::generate::{gen_closed_term, SplitMix64};
+use rs_lambda::{LambdaTerm, Term};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ROUNDS: u32 = 20;
+
+// The mean time and allocation count of one run of `f`.
+fn measure<T>(mut f: impl FnMut() -> T) -> (Duration, usize) {
+    black_box(f());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(f());
+    }
+    let elapsed = start.elapsed() / ROUNDS;
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / ROUNDS as usize;
+    (elapsed, allocations)
+}
+
+fn compare<T, U>(name: &str, string: impl FnMut() -> T, small: impl FnMut() -> U) {
+    let (string_time, string_allocations) = measure(string);
+    let (small_time, small_allocations) = measure(small);
+    println!(
+        "{:<18} {:>10.2?} {:>9} allocs   {:>10.2?} {:>9} allocs   {:.2}x",
+        name,
+        string_time,
+        string_allocations,
+        small_time,
+        small_allocations,
+        string_time.as_secs_f64() / small_time.as_secs_f64()
+    );
+}
+
+fn main() {
+    println!(
+        "{:<18} {:>28}   {:>28}",
+        "", "String names", "SmallName names"
+    );
+    for size in [10_000, 100_000, 1_000_000] {
+        let small: LambdaTerm = gen_closed_term(size, &mut SplitMix64(size as u64));
+        let term: Term<String> = small.map_variables(|name| String::from(name.as_str()));
+        println!("size {}", size);
+        compare("  clone", || term.clone(), || small.clone());
+        compare(
+            "  bound_variables",
+            || term.bound_variables(),
+            || small.bound_variables(),
+        );
+        compare(
+            "  alpha_equivalent",
+            || term.alpha_equivalent(&term),
+            || small.alpha_equivalent(&small),
+        );
+    }
+}
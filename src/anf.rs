@@ -0,0 +1,225 @@
+use crate::names::{Decoration, NameSupply};
+use crate::small::SmallName;
+use crate::{app, lam, var, LambdaTerm};
+use std::collections::HashSet;
+use std::fmt;
+
+// A-normal form: every application has variables or abstractions for its
+// function and argument, and every application but the last one of a body
+// names its result with a let, so evaluation order is spelled out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Variable(SmallName),
+    Lambda {
+        parameter: SmallName,
+        body: Box<Anf>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anf {
+    Return(Value),
+    // let name = function argument in body
+    Let {
+        name: SmallName,
+        function: Value,
+        argument: Value,
+        body: Box<Anf>,
+    },
+    TailCall {
+        function: Value,
+        argument: Value,
+    },
+}
+
+// Turns `term` into a value, adding a let for each application it contains
+// to `bindings`, innermost and leftmost first, which is call-by-value order.
+fn atomize(
+    term: &LambdaTerm,
+    bindings: &mut Vec<(SmallName, Value, Value)>,
+    names: &mut NameSupply,
+) -> Value {
+    match term {
+        LambdaTerm::Variable(id) => Value::Variable(id.clone()),
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => Value::Lambda {
+            parameter: bound_variable.clone(),
+            body: Box::new(convert(return_term, names)),
+        },
+        LambdaTerm::Application { function, argument } => {
+            let function = atomize(function, bindings, names);
+            let argument = atomize(argument, bindings, names);
+            let name = names.fresh("t");
+            bindings.push((name.clone(), function, argument));
+            Value::Variable(name)
+        }
+    }
+}
+
+fn convert(term: &LambdaTerm, names: &mut NameSupply) -> Anf {
+    let mut bindings = Vec::new();
+    let tail = match term {
+        LambdaTerm::Application { function, argument } => {
+            let function = atomize(function, &mut bindings, names);
+            let argument = atomize(argument, &mut bindings, names);
+            Anf::TailCall { function, argument }
+        }
+        _ => Anf::Return(atomize(term, &mut bindings, names)),
+    };
+    bindings
+        .into_iter()
+        .rev()
+        .fold(tail, |body, (name, function, argument)| Anf::Let {
+            name,
+            function,
+            argument,
+            body: Box::new(body),
+        })
+}
+
+// The names introduced for intermediate results are t, t₁, t₂, … skipping
+// any the term already uses, so they never shadow or capture.
+pub fn to_anf(term: &LambdaTerm) -> Anf {
+    let mut used: HashSet<SmallName> = term.free_variables();
+    used.extend(term.bound_variables());
+    let mut names = NameSupply::with_decoration(used, Decoration::Subscripts);
+    convert(term, &mut names)
+}
+
+impl Value {
+    pub fn to_lambda(&self) -> LambdaTerm {
+        match self {
+            Value::Variable(id) => var(id),
+            Value::Lambda { parameter, body } => lam(parameter, body.to_lambda()),
+        }
+    }
+}
+
+impl Anf {
+    // Back to a plain term, reading `let x = f a in M` as `(λx. M) (f a)`.
+    // The result is β-equal to the term the ANF came from.
+    pub fn to_lambda(&self) -> LambdaTerm {
+        match self {
+            Anf::Return(value) => value.to_lambda(),
+            Anf::Let {
+                name,
+                function,
+                argument,
+                body,
+            } => app(
+                lam(name, body.to_lambda()),
+                app(function.to_lambda(), argument.to_lambda()),
+            ),
+            Anf::TailCall { function, argument } => app(function.to_lambda(), argument.to_lambda()),
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Anf::Return(value) => value.fmt_indented(f, indent),
+            Anf::Let {
+                name,
+                function,
+                argument,
+                body,
+            } => {
+                write!(f, "let {} = ", name)?;
+                fmt_call(f, function, argument, indent)?;
+                write!(f, " in\n{:indent$}", "", indent = indent)?;
+                body.fmt_indented(f, indent)
+            }
+            Anf::TailCall { function, argument } => fmt_call(f, function, argument, indent),
+        }
+    }
+}
+
+impl Value {
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Value::Variable(id) => f.write_str(id),
+            Value::Lambda { parameter, body } => {
+                write!(f, "λ{}.\n{:indent$}", parameter, "", indent = indent + 2)?;
+                body.fmt_indented(f, indent + 2)
+            }
+        }
+    }
+}
+
+fn fmt_call(
+    f: &mut fmt::Formatter<'_>,
+    function: &Value,
+    argument: &Value,
+    indent: usize,
+) -> fmt::Result {
+    for (value, separator) in [(function, ""), (argument, " ")] {
+        f.write_str(separator)?;
+        match value {
+            Value::Variable(_) => value.fmt_indented(f, indent)?,
+            Value::Lambda { .. } => {
+                f.write_str("(")?;
+                value.fmt_indented(f, indent)?;
+                f.write_str(")")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// One let per line, with the body of each λ on the lines after it, indented:
+//
+//   let t = f x in
+//   t (λy.
+//     y)
+impl fmt::Display for Anf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::parse;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+
+    #[test]
+    fn names_intermediate_applications() {
+        let anf = to_anf(&parse("f (g x) (h y)").unwrap());
+        assert_eq!(
+            anf.to_string(),
+            "let t = g x in\nlet t₁ = f t in\nlet t₂ = h y in\nt₁ t₂"
+        );
+        let anf = to_anf(&parse("λt. t (t t)").unwrap());
+        assert!(matches!(anf, Anf::Return(Value::Lambda { .. })));
+    }
+
+    #[test]
+    fn converts_back_to_beta_equal_terms() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 500).rule(Beta);
+        let mut rng = SplitMix64(380);
+        let mut compared = 0;
+        for i in 0..400 {
+            let term = gen_closed_term(1 + i % 16, &mut rng);
+            let term = app(app(term, var("p")), lam("q", var("q")));
+            let anf = to_anf(&term);
+            let back = anf.to_lambda();
+            assert_eq!(back.free_variables(), term.free_variables());
+            let Ok(expected) = rewriter.normalize(&term) else {
+                continue;
+            };
+            let normal = rewriter.normalize(&back).unwrap();
+            assert!(normal.alpha_equivalent(&expected), "{}", term);
+            compared += 1;
+        }
+        assert!(compared > 300);
+    }
+}
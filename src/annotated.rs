@@ -0,0 +1,252 @@
+use crate::names::NameSupply;
+use crate::small::SmallName;
+use crate::LambdaTerm;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnnotatedNode {
+    Abstraction {
+        bound_variable: SmallName,
+        return_term: Box<AnnotatedTerm>,
+    },
+    Application {
+        function: Box<AnnotatedTerm>,
+        argument: Box<AnnotatedTerm>,
+    },
+    Variable(SmallName),
+}
+
+// A term that keeps the free variables of every node alongside it, worked
+// out once as the node is built from its children. Substitution can then
+// tell in constant time whether a subterm needs visiting at all and whether
+// a binder would capture, where LambdaTerm::substitute walks the body under
+// every binder it passes to find out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedTerm {
+    node: AnnotatedNode,
+    free_variables: HashSet<SmallName>,
+}
+
+impl AnnotatedTerm {
+    pub fn variable(id: impl Into<SmallName>) -> AnnotatedTerm {
+        let id = id.into();
+        AnnotatedTerm {
+            free_variables: HashSet::from([id.clone()]),
+            node: AnnotatedNode::Variable(id),
+        }
+    }
+
+    pub fn abstraction(
+        bound_variable: impl Into<SmallName>,
+        return_term: AnnotatedTerm,
+    ) -> AnnotatedTerm {
+        let bound_variable = bound_variable.into();
+        let mut free_variables = return_term.free_variables.clone();
+        free_variables.remove(&bound_variable);
+        AnnotatedTerm {
+            node: AnnotatedNode::Abstraction {
+                bound_variable,
+                return_term: Box::new(return_term),
+            },
+            free_variables,
+        }
+    }
+
+    pub fn application(function: AnnotatedTerm, argument: AnnotatedTerm) -> AnnotatedTerm {
+        let free_variables = function
+            .free_variables
+            .union(&argument.free_variables)
+            .cloned()
+            .collect();
+        AnnotatedTerm {
+            node: AnnotatedNode::Application {
+                function: Box::new(function),
+                argument: Box::new(argument),
+            },
+            free_variables,
+        }
+    }
+
+    pub fn node(&self) -> &AnnotatedNode {
+        &self.node
+    }
+
+    pub fn free_variables(&self) -> &HashSet<SmallName> {
+        &self.free_variables
+    }
+
+    pub fn occurs_free(&self, variable: &str) -> bool {
+        self.free_variables.contains(variable)
+    }
+
+    // Capture-avoiding substitution, as LambdaTerm::substitute does it, but
+    // skipping every subterm in which `variable` isn't free and checking for
+    // capture against the cached sets.
+    pub fn substitute(&self, variable: &str, replacement: &AnnotatedTerm) -> AnnotatedTerm {
+        if !self.occurs_free(variable) {
+            return self.clone();
+        }
+        match &self.node {
+            AnnotatedNode::Variable(_) => replacement.clone(),
+            AnnotatedNode::Application { function, argument } => AnnotatedTerm::application(
+                function.substitute(variable, replacement),
+                argument.substitute(variable, replacement),
+            ),
+            AnnotatedNode::Abstraction {
+                bound_variable,
+                return_term,
+            } => {
+                // `variable` is free here, so it isn't `bound_variable` and
+                // does occur in the body.
+                if replacement.occurs_free(bound_variable) {
+                    let mut names = NameSupply::new(
+                        return_term
+                            .free_variables
+                            .iter()
+                            .chain(&replacement.free_variables)
+                            .cloned(),
+                    );
+                    let fresh = names.fresh(bound_variable);
+                    let renamed = return_term
+                        .substitute(bound_variable, &AnnotatedTerm::variable(fresh.clone()));
+                    AnnotatedTerm::abstraction(fresh, renamed.substitute(variable, replacement))
+                } else {
+                    AnnotatedTerm::abstraction(
+                        bound_variable.clone(),
+                        return_term.substitute(variable, replacement),
+                    )
+                }
+            }
+        }
+    }
+
+    // Contracts the term if it is itself a β-redex.
+    pub fn beta(&self) -> Option<AnnotatedTerm> {
+        match &self.node {
+            AnnotatedNode::Application { function, argument } => match &function.node {
+                AnnotatedNode::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => Some(return_term.substitute(bound_variable, argument)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_lambda(&self) -> LambdaTerm {
+        match &self.node {
+            AnnotatedNode::Variable(id) => LambdaTerm::Variable(id.clone()),
+            AnnotatedNode::Abstraction {
+                bound_variable,
+                return_term,
+            } => LambdaTerm::Abstraction {
+                bound_variable: bound_variable.clone(),
+                return_term: Box::new(return_term.to_lambda()),
+            },
+            AnnotatedNode::Application { function, argument } => LambdaTerm::Application {
+                function: Box::new(function.to_lambda()),
+                argument: Box::new(argument.to_lambda()),
+            },
+        }
+    }
+}
+
+impl LambdaTerm {
+    pub fn annotate(&self) -> AnnotatedTerm {
+        match self {
+            LambdaTerm::Variable(id) => AnnotatedTerm::variable(id.clone()),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => AnnotatedTerm::abstraction(bound_variable.clone(), return_term.annotate()),
+            LambdaTerm::Application { function, argument } => {
+                AnnotatedTerm::application(function.annotate(), argument.annotate())
+            }
+        }
+    }
+}
+
+impl From<&LambdaTerm> for AnnotatedTerm {
+    fn from(term: &LambdaTerm) -> AnnotatedTerm {
+        term.annotate()
+    }
+}
+
+impl From<&AnnotatedTerm> for LambdaTerm {
+    fn from(term: &AnnotatedTerm) -> LambdaTerm {
+        term.to_lambda()
+    }
+}
+
+impl fmt::Display for AnnotatedTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_lambda().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::parse;
+
+    // A random term with the outer binders stripped off, so that it has
+    // free variables to substitute for and to be captured.
+    fn open_term(size: usize, rng: &mut SplitMix64) -> LambdaTerm {
+        let mut term = gen_closed_term(size, rng);
+        for _ in 0..2 {
+            if let LambdaTerm::Abstraction { return_term, .. } = term {
+                term = *return_term;
+            }
+        }
+        term
+    }
+
+    fn check_annotations(term: &AnnotatedTerm) {
+        assert_eq!(term.free_variables(), &term.to_lambda().free_variables());
+        match term.node() {
+            AnnotatedNode::Variable(_) => (),
+            AnnotatedNode::Abstraction { return_term, .. } => check_annotations(return_term),
+            AnnotatedNode::Application { function, argument } => {
+                check_annotations(function);
+                check_annotations(argument);
+            }
+        }
+    }
+
+    #[test]
+    fn substitutes_as_lambda_terms_do() {
+        let mut rng = SplitMix64(372);
+        for i in 0..3000 {
+            let term = open_term(3 + i % 30, &mut rng);
+            let replacement = open_term(3 + i % 7, &mut rng);
+            for variable in ["x0", "x1"] {
+                let expected = term.substitute(variable, &replacement);
+                let annotated = term
+                    .annotate()
+                    .substitute(variable, &replacement.annotate());
+                assert!(
+                    annotated.to_lambda().alpha_equivalent(&expected),
+                    "{}[{} := {}]",
+                    term,
+                    variable,
+                    replacement
+                );
+                check_annotations(&annotated);
+            }
+        }
+    }
+
+    #[test]
+    fn renames_binders_that_would_capture() {
+        let term = parse("λy. x y").unwrap().annotate();
+        let substituted = term.substitute("x", &parse("y").unwrap().annotate());
+        assert!(substituted
+            .to_lambda()
+            .alpha_equivalent(&parse("λz. y z").unwrap()));
+        assert!(substituted.occurs_free("y"));
+        assert!(!substituted.occurs_free("x"));
+    }
+}
@@ -0,0 +1,133 @@
+use io::prelude::*;
+use std::io;
+
+use rs_lambda::print::{Parens, PrintOptions, Wrap};
+use rs_lambda::LambdaTerm;
+
+const USAGE: &str = "\
+usage: lambdafmt [options] [file ...]
+
+reprints each file's term in place, or stdin's to stdout
+  --check                   list the files that would change, without
+                            changing them, and exit with status 1 if any would
+  --width <n>               break lines longer than n columns (80)
+  --wrap fill|nested        how lines are broken (nested)
+  --parens minimal|explicit|clarifying
+  --ascii                   write \\ instead of λ
+  --collapse-binders        write λx y. M instead of λx. λy. M
+  --tight-dot               write λx.M instead of λx. M
+  -h, --help                show this message";
+
+struct Options {
+    files: Vec<String>,
+    check: bool,
+    print: PrintOptions,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        files: Vec::new(),
+        check: false,
+        print: PrintOptions {
+            width: Some(80),
+            wrap: Wrap::Nested,
+            ..PrintOptions::default()
+        },
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .map(String::as_str)
+                .ok_or_else(|| format!("{} expects a value", flag))
+        };
+        match arg.as_str() {
+            "--check" => options.check = true,
+            "--width" => {
+                let width = value(arg)?;
+                options.print.width = match width.parse() {
+                    Ok(width) if width > 0 => Some(width),
+                    _ => return Err(format!("invalid width {}", width)),
+                }
+            }
+            "--wrap" => {
+                options.print.wrap = match value(arg)? {
+                    "fill" => Wrap::Fill,
+                    "nested" => Wrap::Nested,
+                    other => return Err(format!("unknown wrap style {}", other)),
+                }
+            }
+            "--parens" => {
+                options.print.parens = match value(arg)? {
+                    "minimal" => Parens::Minimal,
+                    "explicit" => Parens::Explicit,
+                    "clarifying" => Parens::Clarifying,
+                    other => return Err(format!("unknown parens style {}", other)),
+                }
+            }
+            "--ascii" => options.print.ascii = true,
+            "--collapse-binders" => options.print.collapse_binders = true,
+            "--tight-dot" => options.print.space_after_dot = false,
+            other if other.starts_with('-') => return Err(format!("unknown option {}", other)),
+            path => options.files.push(String::from(path)),
+        }
+    }
+    Ok(options)
+}
+
+fn format(source: &str, options: &PrintOptions) -> Result<String, String> {
+    let term = source
+        .parse::<LambdaTerm>()
+        .map_err(|err| format!("{:?}", err))?;
+    Ok(format!("{}\n", term.display_with(options)))
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.files.is_empty() {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        match format(&buffer, &options.print) {
+            Ok(formatted) if options.check => {
+                if formatted != buffer {
+                    println!("<stdin>");
+                    std::process::exit(1);
+                }
+            }
+            Ok(formatted) => io::stdout().write_all(formatted.as_bytes())?,
+            Err(err) => {
+                eprintln!("<stdin>: {}", err);
+                std::process::exit(2);
+            }
+        }
+        return Ok(());
+    }
+    let mut status = 0;
+    for path in &options.files {
+        let source = std::fs::read_to_string(path)?;
+        match format(&source, &options.print) {
+            Ok(formatted) if formatted == source => (),
+            Ok(_) if options.check => {
+                println!("{}", path);
+                status = status.max(1);
+            }
+            Ok(formatted) => std::fs::write(path, formatted)?,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                status = 2;
+            }
+        }
+    }
+    std::process::exit(status);
+}
@@ -1,13 +1,55 @@
 use io::prelude::*;
 use std::io;
 
+const USAGE: &str = "\
+usage: slash_to_lambda [--to-unicode | --to-ascii] < input
+
+rewrites the lambdas in a term between \\ and λ, leaving quoted
+identifiers as they are
+  --to-unicode   \\ becomes λ (the default)
+  --to-ascii     λ becomes \\";
+
+// Swaps `from` for `to` everywhere outside a `...` or "..." quoted
+// identifier, where a \ is an escape and λ may be part of the name.
+fn transliterate(input: &str, from: char, to: char) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '`' | '"' => {
+                output.push(ch);
+                while let Some(quoted) = chars.next() {
+                    output.push(quoted);
+                    if quoted == ch {
+                        break;
+                    }
+                    if quoted == '\\' {
+                        output.extend(chars.next());
+                    }
+                }
+            }
+            _ if ch == from => output.push(to),
+            _ => output.push(ch),
+        }
+    }
+    output
+}
+
 fn main() -> io::Result<()> {
+    let (from, to) = match std::env::args().nth(1).as_deref() {
+        None | Some("--to-unicode") => ('\\', 'λ'),
+        Some("--to-ascii") => ('λ', '\\'),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Some(other) => {
+            eprintln!("unknown option {}\n\n{}", other, USAGE);
+            std::process::exit(2);
+        }
+    };
     let mut buffer = String::new();
     io::stdin().read_to_string(&mut buffer)?;
-    let result = buffer
-        .chars()
-        .map(|ch| if ch == '\\' { 'λ' } else { ch })
-        .collect::<String>();
-    io::stdout().write(result.as_bytes())?;
+    io::stdout().write_all(transliterate(&buffer, from, to).as_bytes())?;
     Ok(())
 }
@@ -0,0 +1,204 @@
+use crate::{DBIndices, DBTerm};
+
+#[derive(Debug)]
+pub enum BlcError {
+    PrematureEnd,
+    InvalidBit(char, usize),
+    IndexOutOfScope {
+        index: usize,
+        abstraction_depth: usize,
+    },
+    TrailingBits(usize),
+}
+
+// Binary lambda calculus: `00 M` is an abstraction, `01 M N` an application
+// and `1^n 0` the variable with de Bruijn index n.
+struct Decoder<I: Iterator<Item = bool>> {
+    bits: I,
+    position: usize,
+}
+
+impl<I: Iterator<Item = bool>> Decoder<I> {
+    fn bit(&mut self) -> Result<bool, BlcError> {
+        let bit = self.bits.next().ok_or(BlcError::PrematureEnd)?;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    fn term(&mut self, abstraction_depth: usize) -> Result<DBTerm, BlcError> {
+        if self.bit()? {
+            let mut index = 1;
+            while self.bit()? {
+                index += 1;
+            }
+            if index > abstraction_depth {
+                Err(BlcError::IndexOutOfScope {
+                    index,
+                    abstraction_depth,
+                })
+            } else {
+                Ok(DBTerm::Variable(index))
+            }
+        } else if self.bit()? {
+            Ok(DBTerm::Application {
+                function: Box::new(self.term(abstraction_depth)?),
+                argument: Box::new(self.term(abstraction_depth)?),
+            })
+        } else {
+            Ok(DBTerm::Abstraction(Box::new(
+                self.term(abstraction_depth + 1)?,
+            )))
+        }
+    }
+}
+
+// Reads a string of `0`s and `1`s, ignoring whitespace, which must hold
+// exactly one closed term.
+pub fn decode_bits(code: &str) -> Result<DBIndices, BlcError> {
+    let mut invalid = None;
+    let bits = code
+        .char_indices()
+        .filter(|(_, ch)| !ch.is_whitespace())
+        .map_while(|(i, ch)| match ch {
+            '0' => Some(false),
+            '1' => Some(true),
+            _ => {
+                invalid = Some(BlcError::InvalidBit(ch, i));
+                None
+            }
+        });
+    let mut decoder = Decoder { bits, position: 0 };
+    let result = decoder.term(0);
+    let trailing = decoder.position;
+    let extra = decoder.bits.next().is_some();
+    if let Some(err) = invalid {
+        return Err(err);
+    }
+    let term = result?;
+    if extra {
+        Err(BlcError::TrailingBits(trailing))
+    } else {
+        Ok(DBIndices(term))
+    }
+}
+
+// Reads a term from the start of packed bytes, most significant bit first,
+// as in Tromp's byte-oriented BLC. Whatever follows the term (usually the
+// program's input) is left alone; the number of bits consumed is returned
+// alongside the term.
+pub fn decode_bytes(bytes: &[u8]) -> Result<(DBIndices, usize), BlcError> {
+    let bits = bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |shift| byte >> shift & 1 == 1));
+    let mut decoder = Decoder { bits, position: 0 };
+    let term = decoder.term(0)?;
+    Ok((DBIndices(term), decoder.position))
+}
+
+// Writes a term as the bits decode_bits reads, or None if it has a free
+// variable, which BLC has no way to write.
+pub fn encode_bits(term: &DBIndices) -> Option<String> {
+    fn encode_mut(term: &DBTerm, bits: &mut String) -> Option<()> {
+        match term {
+            DBTerm::Abstraction(return_term) => {
+                bits.push_str("00");
+                encode_mut(return_term, bits)
+            }
+            DBTerm::Application { function, argument } => {
+                bits.push_str("01");
+                encode_mut(function, bits)?;
+                encode_mut(argument, bits)
+            }
+            DBTerm::Variable(index) => {
+                bits.extend(std::iter::repeat_n('1', *index));
+                bits.push('0');
+                Some(())
+            }
+            DBTerm::FreeVariable(_) => None,
+        }
+    }
+    let mut bits = String::new();
+    encode_mut(&term.0, &mut bits)?;
+    Some(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tromp's encodings of I, K, S, and the Church numeral 2.
+    const KNOWN: [(&str, &str); 4] = [
+        ("0010", "λ 1"),
+        ("0000110", "λ λ 2"),
+        ("00000001011110100111010", "λ λ λ 3 1 (2 1)"),
+        ("0000011100111010", "λ λ 2 (2 1)"),
+    ];
+
+    #[test]
+    fn decodes_known_encodings() {
+        for (bits, expected) in KNOWN {
+            let expected: DBIndices = expected.parse().unwrap();
+            assert_eq!(decode_bits(bits).unwrap(), expected, "{}", bits);
+            // Whitespace is ignored, so encodings can be laid out readably.
+            let spaced: String = bits.chars().flat_map(|bit| [bit, ' ']).collect();
+            assert_eq!(decode_bits(&spaced).unwrap(), expected);
+            assert_eq!(encode_bits(&expected).unwrap(), bits);
+        }
+    }
+
+    #[test]
+    fn round_trips_closed_terms() {
+        for term in crate::generate::closed_terms(7) {
+            let bits = encode_bits(&term).unwrap();
+            assert_eq!(decode_bits(&bits).unwrap(), term, "{}", bits);
+        }
+        assert_eq!(encode_bits(&"λ 1 x".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn decodes_bytes_and_leaves_the_rest() {
+        // K is 0000110 and then 1 followed by another byte of input.
+        let (term, used) = decode_bytes(&[0b0000_1101, 0xff]).unwrap();
+        assert_eq!(term, "λ λ 2".parse().unwrap());
+        assert_eq!(used, 7);
+        let (term, used) = decode_bytes(&[0b0010_0000]).unwrap();
+        assert_eq!(term, "λ 1".parse().unwrap());
+        assert_eq!(used, 4);
+    }
+
+    #[test]
+    fn reports_each_error() {
+        assert!(matches!(
+            decode_bits("0012"),
+            Err(BlcError::InvalidBit('2', 3))
+        ));
+        assert!(matches!(
+            decode_bits("0 0 x"),
+            Err(BlcError::InvalidBit('x', 4))
+        ));
+        assert!(matches!(
+            decode_bits("00100"),
+            Err(BlcError::TrailingBits(4))
+        ));
+        assert!(matches!(
+            decode_bits("0011 0"),
+            Err(BlcError::IndexOutOfScope {
+                index: 2,
+                abstraction_depth: 1
+            })
+        ));
+        assert!(matches!(
+            decode_bits("10"),
+            Err(BlcError::IndexOutOfScope {
+                index: 1,
+                abstraction_depth: 0
+            })
+        ));
+        assert!(matches!(decode_bits("0001"), Err(BlcError::PrematureEnd)));
+        assert!(matches!(decode_bits(""), Err(BlcError::PrematureEnd)));
+        assert!(matches!(
+            decode_bytes(&[0b0000_0001]),
+            Err(BlcError::PrematureEnd)
+        ));
+    }
+}
@@ -0,0 +1,591 @@
+use crate::encodings::{church_false, church_true};
+use crate::names::{Decoration, NameSupply};
+use crate::rewrite::{Beta, Eta, RewriteError, Rewriter, Strategy};
+use crate::small::SmallName;
+use crate::{app, apps, lam, var, LambdaTerm};
+use std::collections::HashSet;
+use std::fmt;
+
+// Head reduction steps allowed per node before giving the node up as ⊥.
+pub const DEFAULT_LIMIT: usize = 1000;
+
+// The Böhm tree of a term, cut off at some depth. A term with a head normal
+// form λx₁ … xₙ. y M₁ … Mₖ is a node with those binders and head, and the
+// trees of M₁ … Mₖ as its children; a term without one is ⊥. So Ω and Y I
+// are both ⊥, while Y f, which has no normal form either, is the infinite
+// tree f (f (f …)).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BohmTree {
+    // No head normal form was found within the step limit.
+    Bottom,
+    // Past the depth the tree was computed to.
+    Unexplored,
+    Node {
+        binders: Vec<SmallName>,
+        head: SmallName,
+        arguments: Vec<BohmTree>,
+    },
+}
+
+// Contracts the head redex, if there is one.
+fn head_step(term: &LambdaTerm) -> Option<LambdaTerm> {
+    match term {
+        LambdaTerm::Variable(_) => None,
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => head_step(return_term).map(|body| lam(bound_variable, body)),
+        LambdaTerm::Application { function, argument } => match &**function {
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => Some(return_term.substitute(bound_variable, argument)),
+            _ => head_step(function).map(|function| app(function, (**argument).clone())),
+        },
+    }
+}
+
+// The head reduction sequence of a term: the term itself, then each term
+// reached by contracting the head redex, ending at a head normal form if
+// there is one and going on forever for an unsolvable term such as Ω.
+pub struct HeadReductions {
+    // The term to yield next, already reduced by one more step.
+    next: Option<LambdaTerm>,
+}
+
+impl Iterator for HeadReductions {
+    type Item = LambdaTerm;
+
+    fn next(&mut self) -> Option<LambdaTerm> {
+        let term = self.next.take()?;
+        self.next = head_step(&term);
+        Some(term)
+    }
+}
+
+impl std::iter::FusedIterator for HeadReductions {}
+
+// Head reduces `term` until it has no head redex, or gives None if that
+// takes more than `limit` steps.
+pub fn head_normal_form(term: &LambdaTerm, limit: usize) -> Option<LambdaTerm> {
+    let mut reductions = term.head_reductions();
+    for _ in 0..=limit {
+        let term = reductions.next()?;
+        if reductions.next.is_none() {
+            return Some(term);
+        }
+    }
+    None
+}
+
+impl LambdaTerm {
+    pub fn head_reductions(&self) -> HeadReductions {
+        HeadReductions {
+            next: Some(self.clone()),
+        }
+    }
+
+    pub fn is_head_normal(&self) -> bool {
+        head_step(self).is_none()
+    }
+
+    // A term is solvable when it has a head normal form, which is what the
+    // Böhm tree shows as anything but ⊥. Solvability is undecidable, so this
+    // gives up after `fuel` head reduction steps and says no.
+    pub fn is_solvable(&self, fuel: usize) -> bool {
+        head_normal_form(self, fuel).is_some()
+    }
+
+    pub fn bohm_tree(&self, depth: usize) -> BohmTree {
+        self.bohm_tree_with_limit(depth, DEFAULT_LIMIT)
+    }
+
+    // The tree down to `depth` levels of nodes, each found with at most
+    // `limit` head reduction steps. Only the parts of the term the tree
+    // reaches are ever reduced.
+    pub fn bohm_tree_with_limit(&self, depth: usize, limit: usize) -> BohmTree {
+        if depth == 0 {
+            return BohmTree::Unexplored;
+        }
+        let term = match head_normal_form(self, limit) {
+            Some(term) => term,
+            None => return BohmTree::Bottom,
+        };
+        let mut binders = Vec::new();
+        let mut body = &term;
+        while let LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } = body
+        {
+            binders.push(bound_variable.clone());
+            body = return_term;
+        }
+        let mut arguments = Vec::new();
+        while let LambdaTerm::Application { function, argument } = body {
+            arguments.push(argument.bohm_tree_with_limit(depth - 1, limit));
+            body = function;
+        }
+        arguments.reverse();
+        match body {
+            LambdaTerm::Variable(head) => BohmTree::Node {
+                binders,
+                head: head.clone(),
+                arguments,
+            },
+            _ => unreachable!("a head normal form has a variable at its head"),
+        }
+    }
+}
+
+impl BohmTree {
+    // Whether the tree is all there, with no ⊥ and nothing cut off, in
+    // which case it's the normal form of the term.
+    pub fn is_complete(&self) -> bool {
+        match self {
+            BohmTree::Bottom | BohmTree::Unexplored => false,
+            BohmTree::Node { arguments, .. } => arguments.iter().all(BohmTree::is_complete),
+        }
+    }
+
+    // The term the tree stands for, if it is complete.
+    pub fn to_term(&self) -> Option<LambdaTerm> {
+        match self {
+            BohmTree::Bottom | BohmTree::Unexplored => None,
+            BohmTree::Node {
+                binders,
+                head,
+                arguments,
+            } => {
+                let mut body = LambdaTerm::Variable(head.clone());
+                for argument in arguments {
+                    body = app(body, argument.to_term()?);
+                }
+                Some(binders.iter().rev().fold(body, |body, id| lam(id, body)))
+            }
+        }
+    }
+}
+
+// Like a term, with ⊥ for Bottom and … for the parts past the depth limit:
+// `λf. f (f (f …))`.
+impl fmt::Display for BohmTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BohmTree::Bottom => write!(f, "⊥"),
+            BohmTree::Unexplored => write!(f, "…"),
+            BohmTree::Node {
+                binders,
+                head,
+                arguments,
+            } => {
+                for id in binders {
+                    write!(f, "λ{}. ", id)?;
+                }
+                write!(f, "{}", head)?;
+                for argument in arguments {
+                    match argument {
+                        BohmTree::Node {
+                            binders, arguments, ..
+                        } if !binders.is_empty() || !arguments.is_empty() => {
+                            write!(f, " ({})", argument)?
+                        }
+                        _ => write!(f, " {}", argument)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SeparateError {
+    // The terms are βη-equal, so no context tells them apart.
+    Equivalent,
+    // A term had no βη-normal form within the step limit.
+    Rewrite(RewriteError),
+}
+
+impl From<RewriteError> for SeparateError {
+    fn from(err: RewriteError) -> SeparateError {
+        SeparateError::Rewrite(err)
+    }
+}
+
+// A context `(λv₁ … vₙ. □) S₁ … Sₙ A₁ … Aₘ`: it closes off the free variables
+// vᵢ of the terms it's for by substituting Sᵢ, then applies the result to
+// the Aᵢ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Separator {
+    pub substitution: Vec<(SmallName, LambdaTerm)>,
+    pub arguments: Vec<LambdaTerm>,
+}
+
+impl Separator {
+    // Plugs `term` into the hole, without reducing anything.
+    pub fn apply(&self, term: &LambdaTerm) -> LambdaTerm {
+        let closed = self
+            .substitution
+            .iter()
+            .rev()
+            .fold(term.clone(), |body, (id, _)| lam(id, body));
+        let substituted = apps(
+            closed,
+            self.substitution.iter().map(|(_, value)| value.clone()),
+        );
+        apps(substituted, self.arguments.iter().cloned())
+    }
+
+    fn substitute(
+        &mut self,
+        variable: &str,
+        replacement: &LambdaTerm,
+        original: &HashSet<SmallName>,
+    ) {
+        for argument in &mut self.arguments {
+            *argument = argument.substitute(variable, replacement);
+        }
+        for (_, value) in &mut self.substitution {
+            *value = value.substitute(variable, replacement);
+        }
+        if original.contains(variable) {
+            self.substitution
+                .push((SmallName::from(variable), replacement.clone()));
+        }
+    }
+}
+
+impl fmt::Display for Separator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arguments = self
+            .substitution
+            .iter()
+            .map(|(_, value)| value)
+            .chain(&self.arguments);
+        if self.substitution.is_empty() {
+            write!(f, "□")?;
+        } else {
+            write!(f, "(")?;
+            for (id, _) in &self.substitution {
+                write!(f, "λ{}. ", id)?;
+            }
+            write!(f, "□)")?;
+        }
+        for argument in arguments {
+            match argument {
+                LambdaTerm::Variable(_) => write!(f, " {}", argument)?,
+                _ => write!(f, " ({})", argument)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// λa₁ … aₙ. body, with fresh names for the aᵢ.
+fn discard(n: usize, body: LambdaTerm, names: &mut NameSupply) -> LambdaTerm {
+    let binders: Vec<SmallName> = (0..n).map(|_| names.fresh("a")).collect();
+    binders.iter().rev().fold(body, |body, id| lam(id, body))
+}
+
+// λa₁ … aₙ. aᵢ, counting from 1.
+fn selector(i: usize, n: usize, names: &mut NameSupply) -> LambdaTerm {
+    let binders: Vec<SmallName> = (0..n).map(|_| names.fresh("a")).collect();
+    let body = var(&binders[i - 1]);
+    binders.iter().rev().fold(body, |body, id| lam(id, body))
+}
+
+// λa₁ … aₙ. λz. z a₁ … aₙ, which holds on to its arguments until it's given
+// a selector.
+fn permutator(n: usize, names: &mut NameSupply) -> LambdaTerm {
+    let binders: Vec<SmallName> = (0..n).map(|_| names.fresh("a")).collect();
+    let z = names.fresh("z");
+    let body = lam(&z, apps(var(&z), binders.iter().map(var)));
+    binders.iter().rev().fold(body, |body, id| lam(id, body))
+}
+
+fn spine(term: &LambdaTerm) -> (&LambdaTerm, Vec<&LambdaTerm>) {
+    let mut arguments = Vec::new();
+    let mut term = term;
+    while let LambdaTerm::Application { function, argument } = term {
+        arguments.push(&**argument);
+        term = function;
+    }
+    arguments.reverse();
+    (term, arguments)
+}
+
+// The longest run of nested abstractions anywhere in `term`.
+fn max_binders(term: &LambdaTerm) -> usize {
+    match term {
+        LambdaTerm::Variable(_) => 0,
+        LambdaTerm::Abstraction { return_term, .. } => {
+            let inner = max_binders(return_term);
+            inner.max(1 + binder_count(return_term))
+        }
+        LambdaTerm::Application { function, argument } => {
+            max_binders(function).max(max_binders(argument))
+        }
+    }
+}
+
+fn binder_count(term: &LambdaTerm) -> usize {
+    match term {
+        LambdaTerm::Abstraction { return_term, .. } => 1 + binder_count(return_term),
+        _ => 0,
+    }
+}
+
+// The most arguments any free occurrence of `id` in `term` is applied to.
+fn max_arity(term: &LambdaTerm, id: &str) -> usize {
+    match term {
+        LambdaTerm::Variable(_) => 0,
+        LambdaTerm::Abstraction { bound_variable, .. } if bound_variable == id => 0,
+        LambdaTerm::Abstraction { return_term, .. } => max_arity(return_term, id),
+        LambdaTerm::Application { .. } => {
+            let (head, arguments) = spine(term);
+            let own = match head {
+                LambdaTerm::Variable(head) if head == id => arguments.len(),
+                _ => max_arity(head, id),
+            };
+            arguments
+                .iter()
+                .map(|argument| max_arity(argument, id))
+                .fold(own, usize::max)
+        }
+    }
+}
+
+// Böhm's theorem: two terms with βη-normal forms that aren't βη-equal can be
+// told apart by a context, which sends the first to λx. λy. x and the second
+// to λx. λy. y. The context is found by Böhm-out, walking down both terms'
+// normal forms in step until they differ:
+//
+// - give both terms the same number of leading abstractions by applying
+//   them to fresh variables;
+// - where the heads differ, replace one head with a function giving true
+//   and the other with one giving false, whatever their arguments;
+// - where the head is the same but takes a different number of arguments,
+//   replace it with a selector that, after a few more arguments, leaves
+//   true for one term and false for the other;
+// - otherwise, for the first argument that differs, replace the head with
+//   a permutator λa₁ … aᵣ. λz. z a₁ … aᵣ and pass it a selector for that
+//   argument. Since r is larger than the number of arguments the head is
+//   given anywhere, the other occurrences of the head inside the arguments
+//   keep them apart.
+//
+// `limit` caps the steps spent normalizing at each stage.
+pub fn separate(
+    first: &LambdaTerm,
+    second: &LambdaTerm,
+    limit: usize,
+) -> Result<Separator, SeparateError> {
+    let rewriter = Rewriter::new(Strategy::TopDown, limit).rule(Beta).rule(Eta);
+    let mut original = first.free_variables();
+    original.extend(second.free_variables());
+    let mut names = NameSupply::with_decoration(
+        original
+            .iter()
+            .cloned()
+            .chain(first.bound_variables())
+            .chain(second.bound_variables()),
+        Decoration::Subscripts,
+    );
+    let mut context = Separator {
+        substitution: Vec::new(),
+        arguments: Vec::new(),
+    };
+    if rewriter
+        .normalize(first)?
+        .alpha_equivalent(&rewriter.normalize(second)?)
+    {
+        return Err(SeparateError::Equivalent);
+    }
+    let mut r = 0;
+    loop {
+        let left = rewriter.normalize(&context.apply(first))?;
+        let right = rewriter.normalize(&context.apply(second))?;
+        let missing = binder_count(&left).max(binder_count(&right));
+        if missing > 0 {
+            for _ in 0..missing {
+                context.arguments.push(var(names.fresh("x")));
+            }
+            continue;
+        }
+        let (left_head, left_arguments) = spine(&left);
+        let (right_head, right_arguments) = spine(&right);
+        let (left_head, right_head) = match (left_head, right_head) {
+            (LambdaTerm::Variable(left_head), LambdaTerm::Variable(right_head)) => {
+                (left_head.clone(), right_head.clone())
+            }
+            _ => unreachable!("a normal form without leading abstractions has a variable head"),
+        };
+        let (k, l) = (left_arguments.len(), right_arguments.len());
+        if left_head != right_head {
+            let to_true = discard(k, church_true(), &mut names);
+            let to_false = discard(l, church_false(), &mut names);
+            context.substitute(&left_head, &to_true, &original);
+            context.substitute(&right_head, &to_false, &original);
+            break;
+        }
+        if k != l {
+            // With h := λu₁ … uₙ₊₁. uₙ₊₁, where n is the larger argument
+            // count and d the difference, the term with fewer arguments
+            // reduces to the last of d + 1 further arguments and the one
+            // with more to the first applied to the rest.
+            let (short, long) = (k.min(l), k.max(l));
+            let d = long - short;
+            let last = selector(long + 1, long + 1, &mut names);
+            context.substitute(&left_head, &last, &original);
+            context
+                .arguments
+                .push(discard(d, church_false(), &mut names));
+            for _ in 1..d {
+                context.arguments.push(var(names.fresh("x")));
+            }
+            context.arguments.push(church_true());
+            if k > l {
+                // The first term came out false; swap the outcomes.
+                context.arguments.push(church_false());
+                context.arguments.push(church_true());
+            }
+            break;
+        }
+        let i = (0..k)
+            .find(|&i| !left_arguments[i].alpha_equivalent(right_arguments[i]))
+            .expect("normal forms that differ differ somewhere");
+        // Each permutator also has more binders than any abstraction around,
+        // previous permutators included, so it can't be mistaken for a
+        // subterm it's compared against further down.
+        r = 1 + [
+            r,
+            max_arity(&left, &left_head),
+            max_arity(&right, &left_head),
+            max_binders(&left),
+            max_binders(&right),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(0);
+        let tuple = permutator(r, &mut names);
+        context.substitute(&left_head, &tuple, &original);
+        for _ in k..r {
+            context.arguments.push(var(names.fresh("x")));
+        }
+        context.arguments.push(selector(i + 1, r, &mut names));
+    }
+    // Whatever is still free can be anything at all.
+    let mut rest: HashSet<SmallName> = context
+        .arguments
+        .iter()
+        .chain(context.substitution.iter().map(|(_, value)| value))
+        .flat_map(LambdaTerm::free_variables)
+        .collect();
+    rest.extend(
+        original
+            .iter()
+            .filter(|id| !context.substitution.iter().any(|(v, _)| v == *id))
+            .cloned(),
+    );
+    let mut rest: Vec<SmallName> = rest.into_iter().collect();
+    rest.sort();
+    let identity = lam("a", var("a"));
+    for id in rest {
+        context.substitute(&id, &identity, &original);
+    }
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{closed_terms, gen_closed_term, RandomSource, SplitMix64};
+    use crate::rewrite::Eta;
+    use crate::{parse, DBIndices, NameScheme};
+
+    // Checks that `separator` sends `first` to true and `second` to false.
+    fn check(first: &LambdaTerm, second: &LambdaTerm, separator: &Separator) {
+        let rewriter = Rewriter::new(Strategy::TopDown, 10_000)
+            .rule(Beta)
+            .rule(Eta);
+        let truth = parse("λx. λy. x").unwrap();
+        let falsity = parse("λx. λy. y").unwrap();
+        let left = rewriter.normalize(&separator.apply(first)).unwrap();
+        let right = rewriter.normalize(&separator.apply(second)).unwrap();
+        assert!(
+            left.alpha_equivalent(&truth),
+            "{} / {}: {}",
+            first,
+            second,
+            separator
+        );
+        assert!(
+            right.alpha_equivalent(&falsity),
+            "{} / {}: {}",
+            first,
+            second,
+            separator
+        );
+    }
+
+    #[test]
+    fn separates_every_pair_of_small_normal_forms() {
+        let normal_forms: Vec<LambdaTerm> = closed_terms(7)
+            .map(|indices| indices.to_named(NameScheme::Letters))
+            .filter(|term| {
+                let rewriter = Rewriter::new(Strategy::TopDown, 1).rule(Beta).rule(Eta);
+                rewriter.step(term).is_none()
+            })
+            .collect();
+        assert_eq!(normal_forms.len(), 106);
+        for (i, first) in normal_forms.iter().enumerate() {
+            for second in &normal_forms[i + 1..] {
+                check(first, second, &separate(first, second, 1000).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn separates_random_pairs() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 200).rule(Beta).rule(Eta);
+        let mut rng = SplitMix64(375);
+        let mut separated = 0;
+        while separated < 300 {
+            let first = gen_closed_term(2 + rng.below(12), &mut rng);
+            let second = gen_closed_term(2 + rng.below(12), &mut rng);
+            let (Ok(first_normal), Ok(second_normal)) =
+                (rewriter.normalize(&first), rewriter.normalize(&second))
+            else {
+                continue;
+            };
+            if DBIndices::from(first_normal) == DBIndices::from(second_normal) {
+                assert!(matches!(
+                    separate(&first, &second, 200),
+                    Err(SeparateError::Equivalent)
+                ));
+            } else {
+                check(&first, &second, &separate(&first, &second, 200).unwrap());
+                separated += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn separates_open_terms_and_spots_equivalent_ones() {
+        for (first, second) in [
+            ("x", "y"),
+            ("x a", "x b"),
+            ("x a", "x a b"),
+            ("λz. x z z", "x"),
+        ] {
+            let (first, second) = (parse(first).unwrap(), parse(second).unwrap());
+            check(&first, &second, &separate(&first, &second, 1000).unwrap());
+        }
+        let first = parse("λx. f x").unwrap();
+        let second = parse("(λy. y) f").unwrap();
+        assert!(matches!(
+            separate(&first, &second, 1000),
+            Err(SeparateError::Equivalent)
+        ));
+    }
+}
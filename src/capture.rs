@@ -0,0 +1,229 @@
+use crate::{app, apps, lam, var, LambdaTerm};
+
+// Each case asks for `term[variable := replacement]`, with `expected` worked
+// out by construction rather than by running the crate's own substitution,
+// so the suite can be used to check `LambdaTerm::substitute` too.
+#[derive(Debug, Clone)]
+pub struct CaptureCase {
+    pub name: String,
+    pub term: LambdaTerm,
+    pub variable: String,
+    pub replacement: LambdaTerm,
+    pub expected: LambdaTerm,
+}
+
+impl CaptureCase {
+    pub fn check<F>(&self, substitute: F) -> bool
+    where
+        F: FnOnce(&LambdaTerm, &str, &LambdaTerm) -> LambdaTerm,
+    {
+        substitute(&self.term, &self.variable, &self.replacement).alpha_equivalent(&self.expected)
+    }
+}
+
+fn lams(bound_variables: &[String], return_term: LambdaTerm) -> LambdaTerm {
+    bound_variables
+        .iter()
+        .rev()
+        .fold(return_term, |body, bound_variable| {
+            lam(bound_variable, body)
+        })
+}
+
+fn names(prefix: &str, n: usize) -> Vec<String> {
+    (1..=n).map(|i| format!("{}{}", prefix, i)).collect()
+}
+
+fn vars(names: &[String]) -> impl Iterator<Item = LambdaTerm> + '_ {
+    names.iter().map(var)
+}
+
+// `λx. ... λx. x` with n binders: the substitution must stop at the first.
+fn shadowed(n: usize) -> CaptureCase {
+    let term = (0..n).fold(var("x"), |body, _| lam("x", body));
+    CaptureCase {
+        name: format!("shadowed/{}", n),
+        expected: term.clone(),
+        term,
+        variable: String::from("x"),
+        replacement: var("z"),
+    }
+}
+
+// `λy1. ... λyn. x` with `x := y1 ... yn`: every binder has to be renamed.
+fn binders_capture_replacement(n: usize) -> CaptureCase {
+    let ys = names("y", n);
+    let fresh = names("fresh", n);
+    let replacement = apps(var(&ys[0]), vars(&ys[1..]));
+    CaptureCase {
+        name: format!("binders_capture_replacement/{}", n),
+        term: lams(&ys, var("x")),
+        variable: String::from("x"),
+        expected: lams(&fresh, replacement.clone()),
+        replacement,
+    }
+}
+
+// `λy1. ... λyn. x y1 ... yn` with `x := y1 ... yn`: the renamed binders are
+// still used in the body and must keep referring to their own abstraction.
+fn renamed_binders_still_used(n: usize) -> CaptureCase {
+    let ys = names("y", n);
+    let fresh = names("fresh", n);
+    let replacement = apps(var(&ys[0]), vars(&ys[1..]));
+    CaptureCase {
+        name: format!("renamed_binders_still_used/{}", n),
+        term: lams(&ys, apps(var("x"), vars(&ys))),
+        variable: String::from("x"),
+        expected: lams(&fresh, apps(replacement.clone(), vars(&fresh))),
+        replacement,
+    }
+}
+
+// `λy. x y y' y'' ...` with `x := y`: renaming `y` to the obvious `y'` would
+// capture the free `y'` (and so on for every prime count up to n).
+fn primed_names_taken(n: usize) -> CaptureCase {
+    let primed: Vec<String> = (1..=n).map(|i| format!("y{}", "'".repeat(i))).collect();
+    CaptureCase {
+        name: format!("primed_names_taken/{}", n),
+        term: lam(
+            "y",
+            apps(var("x"), Some(var("y")).into_iter().chain(vars(&primed))),
+        ),
+        variable: String::from("x"),
+        replacement: var("y"),
+        expected: lam(
+            "fresh",
+            apps(
+                var("y"),
+                Some(var("fresh")).into_iter().chain(vars(&primed)),
+            ),
+        ),
+    }
+}
+
+// `(λx. x) x` with `x := y`: only the free occurrence changes.
+fn free_and_bound_same_name(n: usize) -> CaptureCase {
+    let term = (0..n).fold(var("x"), |argument, _| app(lam("x", var("x")), argument));
+    let expected = (0..n).fold(var("y"), |argument, _| app(lam("x", var("x")), argument));
+    CaptureCase {
+        name: format!("free_and_bound_same_name/{}", n),
+        term,
+        variable: String::from("x"),
+        replacement: var("y"),
+        expected,
+    }
+}
+
+// `λy. λz. ... (z x)` with `x := y z`: nested binders match names free in the
+// replacement at different depths.
+fn nested_capture(n: usize) -> CaptureCase {
+    let replacement = app(var("y"), var("z"));
+    let binders = ["y", "z"];
+    let term = (0..n).fold(app(var("z"), var("x")), |body, i| lam(binders[i % 2], body));
+    let fresh = names("fresh", n);
+    let body = match (0..n).find(|i| binders[i % 2] == "z") {
+        Some(i) => app(var(&fresh[i]), replacement.clone()),
+        None => app(var("z"), replacement.clone()),
+    };
+    let expected = (0..n).fold(body, |body, i| lam(&fresh[i], body));
+    CaptureCase {
+        name: format!("nested_capture/{}", n),
+        term,
+        variable: String::from("x"),
+        replacement,
+        expected,
+    }
+}
+
+pub fn capture_cases(max_size: usize) -> Vec<CaptureCase> {
+    let families: [fn(usize) -> CaptureCase; 6] = [
+        shadowed,
+        binders_capture_replacement,
+        renamed_binders_still_used,
+        primed_names_taken,
+        free_and_bound_same_name,
+        nested_capture,
+    ];
+    (1..=max_size)
+        .flat_map(|n| families.iter().map(move |family| family(n)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotated::AnnotatedTerm;
+    use crate::shared::SharedTerm;
+
+    #[test]
+    fn term_substitution_avoids_capture() {
+        for case in capture_cases(6) {
+            assert!(
+                case.check(|term, variable, replacement| term.substitute(variable, replacement)),
+                "{}",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn shared_substitution_avoids_capture() {
+        for case in capture_cases(6) {
+            assert!(
+                case.check(|term, variable, replacement| {
+                    let result =
+                        SharedTerm::from(term).substitute(variable, &SharedTerm::from(replacement));
+                    LambdaTerm::from(&result)
+                }),
+                "{}",
+                case.name
+            );
+        }
+    }
+
+    #[test]
+    fn annotated_substitution_avoids_capture() {
+        for case in capture_cases(6) {
+            assert!(
+                case.check(|term, variable, replacement| {
+                    let result = AnnotatedTerm::from(term)
+                        .substitute(variable, &AnnotatedTerm::from(replacement));
+                    LambdaTerm::from(&result)
+                }),
+                "{}",
+                case.name
+            );
+        }
+    }
+
+    // Substitution that never renames a binder, to show the suite notices.
+    fn naive(term: &LambdaTerm, variable: &str, replacement: &LambdaTerm) -> LambdaTerm {
+        match term {
+            LambdaTerm::Variable(id) if id == variable => replacement.clone(),
+            LambdaTerm::Variable(_) => term.clone(),
+            LambdaTerm::Application { function, argument } => app(
+                naive(function, variable, replacement),
+                naive(argument, variable, replacement),
+            ),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } if bound_variable != variable => {
+                lam(bound_variable, naive(return_term, variable, replacement))
+            }
+            LambdaTerm::Abstraction { .. } => term.clone(),
+        }
+    }
+
+    #[test]
+    fn catches_a_capturing_substitution() {
+        let failures: Vec<String> = capture_cases(3)
+            .into_iter()
+            .filter(|case| !case.check(naive))
+            .map(|case| case.name)
+            .collect();
+        assert!(failures.contains(&String::from("binders_capture_replacement/1")));
+        assert!(failures.contains(&String::from("primed_names_taken/1")));
+        assert!(!failures.iter().any(|name| name.starts_with("shadowed/")));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::anf::{self, Anf};
+use crate::small::SmallName;
+use crate::{app, apps, lam, var, LambdaTerm};
+use std::fmt;
+
+// The output of closure conversion: ANF without nested functions. Each λ
+// becomes a closed piece of code in Program::functions, and where it stood
+// a closure is built from that code and the variables it captures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Variable(SmallName),
+    // Pairs functions[code] with the current values of `captured`.
+    Closure {
+        code: usize,
+        captured: Vec<SmallName>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Return(Value),
+    // Calls the closure `function` on `argument`.
+    Let {
+        name: SmallName,
+        function: Value,
+        argument: Value,
+        body: Box<Expr>,
+    },
+    TailCall {
+        function: Value,
+        argument: Value,
+    },
+}
+
+// A closed function: its body mentions nothing but its parameter and the
+// variables in its environment, which a closure supplies in this order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Code {
+    pub environment: Vec<SmallName>,
+    pub parameter: SmallName,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub functions: Vec<Code>,
+    pub main: Expr,
+}
+
+fn free_in_value(value: &anf::Value, bound: &mut Vec<SmallName>, free: &mut Vec<SmallName>) {
+    match value {
+        anf::Value::Variable(id) => {
+            if !bound.contains(id) && !free.contains(id) {
+                free.push(id.clone());
+            }
+        }
+        anf::Value::Lambda { parameter, body } => {
+            bound.push(parameter.clone());
+            free_in_anf(body, bound, free);
+            bound.pop();
+        }
+    }
+}
+
+// The free variables in order of first occurrence, so that environments
+// come out the same every time.
+fn free_in_anf(term: &Anf, bound: &mut Vec<SmallName>, free: &mut Vec<SmallName>) {
+    match term {
+        Anf::Return(value) => free_in_value(value, bound, free),
+        Anf::Let {
+            name,
+            function,
+            argument,
+            body,
+        } => {
+            free_in_value(function, bound, free);
+            free_in_value(argument, bound, free);
+            bound.push(name.clone());
+            free_in_anf(body, bound, free);
+            bound.pop();
+        }
+        Anf::TailCall { function, argument } => {
+            free_in_value(function, bound, free);
+            free_in_value(argument, bound, free);
+        }
+    }
+}
+
+struct Converter {
+    functions: Vec<Code>,
+}
+
+impl Converter {
+    fn value(&mut self, value: &anf::Value) -> Value {
+        match value {
+            anf::Value::Variable(id) => Value::Variable(id.clone()),
+            anf::Value::Lambda { parameter, body } => {
+                let mut captured = Vec::new();
+                free_in_value(value, &mut Vec::new(), &mut captured);
+                let body = self.expr(body);
+                self.functions.push(Code {
+                    environment: captured.clone(),
+                    parameter: parameter.clone(),
+                    body,
+                });
+                Value::Closure {
+                    code: self.functions.len() - 1,
+                    captured,
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, term: &Anf) -> Expr {
+        match term {
+            Anf::Return(value) => Expr::Return(self.value(value)),
+            Anf::Let {
+                name,
+                function,
+                argument,
+                body,
+            } => Expr::Let {
+                name: name.clone(),
+                function: self.value(function),
+                argument: self.value(argument),
+                body: Box::new(self.expr(body)),
+            },
+            Anf::TailCall { function, argument } => Expr::TailCall {
+                function: self.value(function),
+                argument: self.value(argument),
+            },
+        }
+    }
+}
+
+// Flat closure conversion: each closure copies just the variables its code
+// uses, innermost functions coming first in Program::functions. Variables
+// free in the whole program are captured like any other.
+pub fn convert(term: &Anf) -> Program {
+    let mut converter = Converter {
+        functions: Vec::new(),
+    };
+    let main = converter.expr(term);
+    Program {
+        functions: converter.functions,
+        main,
+    }
+}
+
+// ANF conversion followed by closure conversion.
+pub fn compile(term: &LambdaTerm) -> Program {
+    convert(&anf::to_anf(term))
+}
+
+impl Program {
+    // Back to a plain term, with each closure written as its code abstracted
+    // over the environment and applied to the captured variables. The result
+    // is β-equal to the term the program came from.
+    pub fn to_lambda(&self) -> LambdaTerm {
+        self.expr_to_lambda(&self.main)
+    }
+
+    fn value_to_lambda(&self, value: &Value) -> LambdaTerm {
+        match value {
+            Value::Variable(id) => var(id),
+            Value::Closure { code, captured } => {
+                let code = &self.functions[*code];
+                let function = lam(&code.parameter, self.expr_to_lambda(&code.body));
+                let closed = code
+                    .environment
+                    .iter()
+                    .rev()
+                    .fold(function, |body, id| lam(id, body));
+                apps(closed, captured.iter().map(var))
+            }
+        }
+    }
+
+    fn expr_to_lambda(&self, expr: &Expr) -> LambdaTerm {
+        match expr {
+            Expr::Return(value) => self.value_to_lambda(value),
+            Expr::Let {
+                name,
+                function,
+                argument,
+                body,
+            } => app(
+                lam(name, self.expr_to_lambda(body)),
+                app(
+                    self.value_to_lambda(function),
+                    self.value_to_lambda(argument),
+                ),
+            ),
+            Expr::TailCall { function, argument } => app(
+                self.value_to_lambda(function),
+                self.value_to_lambda(argument),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Variable(id) => f.write_str(id),
+            Value::Closure { code, captured } => {
+                write!(f, "closure(f{}", code)?;
+                for id in captured {
+                    write!(f, ", {}", id)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+impl Expr {
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Expr::Return(value) => write!(f, "{}", value),
+            Expr::Let {
+                name,
+                function,
+                argument,
+                body,
+            } => {
+                write!(
+                    f,
+                    "let {} = {} {} in\n{:indent$}",
+                    name,
+                    function,
+                    argument,
+                    "",
+                    indent = indent
+                )?;
+                body.fmt_indented(f, indent)
+            }
+            Expr::TailCall { function, argument } => write!(f, "{} {}", function, argument),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+//   f0 [y] x =
+//     y x
+//   main =
+//     closure(f0, y)
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, code) in self.functions.iter().enumerate() {
+            writeln!(
+                f,
+                "f{} [{}] {} =",
+                i,
+                code.environment.join(", "),
+                code.parameter
+            )?;
+            f.write_str("  ")?;
+            code.body.fmt_indented(f, 2)?;
+            writeln!(f)?;
+        }
+        f.write_str("main =\n  ")?;
+        self.main.fmt_indented(f, 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::parse;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+
+    #[test]
+    fn captures_free_variables() {
+        let program = compile(&parse("λx. λy. x y z").unwrap());
+        assert_eq!(program.functions.len(), 2);
+        let inner = program
+            .functions
+            .iter()
+            .find(|code| code.parameter == "y")
+            .unwrap();
+        assert_eq!(inner.environment, ["x", "z"]);
+    }
+
+    #[test]
+    fn compiles_to_closed_code_and_beta_equal_terms() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 500).rule(Beta);
+        let mut rng = SplitMix64(381);
+        let mut compared = 0;
+        for i in 0..400 {
+            let term = gen_closed_term(1 + i % 16, &mut rng);
+            let term = app(app(term, var("p")), lam("q", var("q")));
+            let program = compile(&term);
+            for code in 0..program.functions.len() {
+                let closure = Value::Closure {
+                    code,
+                    captured: Vec::new(),
+                };
+                let closed = program.value_to_lambda(&closure);
+                assert!(closed.free_variables().is_empty(), "{}", closed);
+            }
+            let back = program.to_lambda();
+            assert_eq!(back.free_variables(), term.free_variables());
+            let Ok(expected) = rewriter.normalize(&term) else {
+                continue;
+            };
+            let normal = rewriter.normalize(&back).unwrap();
+            assert!(normal.alpha_equivalent(&expected), "{}", term);
+            compared += 1;
+        }
+        assert!(compared > 300);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{lambda, LambdaTerm};
+
+pub fn s() -> LambdaTerm {
+    lambda!(|x, y, z| x z (y z))
+}
+
+pub fn k() -> LambdaTerm {
+    lambda!(|x, y| x)
+}
+
+pub fn i() -> LambdaTerm {
+    lambda!(|x| x)
+}
+
+pub fn b() -> LambdaTerm {
+    lambda!(|x, y, z| x (y z))
+}
+
+pub fn c() -> LambdaTerm {
+    lambda!(|x, y, z| x z y)
+}
+
+pub fn w() -> LambdaTerm {
+    lambda!(|x, y| x y y)
+}
+
+// ω, which applies its argument to itself.
+pub fn omega() -> LambdaTerm {
+    lambda!(|x| x x)
+}
+
+// Ω = ω ω, which reduces only to itself.
+pub fn big_omega() -> LambdaTerm {
+    lambda!((|x| x x) (|x| x x))
+}
+
+// Curry's fixed point combinator.
+pub fn y() -> LambdaTerm {
+    lambda!(|f| (|x| f (x x)) (|x| f (x x)))
+}
+
+// Turing's fixed point combinator, Θ = A A with A = λx. λy. y (x x y). Unlike
+// Y, Θ f reduces to f (Θ f) itself.
+pub fn theta() -> LambdaTerm {
+    lambda!((|x, y| y (x x y)) (|x, y| y (x x y)))
+}
+
+pub const NAMES: [&str; 10] = ["S", "K", "I", "B", "C", "W", "ω", "Ω", "Y", "Θ"];
+
+pub fn by_name(name: &str) -> Option<LambdaTerm> {
+    match name {
+        "S" => Some(s()),
+        "K" => Some(k()),
+        "I" => Some(i()),
+        "B" => Some(b()),
+        "C" => Some(c()),
+        "W" => Some(w()),
+        "ω" => Some(omega()),
+        "Ω" => Some(big_omega()),
+        "Y" => Some(y()),
+        "Θ" => Some(theta()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+    use crate::{app, apps, parse, var};
+
+    fn normalize(term: LambdaTerm) -> LambdaTerm {
+        Rewriter::new(Strategy::TopDown, 100)
+            .rule(Beta)
+            .normalize(&term)
+            .unwrap()
+    }
+
+    #[test]
+    fn every_name_gives_a_closed_term_that_reparses() {
+        for name in NAMES {
+            let term = by_name(name).unwrap();
+            assert!(term.free_variables().is_empty(), "{}", name);
+            assert_eq!(parse(&term.to_string()).unwrap(), term, "{}", name);
+        }
+        for name in ["", "s", "KI", "Z", "omega"] {
+            assert_eq!(by_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn combinators_reduce_as_their_definitions_say() {
+        let [f, g, x, y] = ["f", "g", "x", "y"].map(var);
+        let cases = [
+            (apps(s(), [k(), k(), x.clone()]), x.clone()),
+            (apps(k(), [x.clone(), y.clone()]), x.clone()),
+            (app(i(), x.clone()), x.clone()),
+            (
+                apps(b(), [f.clone(), g.clone(), x.clone()]),
+                app(f.clone(), app(g.clone(), x.clone())),
+            ),
+            (
+                apps(c(), [f.clone(), x.clone(), y.clone()]),
+                apps(f.clone(), [y.clone(), x.clone()]),
+            ),
+            (
+                apps(w(), [f.clone(), x.clone()]),
+                apps(f.clone(), [x.clone(), x.clone()]),
+            ),
+            (app(omega(), x.clone()), app(x.clone(), x.clone())),
+        ];
+        for (term, expected) in cases {
+            assert_eq!(normalize(term.clone()), expected, "{}", term);
+        }
+    }
+
+    #[test]
+    fn fixed_point_combinators_unfold() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 100).rule(Beta);
+        let step = |term: &LambdaTerm| rewriter.step(term).unwrap().term;
+        let f = var("f");
+        // Ω steps to itself.
+        assert_eq!(step(&big_omega()), big_omega());
+        // Θ f reduces to f (Θ f) in two steps.
+        let unfolded = step(&step(&app(theta(), f.clone())));
+        assert_eq!(unfolded, app(f.clone(), app(theta(), f.clone())));
+        // Y f and f (Y f) share a reduct, though neither reduces to the
+        // other.
+        let once = step(&app(y(), f.clone()));
+        let other = step(&app(f.clone(), app(y(), f.clone())));
+        assert!(step(&once).alpha_equivalent(&other));
+    }
+}
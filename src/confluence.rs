@@ -0,0 +1,97 @@
+use crate::{DBIndices, LambdaTerm};
+use std::collections::HashMap;
+
+impl LambdaTerm {
+    // The complete development: contracts every redex in the term at once,
+    // those created by doing so excepted. Any term that `self` reduces to
+    // in one β-step reduces to this one in a single parallel step, which is
+    // Takahashi's route to Church–Rosser.
+    pub fn develop(&self) -> LambdaTerm {
+        match self {
+            LambdaTerm::Variable(_) => self.clone(),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => LambdaTerm::Abstraction {
+                bound_variable: bound_variable.clone(),
+                return_term: Box::new(return_term.develop()),
+            },
+            LambdaTerm::Application { function, argument } => match &**function {
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => return_term
+                    .develop()
+                    .substitute(bound_variable, &argument.develop()),
+                _ => LambdaTerm::Application {
+                    function: Box::new(function.develop()),
+                    argument: Box::new(argument.develop()),
+                },
+            },
+        }
+    }
+}
+
+// A common reduct of two terms, and how each gets there: `left` starts at
+// the first term and `right` at the second, both end at `term`, and each
+// term in them parallel reduces to the next by a complete development.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Join {
+    pub term: LambdaTerm,
+    pub left: Vec<LambdaTerm>,
+    pub right: Vec<LambdaTerm>,
+}
+
+// Searches for a common reduct of `a` and `b`, as Church–Rosser promises
+// when both are reducts of one term. Each side is developed completely over
+// and over, up to `fuel` times, until a term on one side is α-equivalent to
+// a term on the other; a pair with a common normal form always meets this
+// way once both have reached it.
+pub fn join(a: &LambdaTerm, b: &LambdaTerm, fuel: usize) -> Option<Join> {
+    let mut left = vec![a.clone()];
+    let mut right = vec![b.clone()];
+    let mut left_seen = HashMap::from([(DBIndices::from(a.clone()), 0)]);
+    let mut right_seen = HashMap::from([(DBIndices::from(b.clone()), 0)]);
+    let meet = |left: &[LambdaTerm], right: &[LambdaTerm], i: usize, j: usize| {
+        Some(Join {
+            term: left[i].clone(),
+            left: left[..=i].to_vec(),
+            right: right[..=j].to_vec(),
+        })
+    };
+    if let Some(&j) = right_seen.get(&DBIndices::from(a.clone())) {
+        return meet(&left, &right, 0, j);
+    }
+    for _ in 0..fuel {
+        let next_left = left.last().expect("never empty").develop();
+        let next_right = right.last().expect("never empty").develop();
+        let (left_key, right_key) = (
+            DBIndices::from(next_left.clone()),
+            DBIndices::from(next_right.clone()),
+        );
+        let (left_stuck, right_stuck) = (
+            left_seen.contains_key(&left_key),
+            right_seen.contains_key(&right_key),
+        );
+        if !left_stuck {
+            left.push(next_left);
+            left_seen.insert(left_key.clone(), left.len() - 1);
+            if let Some(&j) = right_seen.get(&left_key) {
+                return meet(&left, &right, left.len() - 1, j);
+            }
+        }
+        if !right_stuck {
+            right.push(next_right);
+            right_seen.insert(right_key.clone(), right.len() - 1);
+            if let Some(&i) = left_seen.get(&right_key) {
+                return meet(&left, &right, i, right.len() - 1);
+            }
+        }
+        // Both sides are cycling through terms already seen, such as Ω,
+        // without having met.
+        if left_stuck && right_stuck {
+            return None;
+        }
+    }
+    None
+}
@@ -0,0 +1,201 @@
+use crate::{parse, LambdaTerm};
+
+// A well-known term together with facts about it that tests can check
+// against. `normal_form` is None for terms without one, which reduction
+// strategies can only run out of steps on; where it is given, it's the
+// result of normal order reduction up to alpha equivalence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub name: &'static str,
+    pub source: &'static str,
+    pub normal_form: Option<&'static str>,
+    pub closed: bool,
+    // Why the term is interesting.
+    pub note: &'static str,
+}
+
+impl Entry {
+    pub fn term(&self) -> LambdaTerm {
+        parse(self.source).expect("corpus sources parse")
+    }
+
+    pub fn normal_form_term(&self) -> Option<LambdaTerm> {
+        self.normal_form
+            .map(|source| parse(source).expect("corpus normal forms parse"))
+    }
+}
+
+macro_rules! entry {
+    ($name:expr, $source:expr, $normal_form:expr, $closed:expr, $note:expr) => {
+        Entry {
+            name: $name,
+            source: $source,
+            normal_form: $normal_form,
+            closed: $closed,
+            note: $note,
+        }
+    };
+}
+
+pub const ENTRIES: &[Entry] = &[
+    entry!("I", "λx. x", Some("λx. x"), true, "identity"),
+    entry!("K", "λx. λy. x", Some("λx. λy. x"), true, "constant"),
+    entry!(
+        "S",
+        "λx. λy. λz. x z (y z)",
+        Some("λx. λy. λz. x z (y z)"),
+        true,
+        "substitution"
+    ),
+    entry!(
+        "B",
+        "λx. λy. λz. x (y z)",
+        Some("λx. λy. λz. x (y z)"),
+        true,
+        "composition"
+    ),
+    entry!(
+        "C",
+        "λx. λy. λz. x z y",
+        Some("λx. λy. λz. x z y"),
+        true,
+        "argument swap"
+    ),
+    entry!(
+        "W",
+        "λx. λy. x y y",
+        Some("λx. λy. x y y"),
+        true,
+        "duplication"
+    ),
+    entry!("ω", "λx. x x", Some("λx. x x"), true, "self application"),
+    entry!(
+        "S K K",
+        "(λx. λy. λz. x z (y z)) (λx. λy. x) (λx. λy. x)",
+        Some("λz. z"),
+        true,
+        "reduces to I, the classic SKI identity"
+    ),
+    entry!("I I", "(λx. x) (λx. x)", Some("λx. x"), true, "one redex"),
+    entry!(
+        "redex under a binder",
+        "λx. (λy. y) x",
+        Some("λx. x"),
+        true,
+        "weak head normal already, but not normal"
+    ),
+    entry!(
+        "capture",
+        "(λx. λy. x) y",
+        Some("λz. y"),
+        false,
+        "substituting naively would capture the free y"
+    ),
+    entry!(
+        "plus 2 3",
+        "(λm. λn. λf. λx. m f (n f x)) (λf. λx. f (f x)) (λf. λx. f (f (f x)))",
+        Some("λf. λx. f (f (f (f (f x))))"),
+        true,
+        "Church addition"
+    ),
+    entry!(
+        "mult 2 3",
+        "(λm. λn. λf. m (n f)) (λf. λx. f (f x)) (λf. λx. f (f (f x)))",
+        Some("λf. λx. f (f (f (f (f (f x)))))"),
+        true,
+        "Church multiplication"
+    ),
+    entry!(
+        "exp 2 3",
+        "(λm. λn. n m) (λf. λx. f (f x)) (λf. λx. f (f (f x)))",
+        Some("λf. λx. f (f (f (f (f (f (f (f x)))))))"),
+        true,
+        "Church exponentiation, 2 to the 3"
+    ),
+    entry!(
+        "pred 3",
+        "(λn. λf. λx. n (λg. λh. h (g f)) (λu. x) (λu. u)) (λf. λx. f (f (f x)))",
+        Some("λf. λx. f (f x)"),
+        true,
+        "Kleene's predecessor"
+    ),
+    entry!(
+        "K I Ω",
+        "(λx. λy. x) (λx. x) ((λx. x x) (λx. x x))",
+        Some("λx. x"),
+        true,
+        "normal order finds the normal form, applicative order loops on Ω"
+    ),
+    entry!(
+        "Ω",
+        "(λx. x x) (λx. x x)",
+        None,
+        true,
+        "reduces only to itself"
+    ),
+    entry!(
+        "ω₃ ω₃",
+        "(λx. x x x) (λx. x x x)",
+        None,
+        true,
+        "grows with every step"
+    ),
+    entry!(
+        "Y",
+        "λf. (λx. f (x x)) (λx. f (x x))",
+        None,
+        true,
+        "Curry's fixed point combinator, which has no normal form"
+    ),
+    entry!(
+        "Y I",
+        "(λf. (λx. f (x x)) (λx. f (x x))) (λx. x)",
+        None,
+        true,
+        "the least fixed point of I, which diverges"
+    ),
+    entry!(
+        "Θ",
+        "(λx. λy. y (x x y)) (λx. λy. y (x x y))",
+        None,
+        true,
+        "Turing's fixed point combinator"
+    ),
+];
+
+pub fn by_name(name: &str) -> Option<&'static Entry> {
+    ENTRIES.iter().find(|entry| entry.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rewrite::{Beta, RewriteError, Rewriter, Strategy};
+
+    #[test]
+    fn entries_hold_what_they_claim() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 500).rule(Beta);
+        for entry in ENTRIES {
+            let term = entry.term();
+            assert_eq!(
+                term.free_variables().is_empty(),
+                entry.closed,
+                "{}",
+                entry.name
+            );
+            match (entry.normal_form_term(), rewriter.normalize(&term)) {
+                (Some(expected), Ok(normal)) => assert!(
+                    normal.alpha_equivalent(&expected),
+                    "{} normalized to {}",
+                    entry.name,
+                    normal
+                ),
+                (None, Err(RewriteError::StepLimit(_))) => (),
+                (expected, found) => {
+                    panic!("{}: expected {:?}, found {:?}", entry.name, expected, found)
+                }
+            }
+            assert_eq!(by_name(entry.name), Some(entry));
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::path::{Path, Step};
+use crate::small::SmallName;
+use crate::LambdaTerm;
+use std::mem;
+
+// What was left behind on the way down to the focus, innermost last.
+enum Crumb {
+    Body { bound_variable: SmallName },
+    Function { argument: LambdaTerm },
+    Argument { function: LambdaTerm },
+}
+
+// A zipper over an owned term. Moves return false, leaving the cursor where
+// it was, when there is nowhere to go in that direction.
+pub struct TermCursor {
+    focus: LambdaTerm,
+    crumbs: Vec<Crumb>,
+}
+
+impl TermCursor {
+    pub fn new(term: LambdaTerm) -> TermCursor {
+        TermCursor {
+            focus: term,
+            crumbs: Vec::new(),
+        }
+    }
+
+    pub fn focus(&self) -> &LambdaTerm {
+        &self.focus
+    }
+
+    pub fn path(&self) -> Path {
+        Path(
+            self.crumbs
+                .iter()
+                .map(|crumb| match crumb {
+                    Crumb::Body { .. } => Step::Body,
+                    Crumb::Function { .. } => Step::Function,
+                    Crumb::Argument { .. } => Step::Argument,
+                })
+                .collect(),
+        )
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.crumbs.is_empty()
+    }
+
+    fn take_focus(&mut self) -> LambdaTerm {
+        mem::replace(&mut self.focus, LambdaTerm::Variable(SmallName::default()))
+    }
+
+    // Into an abstraction's body, or an application's function.
+    pub fn down(&mut self) -> bool {
+        match self.take_focus() {
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => {
+                self.crumbs.push(Crumb::Body { bound_variable });
+                self.focus = *return_term;
+                true
+            }
+            LambdaTerm::Application { function, argument } => {
+                self.crumbs.push(Crumb::Function {
+                    argument: *argument,
+                });
+                self.focus = *function;
+                true
+            }
+            variable => {
+                self.focus = variable;
+                false
+            }
+        }
+    }
+
+    pub fn up(&mut self) -> bool {
+        let focus = match self.crumbs.pop() {
+            Some(crumb) => self.rebuild(crumb),
+            None => return false,
+        };
+        self.focus = focus;
+        true
+    }
+
+    // From an application's argument to its function.
+    pub fn left(&mut self) -> bool {
+        match self.crumbs.pop() {
+            Some(Crumb::Argument { function }) => {
+                let argument = mem::replace(&mut self.focus, function);
+                self.crumbs.push(Crumb::Function { argument });
+                true
+            }
+            Some(crumb) => {
+                self.crumbs.push(crumb);
+                false
+            }
+            None => false,
+        }
+    }
+
+    // From an application's function to its argument.
+    pub fn right(&mut self) -> bool {
+        match self.crumbs.pop() {
+            Some(Crumb::Function { argument }) => {
+                let function = mem::replace(&mut self.focus, argument);
+                self.crumbs.push(Crumb::Argument { function });
+                true
+            }
+            Some(crumb) => {
+                self.crumbs.push(crumb);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub fn root(&mut self) {
+        while self.up() {}
+    }
+
+    // Swaps in a new focus, handing back the subterm it replaces.
+    pub fn replace(&mut self, term: LambdaTerm) -> LambdaTerm {
+        mem::replace(&mut self.focus, term)
+    }
+
+    pub fn into_term(mut self) -> LambdaTerm {
+        self.root();
+        self.focus
+    }
+
+    fn rebuild(&mut self, crumb: Crumb) -> LambdaTerm {
+        let focus = Box::new(self.take_focus());
+        match crumb {
+            Crumb::Body { bound_variable } => LambdaTerm::Abstraction {
+                bound_variable,
+                return_term: focus,
+            },
+            Crumb::Function { argument } => LambdaTerm::Application {
+                function: focus,
+                argument: Box::new(argument),
+            },
+            Crumb::Argument { function } => LambdaTerm::Application {
+                function: Box::new(function),
+                argument: focus,
+            },
+        }
+    }
+}
+
+impl From<LambdaTerm> for TermCursor {
+    fn from(term: LambdaTerm) -> TermCursor {
+        TermCursor::new(term)
+    }
+}
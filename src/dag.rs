@@ -0,0 +1,163 @@
+use crate::store::{Node, TermId, TermStore};
+use crate::symbol::Symbol;
+use crate::{DBIndices, DBTerm, LambdaTerm, NameScheme};
+use std::collections::HashMap;
+use std::fmt;
+
+// A term with each distinct subterm stored once. Bound variables are de
+// Bruijn indices, so alpha-equivalent subterms share a node: λx. x and λy. y
+// are one node, and so are two occurrences of the variable bound one binder
+// up, whichever binder that is.
+#[derive(Debug, Clone)]
+pub struct Dag {
+    pub store: TermStore,
+    pub root: TermId,
+    // How many times each node occurs in the term as a tree, by TermId.
+    pub occurrences: Vec<usize>,
+    // The size of each node's subterm as a tree, by TermId.
+    pub sizes: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DagStats {
+    // The nodes in the term as a tree.
+    pub total_nodes: usize,
+    // The nodes left once repeated subterms are shared.
+    pub unique_nodes: usize,
+}
+
+// A subterm that occurs more than once. `saved` is the number of tree nodes
+// sharing it does away with, (occurrences - 1) × size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repeated {
+    pub id: TermId,
+    pub size: usize,
+    pub occurrences: usize,
+    pub saved: usize,
+}
+
+impl LambdaTerm {
+    pub fn to_dag(&self) -> Dag {
+        fn to_dag_mut<'a>(
+            term: &'a LambdaTerm,
+            scope: &mut Vec<&'a str>,
+            dag: &mut Dag,
+            known: &mut HashMap<Node, TermId>,
+        ) -> TermId {
+            let (node, size) = match term {
+                LambdaTerm::Variable(id) => match scope.iter().rposition(|bound| bound == id) {
+                    Some(position) => (Node::Variable(scope.len() - position), 1),
+                    None => (Node::FreeVariable(Symbol::intern(id)), 1),
+                },
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    scope.push(bound_variable);
+                    let return_term = to_dag_mut(return_term, scope, dag, known);
+                    scope.pop();
+                    (Node::Abstraction(return_term), 1 + dag.sizes[return_term.0])
+                }
+                LambdaTerm::Application { function, argument } => {
+                    let function = to_dag_mut(function, scope, dag, known);
+                    let argument = to_dag_mut(argument, scope, dag, known);
+                    (
+                        Node::Application(function, argument),
+                        1 + dag.sizes[function.0] + dag.sizes[argument.0],
+                    )
+                }
+            };
+            let id = *known.entry(node).or_insert_with(|| {
+                dag.occurrences.push(0);
+                dag.sizes.push(size);
+                dag.store.add(node)
+            });
+            dag.occurrences[id.0] += 1;
+            id
+        }
+        let mut dag = Dag {
+            store: TermStore::new(),
+            root: TermId(0),
+            occurrences: Vec::new(),
+            sizes: Vec::new(),
+        };
+        dag.root = to_dag_mut(self, &mut Vec::new(), &mut dag, &mut HashMap::new());
+        dag
+    }
+}
+
+impl Dag {
+    pub fn stats(&self) -> DagStats {
+        DagStats {
+            total_nodes: self.sizes[self.root.0],
+            unique_nodes: self.store.len(),
+        }
+    }
+
+    // The subterms occurring more than once, leaving out variables, which
+    // are always cheap to repeat, and subterms that only ever occur inside a
+    // larger repeated one. Those whose sharing saves the most come first.
+    pub fn repeated(&self) -> Vec<Repeated> {
+        let is_repeated = |i: usize| self.occurrences[i] > 1 && self.sizes[i] > 1;
+        // How many of each node's occurrences are inside a repeated parent.
+        let mut inside = vec![0; self.store.len()];
+        for i in (0..self.store.len()).filter(|&i| is_repeated(i)) {
+            match *self.store.get(TermId(i)) {
+                Node::Abstraction(child) => inside[child.0] += self.occurrences[i],
+                Node::Application(function, argument) => {
+                    inside[function.0] += self.occurrences[i];
+                    inside[argument.0] += self.occurrences[i];
+                }
+                Node::Variable(_) | Node::FreeVariable(_) => (),
+            }
+        }
+        let mut repeated: Vec<Repeated> = (0..self.store.len())
+            .filter(|&i| is_repeated(i) && inside[i] < self.occurrences[i])
+            .map(|i| Repeated {
+                id: TermId(i),
+                size: self.sizes[i],
+                occurrences: self.occurrences[i],
+                saved: (self.occurrences[i] - 1) * self.sizes[i],
+            })
+            .collect();
+        repeated.sort_by(|a, b| b.saved.cmp(&a.saved).then(a.id.cmp(&b.id)));
+        repeated
+    }
+
+    // How many binders outside the subterm at `id` it refers to.
+    fn dangling(&self, id: TermId) -> usize {
+        match *self.store.get(id) {
+            Node::Variable(index) => index,
+            Node::FreeVariable(_) => 0,
+            Node::Abstraction(return_term) => self.dangling(return_term).saturating_sub(1),
+            Node::Application(function, argument) => {
+                self.dangling(function).max(self.dangling(argument))
+            }
+        }
+    }
+
+    // The subterm at `id` with names from `scheme`. A subterm using variables
+    // bound outside it is closed over them by as many abstractions, so the
+    // x x repeated in λx. x x (x x) comes out as λa. a a.
+    pub fn to_named(&self, id: TermId, scheme: NameScheme) -> LambdaTerm {
+        let DBIndices(mut term) = self.store.to_indices(id);
+        for _ in 0..self.dangling(id) {
+            term = DBTerm::Abstraction(Box::new(term));
+        }
+        DBIndices(term).to_named(scheme)
+    }
+}
+
+impl fmt::Display for DagStats {
+    // E.g. `12 unique of 20 nodes (40% shared)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shared = self.total_nodes - self.unique_nodes;
+        write!(
+            f,
+            "{} unique of {} nodes ({}% shared)",
+            self.unique_nodes,
+            self.total_nodes,
+            100 * shared / self.total_nodes
+        )
+    }
+}
@@ -0,0 +1,165 @@
+use crate::json::Json;
+use crate::rewrite::RewriteError;
+use crate::{LambdaTerm, LexError, Lexer, Parser, ParserError, Token};
+use std::cell::Cell;
+use std::ops::Range;
+use std::rc::Rc;
+
+// An error in a form tools can act on: a stable code to match on, a message
+// for people, and where in the source it arose, as a range of byte offsets,
+// when that's known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Range<usize>>,
+}
+
+impl Diagnostic {
+    pub fn to_json(&self) -> Json {
+        let span = match &self.span {
+            Some(span) => Json::Object(vec![
+                (String::from("start"), Json::Number(span.start as f64)),
+                (String::from("end"), Json::Number(span.end as f64)),
+            ]),
+            None => Json::Null,
+        };
+        Json::Object(vec![
+            (String::from("code"), Json::String(String::from(self.code))),
+            (String::from("message"), Json::String(self.message.clone())),
+            (String::from("span"), span),
+        ])
+    }
+}
+
+impl ParserError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::PrematureEnd => "premature-end",
+            ParserError::ParenOutOfBounds { .. } => "paren-out-of-bounds",
+            ParserError::ExpectedIdentifierGot(_) => "expected-identifier",
+            ParserError::ExpectedGot(..) => "unexpected-token",
+            ParserError::Unexpected(_) => "unexpected-token",
+            ParserError::UnmatchedParens(_) => "unmatched-parens",
+            ParserError::ExpectedIndexGot(_) => "expected-index",
+            ParserError::UnterminatedQuote(_) => "unterminated-quote",
+            ParserError::IndexOutOfScope { .. } => "index-out-of-scope",
+            ParserError::StrayCharacter(_) => "stray-character",
+            ParserError::EmbeddedNul => "embedded-nul",
+            ParserError::EmptyParens => "empty-parens",
+            ParserError::TooDeep(_) => "too-deep",
+            ParserError::InputTooLong(_) => "input-too-long",
+            ParserError::IdentifierTooLong(_) => "identifier-too-long",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ParserError::PrematureEnd => String::from("the input ended in the middle of a term"),
+            ParserError::ParenOutOfBounds { .. } => {
+                String::from("a closing parenthesis ends a group that was never opened here")
+            }
+            ParserError::ExpectedIdentifierGot(token) => {
+                format!("expected a variable name after λ, found {:?}", token)
+            }
+            ParserError::ExpectedGot(expected, found) => {
+                format!("expected {:?}, found {:?}", expected, found)
+            }
+            ParserError::Unexpected(token) => format!("unexpected {:?}", token),
+            ParserError::UnmatchedParens(n) if *n > 0 => {
+                format!("unbalanced parentheses, {} left open", n)
+            }
+            ParserError::UnmatchedParens(n) => {
+                format!("unbalanced parentheses, {} closed too many", -n)
+            }
+            ParserError::ExpectedIndexGot(token) => {
+                format!("expected a de Bruijn index, found {:?}", token)
+            }
+            ParserError::UnterminatedQuote(id) => {
+                format!("the quoted name `{}` is never closed", id)
+            }
+            ParserError::IndexOutOfScope {
+                index,
+                abstraction_depth,
+            } => format!(
+                "index {} refers past the {} enclosing abstractions",
+                index, abstraction_depth
+            ),
+            ParserError::StrayCharacter(ch) => format!("stray character {:?}", ch),
+            ParserError::EmbeddedNul => String::from("the input contains a NUL byte"),
+            ParserError::EmptyParens => String::from("empty parentheses"),
+            ParserError::TooDeep(depth) => {
+                format!("the term nests more than {} levels deep", depth)
+            }
+            ParserError::InputTooLong(limit) => {
+                format!("the input is longer than {} bytes", limit)
+            }
+            ParserError::IdentifierTooLong(limit) => {
+                format!("a name is longer than {} characters", limit)
+            }
+        }
+    }
+}
+
+// Without a span, since a ParserError doesn't say where it arose; parse
+// gives one that does.
+impl From<&ParserError> for Diagnostic {
+    fn from(err: &ParserError) -> Diagnostic {
+        Diagnostic {
+            code: err.code(),
+            message: err.message(),
+            span: None,
+        }
+    }
+}
+
+impl From<&RewriteError> for Diagnostic {
+    fn from(err: &RewriteError) -> Diagnostic {
+        Diagnostic {
+            code: err.code(),
+            message: err.to_string(),
+            span: None,
+        }
+    }
+}
+
+// Hands tokens to the parser while noting the span of the last one, which is
+// where the parser was when it gave up.
+struct Spans<'a> {
+    code: &'a str,
+    lexer: Lexer<'a>,
+    last: Rc<Cell<(usize, usize)>>,
+}
+
+impl<'a> Iterator for Spans<'a> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Result<Token, LexError>> {
+        let before = self.lexer.position();
+        let token = self.lexer.next();
+        let rest = &self.code[before..];
+        let start = before + (rest.len() - rest.trim_start().len());
+        let end = self.lexer.position().max(start);
+        self.last.set((start, end));
+        token
+    }
+}
+
+// parse, but reporting a failure as a Diagnostic spanning the token at
+// which parsing stopped, or the end of the input if it ran out.
+pub fn parse(code: &str) -> Result<LambdaTerm, Diagnostic> {
+    let last = Rc::new(Cell::new((0, 0)));
+    let spans = Spans {
+        code,
+        lexer: Lexer::new(code),
+        last: Rc::clone(&last),
+    };
+    Parser::new(spans).parse().map_err(|err| {
+        let (start, end) = last.get();
+        Diagnostic {
+            code: err.code(),
+            message: err.message(),
+            span: Some(start..end),
+        }
+    })
+}
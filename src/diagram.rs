@@ -0,0 +1,208 @@
+use crate::LambdaTerm;
+use std::fmt::Write;
+
+// The lines of one of John Tromp's lambda diagrams, on a grid with a column
+// per variable occurrence and a row per abstraction or application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    // An abstraction, spanning the columns of its body.
+    Abstraction {
+        row: usize,
+        from: usize,
+        to: usize,
+    },
+    // An application, joining the function's column to the argument's.
+    Application {
+        row: usize,
+        from: usize,
+        to: usize,
+    },
+    // A variable, hanging from its binder down to where it's used.
+    Variable {
+        column: usize,
+        from: usize,
+        to: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    pub width: usize,
+    pub height: usize,
+    pub lines: Vec<Line>,
+}
+
+// Each column is this many cells wide, in characters for to_ascii and in
+// SVG units for to_svg. A variable sits one cell in, and an abstraction
+// spans three cells per column, so neighbouring abstractions don't touch.
+const PITCH: usize = 4;
+
+impl Diagram {
+    // Lays out `term`. A variable hangs from the abstraction binding it, or
+    // from the top edge if it's free. An application draws its function and
+    // argument side by side and joins them below the taller of the two,
+    // where the function's line carries on down. The whole term's line
+    // sticks out one row below everything else.
+    pub fn new(term: &LambdaTerm) -> Diagram {
+        // Draws `term` with its top left corner at `column` and `row`, and
+        // gives its width and height.
+        fn layout_mut<'a>(
+            term: &'a LambdaTerm,
+            column: usize,
+            row: usize,
+            binders: &mut Vec<(&'a str, usize)>,
+            lines: &mut Vec<Line>,
+        ) -> (usize, usize) {
+            match term {
+                LambdaTerm::Variable(id) => {
+                    let from = match binders.iter().rev().find(|(bound, _)| bound == id) {
+                        Some(&(_, binder)) => binder + 1,
+                        None => 0,
+                    };
+                    lines.push(Line::Variable {
+                        column,
+                        from,
+                        to: row,
+                    });
+                    (1, 1)
+                }
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    binders.push((bound_variable, row));
+                    let (width, height) = layout_mut(return_term, column, row + 1, binders, lines);
+                    binders.pop();
+                    lines.push(Line::Abstraction {
+                        row,
+                        from: column,
+                        to: column + width - 1,
+                    });
+                    (width, height + 1)
+                }
+                LambdaTerm::Application { function, argument } => {
+                    let (function_width, function_height) =
+                        layout_mut(function, column, row, binders, lines);
+                    let argument_column = column + function_width;
+                    let (argument_width, argument_height) =
+                        layout_mut(argument, argument_column, row, binders, lines);
+                    let height = function_height.max(argument_height) + 1;
+                    let bottom = row + height - 1;
+                    lines.push(Line::Variable {
+                        column,
+                        from: row + function_height,
+                        to: bottom,
+                    });
+                    lines.push(Line::Variable {
+                        column: argument_column,
+                        from: row + argument_height,
+                        to: bottom,
+                    });
+                    lines.push(Line::Application {
+                        row: bottom,
+                        from: column,
+                        to: argument_column,
+                    });
+                    (function_width + argument_width, height)
+                }
+            }
+        }
+        let mut lines = Vec::new();
+        let (width, height) = layout_mut(term, 0, 0, &mut Vec::new(), &mut lines);
+        lines.push(Line::Variable {
+            column: 0,
+            from: height,
+            to: height,
+        });
+        Diagram {
+            width,
+            height: height + 1,
+            lines,
+        }
+    }
+
+    // Draws the diagram in characters, with `-` for abstractions and
+    // applications, `|` for variables and `+` where they cross.
+    pub fn to_ascii(&self) -> String {
+        let mut grid = vec![vec![' '; self.width * PITCH - 1]; self.height];
+        for line in &self.lines {
+            match *line {
+                Line::Abstraction { row, from, to } => {
+                    for cell in &mut grid[row][from * PITCH..to * PITCH + PITCH - 1] {
+                        *cell = '-';
+                    }
+                }
+                Line::Application { row, from, to } => {
+                    for cell in &mut grid[row][from * PITCH + 1..=to * PITCH + 1] {
+                        *cell = '-';
+                    }
+                }
+                Line::Variable { .. } => (),
+            }
+        }
+        for line in &self.lines {
+            if let Line::Variable { column, from, to } = *line {
+                for row in &mut grid[from..=to] {
+                    let cell = &mut row[column * PITCH + 1];
+                    *cell = if *cell == '-' { '+' } else { '|' };
+                }
+            }
+        }
+        let mut out = String::new();
+        for row in grid {
+            let row: String = row.into_iter().collect();
+            out.push_str(row.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    // Draws the diagram as an SVG image, each line a filled rectangle one
+    // unit thick. Row r spans y = 2r to 2r + 2, with its horizontal line
+    // along the top.
+    pub fn to_svg(&self) -> String {
+        let (width, height) = (self.width * PITCH - 1, self.height * 2 - 1);
+        let mut out = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {0} {1}\" \
+             width=\"{2}\" height=\"{3}\" shape-rendering=\"crispEdges\">\n",
+            width,
+            height,
+            width * 4,
+            height * 4
+        );
+        let mut rect = |x: usize, y: usize, width: usize, height: usize| {
+            writeln!(
+                out,
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                x, y, width, height
+            )
+            .unwrap();
+        };
+        for line in &self.lines {
+            match *line {
+                Line::Abstraction { row, from, to } => {
+                    rect(from * PITCH, row * 2, (to - from + 1) * PITCH - 1, 1)
+                }
+                Line::Application { row, from, to } => {
+                    rect(from * PITCH + 1, row * 2, (to - from) * PITCH + 1, 1)
+                }
+                // Starts just under the line above its first row, and ends
+                // level with the bottom of any line in its last row.
+                Line::Variable { column, from, to } => rect(
+                    column * PITCH + 1,
+                    (from * 2).saturating_sub(1),
+                    1,
+                    (to * 2 + 1) - (from * 2).saturating_sub(1),
+                ),
+            }
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+impl LambdaTerm {
+    pub fn diagram(&self) -> Diagram {
+        Diagram::new(self)
+    }
+}
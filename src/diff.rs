@@ -0,0 +1,96 @@
+use crate::path::{Path, Step};
+use crate::LambdaTerm;
+
+// One position where two terms disagree, with the subterm each has there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub path: Path,
+    pub left: LambdaTerm,
+    pub right: LambdaTerm,
+}
+
+// The positions where `left` and `right` differ, up to renaming bound
+// variables, in left to right order. Two applications or two abstractions
+// never differ themselves, only in their subterms, so each difference is as
+// deep as it can be; the list is empty exactly when the terms are alpha
+// equivalent. A variable bound in one term and free in the other, or bound
+// by different abstractions, counts as a difference.
+pub fn diff(left: &LambdaTerm, right: &LambdaTerm) -> Vec<Difference> {
+    fn diff_mut<'a>(
+        left: &'a LambdaTerm,
+        right: &'a LambdaTerm,
+        path: Path,
+        binders: &mut Vec<(&'a str, &'a str)>,
+        differences: &mut Vec<Difference>,
+    ) {
+        match (left, right) {
+            (LambdaTerm::Variable(left_id), LambdaTerm::Variable(right_id)) => {
+                let left_binder = binders.iter().rposition(|(id, _)| id == left_id);
+                let right_binder = binders.iter().rposition(|(_, id)| id == right_id);
+                let same = match (left_binder, right_binder) {
+                    (None, None) => left_id == right_id,
+                    (left_binder, right_binder) => left_binder == right_binder,
+                };
+                if same {
+                    return;
+                }
+            }
+            (
+                LambdaTerm::Application {
+                    function: left_function,
+                    argument: left_argument,
+                },
+                LambdaTerm::Application {
+                    function: right_function,
+                    argument: right_argument,
+                },
+            ) => {
+                diff_mut(
+                    left_function,
+                    right_function,
+                    path.child(Step::Function),
+                    binders,
+                    differences,
+                );
+                diff_mut(
+                    left_argument,
+                    right_argument,
+                    path.child(Step::Argument),
+                    binders,
+                    differences,
+                );
+                return;
+            }
+            (
+                LambdaTerm::Abstraction {
+                    bound_variable: left_variable,
+                    return_term: left_return,
+                },
+                LambdaTerm::Abstraction {
+                    bound_variable: right_variable,
+                    return_term: right_return,
+                },
+            ) => {
+                binders.push((left_variable, right_variable));
+                diff_mut(
+                    left_return,
+                    right_return,
+                    path.child(Step::Body),
+                    binders,
+                    differences,
+                );
+                binders.pop();
+                return;
+            }
+            _ => (),
+        }
+        differences.push(Difference {
+            path,
+            left: left.clone(),
+            right: right.clone(),
+        });
+    }
+    let mut differences = Vec::new();
+    diff_mut(left, right, Path::new(), &mut Vec::new(), &mut differences);
+    differences
+}
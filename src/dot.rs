@@ -0,0 +1,80 @@
+use crate::small::SmallName;
+use crate::LambdaTerm;
+use std::fmt::Write;
+
+// Quotes a label for the DOT language, in which only `"` and `\` need
+// escaping inside a quoted string.
+fn quote(label: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in label.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+impl LambdaTerm {
+    // Renders the syntax tree as a Graphviz digraph. Abstractions are drawn
+    // as λx, applications as @ and variables by name; each bound variable
+    // also has a dashed edge back to the abstraction binding it, and free
+    // variables are drawn with a dashed outline.
+    pub fn to_dot(&self) -> String {
+        fn to_dot_mut(
+            term: &LambdaTerm,
+            binders: &mut Vec<(SmallName, usize)>,
+            next: &mut usize,
+            out: &mut String,
+        ) -> usize {
+            let node = *next;
+            *next += 1;
+            match term {
+                LambdaTerm::Variable(id) => {
+                    match binders.iter().rev().find(|(bound, _)| bound == id) {
+                        Some(&(_, binder)) => {
+                            writeln!(out, "  n{} [label={}];", node, quote(id)).unwrap();
+                            writeln!(
+                                out,
+                                "  n{} -> n{} [style=dashed, constraint=false];",
+                                node, binder
+                            )
+                            .unwrap();
+                        }
+                        None => writeln!(out, "  n{} [label={}, style=dashed];", node, quote(id))
+                            .unwrap(),
+                    }
+                }
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    writeln!(
+                        out,
+                        "  n{} [label={}];",
+                        node,
+                        quote(&format!("λ{}", bound_variable))
+                    )
+                    .unwrap();
+                    binders.push((bound_variable.clone(), node));
+                    let body = to_dot_mut(return_term, binders, next, out);
+                    binders.pop();
+                    writeln!(out, "  n{} -> n{};", node, body).unwrap();
+                }
+                LambdaTerm::Application { function, argument } => {
+                    writeln!(out, "  n{} [label=\"@\"];", node).unwrap();
+                    let function = to_dot_mut(function, binders, next, out);
+                    let argument = to_dot_mut(argument, binders, next, out);
+                    writeln!(out, "  n{} -> n{};", node, function).unwrap();
+                    writeln!(out, "  n{} -> n{};", node, argument).unwrap();
+                }
+            }
+            node
+        }
+        let mut out = String::from("digraph term {\n");
+        to_dot_mut(self, &mut Vec::new(), &mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+}
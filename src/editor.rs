@@ -0,0 +1,334 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+// What a keypress asks the editor to do, after escape sequences and UTF-8
+// have been decoded.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Backspace,
+    Delete,
+    // Ctrl-U and Ctrl-K.
+    KillToStart,
+    KillToEnd,
+    Enter,
+    // Ctrl-C abandons the line, Ctrl-D on an empty line ends the input.
+    Interrupt,
+    EndOfInput,
+    Ignored,
+}
+
+fn read_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+// Reads one key, or None once the input is exhausted. Both the CSI (`ESC [`)
+// and SS3 (`ESC O`) forms of the cursor keys are understood, since terminals
+// differ in which they send.
+pub fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    let byte = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfInput,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x08 | 0x7f => Key::Backspace,
+        0x0b => Key::KillToEnd,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::KillToStart,
+        0x1b => read_escape(input)?,
+        byte if byte < 0x20 => Key::Ignored,
+        byte => read_char(byte, input)?,
+    };
+    Ok(Some(key))
+}
+
+fn read_escape<R: Read>(input: &mut R) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => (),
+        _ => return Ok(Key::Ignored),
+    }
+    let mut parameter = String::new();
+    loop {
+        let key = match read_byte(input)? {
+            Some(digit @ b'0'..=b'9') => {
+                parameter.push(digit as char);
+                continue;
+            }
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            Some(b'~') => match parameter.as_str() {
+                "1" | "7" => Key::Home,
+                "4" | "8" => Key::End,
+                "3" => Key::Delete,
+                _ => Key::Ignored,
+            },
+            _ => Key::Ignored,
+        };
+        return Ok(key);
+    }
+}
+
+fn read_char<R: Read>(first: u8, input: &mut R) -> io::Result<Key> {
+    let length = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    for _ in 1..length {
+        match read_byte(input)? {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    Ok(std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|text| text.chars().next())
+        .map_or(Key::Ignored, Key::Char))
+}
+
+// A single line being edited, with the position in the history it was
+// recalled from. Lines recalled and then changed only change the copy.
+#[derive(Default)]
+pub struct Editor {
+    line: Vec<char>,
+    cursor: usize,
+    // Counts back from the end of the history; 0 is the line being typed.
+    recalled: usize,
+    // The line being typed, kept while browsing the history.
+    draft: Vec<char>,
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor::default()
+    }
+
+    // Reads keys until Enter, redrawing the prompt and line after each, and
+    // returns the line, or None if the input ended first. The terminal is
+    // expected to be in raw mode already; see `read_line_from_terminal`.
+    pub fn read_line<R: Read, W: Write>(
+        &mut self,
+        prompt: &str,
+        history: &[String],
+        input: &mut R,
+        output: &mut W,
+    ) -> io::Result<Option<String>> {
+        self.line.clear();
+        self.cursor = 0;
+        self.recalled = 0;
+        self.draft.clear();
+        self.redraw(prompt, output)?;
+        loop {
+            let key = match read_key(input)? {
+                Some(key) => key,
+                None if self.line.is_empty() => return Ok(None),
+                None => Key::Enter,
+            };
+            match key {
+                Key::Enter => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(Some(self.line.iter().collect()));
+                }
+                Key::EndOfInput if self.line.is_empty() => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(None);
+                }
+                Key::Interrupt => {
+                    write!(output, "^C\r\n")?;
+                    self.line.clear();
+                    self.cursor = 0;
+                    self.recalled = 0;
+                }
+                key => self.apply(key, history),
+            }
+            self.redraw(prompt, output)?;
+        }
+    }
+
+    fn apply(&mut self, key: Key, history: &[String]) {
+        match key {
+            Key::Char(ch) => {
+                self.line.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.line.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.line.len(),
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+            }
+            Key::Delete | Key::EndOfInput if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+            }
+            Key::KillToStart => {
+                self.line.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::KillToEnd => self.line.truncate(self.cursor),
+            Key::Up if self.recalled < history.len() => self.recall(self.recalled + 1, history),
+            Key::Down if self.recalled > 0 => self.recall(self.recalled - 1, history),
+            _ => (),
+        }
+    }
+
+    fn recall(&mut self, recalled: usize, history: &[String]) {
+        if self.recalled == 0 {
+            self.draft = self.line.clone();
+        }
+        self.recalled = recalled;
+        self.line = match recalled {
+            0 => self.draft.clone(),
+            n => history[history.len() - n].chars().collect(),
+        };
+        self.cursor = self.line.len();
+    }
+
+    // Rewrites the whole line in place and moves the terminal's cursor back
+    // to the editor's, counting each char as one column.
+    fn redraw<W: Write>(&self, prompt: &str, output: &mut W) -> io::Result<()> {
+        let line: String = self.line.iter().collect();
+        write!(output, "\r{}{}\x1b[K", prompt, line)?;
+        let behind = self.line.len() - self.cursor;
+        if behind > 0 {
+            write!(output, "\x1b[{}D", behind)?;
+        }
+        output.flush()
+    }
+
+    // Puts the controlling terminal into raw mode for the one line, so that
+    // keys arrive as they're pressed, and restores it before returning.
+    pub fn read_line_from_terminal<W: Write>(
+        &mut self,
+        prompt: &str,
+        history: &[String],
+        output: &mut W,
+    ) -> io::Result<Option<String>> {
+        let _raw = RawMode::enable()?;
+        self.read_line(prompt, history, &mut io::stdin().lock(), output)
+    }
+}
+
+// Left to stty rather than termios bindings, which would need a dependency.
+struct RawMode {
+    saved: String,
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let result = Command::new("stty")
+        .args(args)
+        .stdin(File::open("/dev/tty")?)
+        .stderr(Stdio::null())
+        .output()?;
+    if !result.status.success() {
+        return Err(io::Error::other(
+            "stty couldn't change the terminal settings",
+        ));
+    }
+    Ok(String::from_utf8_lossy(&result.stdout).trim().to_string())
+}
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Ok(RawMode { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(keys: &str, history: &[&str]) -> Option<String> {
+        let history: Vec<String> = history.iter().map(|line| line.to_string()).collect();
+        let mut output = Vec::new();
+        Editor::new()
+            .read_line("λ> ", &history, &mut keys.as_bytes(), &mut output)
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_keys() {
+        let mut input = "λ\x1b[D\x1bOC\x1b[3~\x1b[1~\x7f\r".as_bytes();
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut input).unwrap() {
+            keys.push(key);
+        }
+        assert_eq!(
+            keys,
+            [
+                Key::Char('λ'),
+                Key::Left,
+                Key::Right,
+                Key::Delete,
+                Key::Home,
+                Key::Backspace,
+                Key::Enter
+            ]
+        );
+    }
+
+    #[test]
+    fn edits_at_the_cursor() {
+        assert_eq!(edit("λx. x\r", &[]).unwrap(), "λx. x");
+        assert_eq!(edit("λ. x\x1b[D\x1b[D\x1b[Dx\r", &[]).unwrap(), "λx. x");
+        assert_eq!(edit("ab\x01c\x05d\r", &[]).unwrap(), "cabd");
+        assert_eq!(edit("abc\x1b[D\x7f\x1b[3~\r", &[]).unwrap(), "a");
+        assert_eq!(edit("abc\x1b[D\x15\r", &[]).unwrap(), "c");
+        assert_eq!(edit("abc\x01\x0b\r", &[]).unwrap(), "");
+        assert_eq!(edit("abc\x03x\r", &[]).unwrap(), "x");
+    }
+
+    #[test]
+    fn browses_the_history() {
+        let history = ["first", "second"];
+        assert_eq!(edit("\x1b[A\r", &history).unwrap(), "second");
+        assert_eq!(edit("\x1b[A\x1b[A\x1b[A\r", &history).unwrap(), "first");
+        assert_eq!(edit("\x1b[A\x1b[A\x1b[B\r", &history).unwrap(), "second");
+        assert_eq!(edit("new\x1b[A\x1b[B\r", &history).unwrap(), "new");
+        assert_eq!(edit("\x1b[A!\r", &history).unwrap(), "second!");
+    }
+
+    #[test]
+    fn ends_on_end_of_input() {
+        assert_eq!(edit("", &[]), None);
+        assert_eq!(edit("\x04", &[]), None);
+        assert_eq!(edit("ab\x01\x04\r", &[]).unwrap(), "b");
+        assert_eq!(edit("ab", &[]).unwrap(), "ab");
+    }
+}
@@ -0,0 +1,520 @@
+use crate::names::NameSupply;
+use crate::small::SmallName;
+use crate::{app, lam, lambda, var, LambdaTerm};
+use std::fmt;
+
+// λf. λx. fⁿ x
+pub fn church_nat(n: u64) -> LambdaTerm {
+    let body = (0..n).fold(var("x"), |body, _| app(var("f"), body));
+    lam("f", lam("x", body))
+}
+
+// λn. λf. λx. f (n f x)
+pub fn succ() -> LambdaTerm {
+    lambda!(|n, f, x| f (n f x))
+}
+
+// λm. λn. λf. λx. m f (n f x)
+pub fn plus() -> LambdaTerm {
+    lambda!(|m, n, f, x| m f (n f x))
+}
+
+// λm. λn. λf. m (n f)
+pub fn mult() -> LambdaTerm {
+    lambda!(|m, n, f| m (n f))
+}
+
+// λm. λn. λf. λx. n m f x, which raises m to the power n. The shorter
+// λm. λn. n m gives λx. x for a power of 0, which is only η-equal to 1.
+pub fn exp() -> LambdaTerm {
+    lambda!(|m, n, f, x| n m f x)
+}
+
+// λn. λf. λx. n (λg. λh. h (g f)) (λu. x) (λu. u), where pred 0 is 0.
+pub fn pred() -> LambdaTerm {
+    lambda!(|n, f, x| n (|g, h| h (g f)) (|u| x) (|u| u))
+}
+
+// λm. λn. n pred m, which stops at 0 rather than going negative.
+pub fn sub() -> LambdaTerm {
+    lam("m", lam("n", app(app(var("n"), pred()), var("m"))))
+}
+
+// Reads back a Church numeral in normal form, whatever its binders are
+// called.
+pub fn decode_church_nat(term: &LambdaTerm) -> Option<u64> {
+    let (f, x, mut body) = match term {
+        LambdaTerm::Abstraction {
+            bound_variable: f,
+            return_term,
+        } => match &**return_term {
+            LambdaTerm::Abstraction {
+                bound_variable: x,
+                return_term,
+            } if x != f => (f, x, &**return_term),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let mut n = 0;
+    loop {
+        match body {
+            LambdaTerm::Variable(id) if id == x => break Some(n),
+            LambdaTerm::Application { function, argument } if matches!(&**function, LambdaTerm::Variable(id) if id == f) =>
+            {
+                n += 1;
+                body = argument;
+            }
+            _ => break None,
+        }
+    }
+}
+
+// λt. λf. t
+pub fn church_true() -> LambdaTerm {
+    lambda!(|t, f| t)
+}
+
+// λt. λf. f
+pub fn church_false() -> LambdaTerm {
+    lambda!(|t, f| f)
+}
+
+pub fn church_bool(b: bool) -> LambdaTerm {
+    if b {
+        church_true()
+    } else {
+        church_false()
+    }
+}
+
+// λp. λa. λb. p a b, which picks a when p is true and b when it is false.
+pub fn church_if() -> LambdaTerm {
+    lambda!(|p, a, b| p a b)
+}
+
+// Reads back a Church boolean in normal form, whatever its binders are
+// called.
+pub fn decode_church_bool(term: &LambdaTerm) -> Option<bool> {
+    match term {
+        LambdaTerm::Abstraction {
+            bound_variable: t,
+            return_term,
+        } => match &**return_term {
+            LambdaTerm::Abstraction {
+                bound_variable: f,
+                return_term,
+            } if t != f => match &**return_term {
+                LambdaTerm::Variable(id) if id == t => Some(true),
+                LambdaTerm::Variable(id) if id == f => Some(false),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// λa. λb. λs. s a b
+pub fn pair() -> LambdaTerm {
+    lambda!(|a, b, s| s a b)
+}
+
+// λp. p (λa. λb. a)
+pub fn fst() -> LambdaTerm {
+    lambda!(|p| p(|a, b| a))
+}
+
+// λp. p (λa. λb. b)
+pub fn snd() -> LambdaTerm {
+    lambda!(|p| p(|a, b| b))
+}
+
+// The normal form of `pair first second`, λs. s first second, with `s`
+// renamed if either component uses it.
+pub fn church_pair(first: LambdaTerm, second: LambdaTerm) -> LambdaTerm {
+    let s = fresh_binder("s", [&first, &second]);
+    lam(s.clone(), app(app(var(s), first), second))
+}
+
+// Reads back a pair in normal form, giving its components.
+pub fn decode_church_pair(term: &LambdaTerm) -> Option<(LambdaTerm, LambdaTerm)> {
+    let (s, body) = match term {
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => (bound_variable, &**return_term),
+        _ => return None,
+    };
+    match body {
+        LambdaTerm::Application {
+            function,
+            argument: second,
+        } => match &**function {
+            LambdaTerm::Application {
+                function: selector,
+                argument: first,
+            } if matches!(&**selector, LambdaTerm::Variable(id) if id == s)
+                && !first.free_variables().contains(s)
+                && !second.free_variables().contains(s) =>
+            {
+                Some(((**first).clone(), (**second).clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Lists are right folds: [x₁, …, xₙ] is λc. λn. c x₁ (… (c xₙ n)).
+
+// λc. λn. n
+pub fn nil() -> LambdaTerm {
+    lambda!(|c, n| n)
+}
+
+// λh. λt. λc. λn. c h (t c n)
+pub fn cons() -> LambdaTerm {
+    lambda!(|h, t, c, n| c h (t c n))
+}
+
+pub fn church_list(elements: impl IntoIterator<Item = LambdaTerm>) -> LambdaTerm {
+    let elements: Vec<LambdaTerm> = elements.into_iter().collect();
+    let c = fresh_binder("c", &elements);
+    let n = fresh_binder("n", elements.iter().chain([&var(c.clone())]));
+    let body = elements
+        .into_iter()
+        .rev()
+        .fold(var(n.clone()), |rest, element| {
+            app(app(var(c.clone()), element), rest)
+        });
+    lam(c, lam(n, body))
+}
+
+// Reads back a list in normal form, giving its elements in order.
+pub fn decode_church_list(term: &LambdaTerm) -> Option<Vec<LambdaTerm>> {
+    let (c, n, mut body) = match term {
+        LambdaTerm::Abstraction {
+            bound_variable: c,
+            return_term,
+        } => match &**return_term {
+            LambdaTerm::Abstraction {
+                bound_variable: n,
+                return_term,
+            } if n != c => (c, n, &**return_term),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let mut elements = Vec::new();
+    loop {
+        match body {
+            LambdaTerm::Variable(id) if id == n => break Some(elements),
+            LambdaTerm::Application {
+                function,
+                argument: rest,
+            } => match &**function {
+                LambdaTerm::Application {
+                    function: head,
+                    argument: element,
+                } if matches!(&**head, LambdaTerm::Variable(id) if id == c) => {
+                    let free = element.free_variables();
+                    if free.contains(c) || free.contains(n) {
+                        break None;
+                    }
+                    elements.push((**element).clone());
+                    body = rest;
+                }
+                _ => break None,
+            },
+            _ => break None,
+        }
+    }
+}
+
+// `base`, primed until no term in `terms` has it free.
+fn fresh_binder<'a>(base: &str, terms: impl IntoIterator<Item = &'a LambdaTerm>) -> SmallName {
+    let mut names = NameSupply::new(terms.into_iter().flat_map(LambdaTerm::free_variables));
+    names.fresh(base)
+}
+
+// Where, inside the value being decoded, a mismatch was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    Element(usize),
+    First,
+    Second,
+}
+
+// `found` isn't the encoding of `expected`. The positions lead from the
+// whole term down to `found`, outermost first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    pub expected: String,
+    pub found: LambdaTerm,
+    pub positions: Vec<Position>,
+}
+
+impl DecodeError {
+    fn mismatch<T: Decode>(found: &LambdaTerm) -> DecodeError {
+        DecodeError {
+            expected: T::expected(),
+            found: found.clone(),
+            positions: Vec::new(),
+        }
+    }
+
+    fn within(mut self, position: Position) -> DecodeError {
+        self.positions.insert(0, position);
+        self
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}", self.expected)?;
+        for position in self.positions.iter().rev() {
+            match position {
+                Position::Element(index) => write!(f, " at element {} of a list", index)?,
+                Position::First => write!(f, " in the first component of a pair")?,
+                Position::Second => write!(f, " in the second component of a pair")?,
+            }
+        }
+        write!(f, ", found {}", self.found)
+    }
+}
+
+// Reads a β-normal term as the Church encoding of a Rust value.
+pub trait Decode: Sized {
+    // Describes the encoding, as in "a Church numeral".
+    fn expected() -> String;
+
+    fn decode(term: &LambdaTerm) -> Result<Self, DecodeError>;
+}
+
+impl Decode for u64 {
+    fn expected() -> String {
+        String::from("a Church numeral")
+    }
+
+    fn decode(term: &LambdaTerm) -> Result<u64, DecodeError> {
+        decode_church_nat(term).ok_or_else(|| DecodeError::mismatch::<u64>(term))
+    }
+}
+
+impl Decode for bool {
+    fn expected() -> String {
+        String::from("a Church boolean")
+    }
+
+    fn decode(term: &LambdaTerm) -> Result<bool, DecodeError> {
+        decode_church_bool(term).ok_or_else(|| DecodeError::mismatch::<bool>(term))
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn expected() -> String {
+        format!("a Church list of {}", T::expected())
+    }
+
+    fn decode(term: &LambdaTerm) -> Result<Vec<T>, DecodeError> {
+        let elements =
+            decode_church_list(term).ok_or_else(|| DecodeError::mismatch::<Vec<T>>(term))?;
+        elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| {
+                T::decode(element).map_err(|err| err.within(Position::Element(index)))
+            })
+            .collect()
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn expected() -> String {
+        format!("a Church pair of {} and {}", A::expected(), B::expected())
+    }
+
+    fn decode(term: &LambdaTerm) -> Result<(A, B), DecodeError> {
+        let (first, second) =
+            decode_church_pair(term).ok_or_else(|| DecodeError::mismatch::<(A, B)>(term))?;
+        Ok((
+            A::decode(&first).map_err(|err| err.within(Position::First))?,
+            B::decode(&second).map_err(|err| err.within(Position::Second))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+
+    fn normalize(term: LambdaTerm) -> LambdaTerm {
+        Rewriter::new(Strategy::TopDown, 10_000)
+            .rule(Beta)
+            .normalize(&term)
+            .unwrap()
+    }
+
+    #[test]
+    fn numerals_round_trip() {
+        for n in 0..50 {
+            assert_eq!(decode_church_nat(&church_nat(n)), Some(n));
+            assert_eq!(u64::decode(&church_nat(n)), Ok(n));
+        }
+        // The binders' names don't matter, as long as they differ.
+        let renamed = parse("λs. λz. s (s (s z))").unwrap();
+        assert_eq!(decode_church_nat(&renamed), Some(3));
+        assert_eq!(decode_church_nat(&church_nat(1_000)), Some(1_000));
+    }
+
+    #[test]
+    fn rejects_terms_that_are_not_numerals() {
+        for code in [
+            "λf. λf. f",
+            "λf. λx. x f",
+            "λf. λx. f f x",
+            "λf. λx. g x",
+            "λx. x",
+            "f x",
+        ] {
+            let term = parse(code).unwrap();
+            assert_eq!(decode_church_nat(&term), None, "{}", code);
+            let err = u64::decode(&term).unwrap_err();
+            assert_eq!(
+                err,
+                DecodeError {
+                    expected: String::from("a Church numeral"),
+                    found: term.clone(),
+                    positions: Vec::new(),
+                }
+            );
+        }
+        let err = u64::decode(&parse("λx. x").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "expected a Church numeral, found λx. x");
+        // Decoding wants the normal form, not a term that reduces to one.
+        let unreduced = app(succ(), church_nat(1));
+        assert!(u64::decode(&unreduced).is_err());
+        assert_eq!(u64::decode(&normalize(unreduced)), Ok(2));
+    }
+
+    fn evaluate<T: Decode>(function: LambdaTerm, arguments: &[LambdaTerm]) -> T {
+        let term = crate::apps(function, arguments.iter().cloned());
+        T::decode(&normalize(term)).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_u64() {
+        let n = church_nat;
+        for a in 0..5 {
+            assert_eq!(evaluate::<u64>(succ(), &[n(a)]), a + 1);
+            assert_eq!(evaluate::<u64>(pred(), &[n(a)]), a.saturating_sub(1));
+            for b in 0..4 {
+                assert_eq!(evaluate::<u64>(plus(), &[n(a), n(b)]), a + b);
+                assert_eq!(evaluate::<u64>(mult(), &[n(a), n(b)]), a * b);
+                assert_eq!(evaluate::<u64>(sub(), &[n(a), n(b)]), a.saturating_sub(b));
+            }
+        }
+        assert_eq!(evaluate::<u64>(pred(), &[n(0)]), 0);
+        assert_eq!(evaluate::<u64>(sub(), &[n(2), n(5)]), 0);
+        assert_eq!(evaluate::<u64>(exp(), &[n(2), n(3)]), 8);
+        assert_eq!(evaluate::<u64>(exp(), &[n(3), n(2)]), 9);
+        assert_eq!(evaluate::<u64>(exp(), &[n(5), n(0)]), 1);
+    }
+
+    #[test]
+    fn booleans_select_and_decode() {
+        for b in [true, false] {
+            assert_eq!(decode_church_bool(&church_bool(b)), Some(b));
+            assert_eq!(bool::decode(&church_bool(b)), Ok(b));
+            let chosen = normalize(crate::apps(
+                church_if(),
+                [church_bool(b), var("a"), var("b")],
+            ));
+            assert_eq!(chosen, var(if b { "a" } else { "b" }));
+        }
+        assert_eq!(
+            decode_church_bool(&parse("λx. λy. y").unwrap()),
+            Some(false)
+        );
+        for code in ["λt. λt. t", "λt. λf. g", "λt. t", "λt. λf. t f"] {
+            assert_eq!(decode_church_bool(&parse(code).unwrap()), None, "{}", code);
+        }
+        // 0 and false are the same term.
+        assert_eq!(bool::decode(&church_nat(0)), Ok(false));
+        assert!(bool::decode(&church_nat(1)).is_err());
+    }
+
+    #[test]
+    fn pairs_project_and_decode() {
+        let built = normalize(crate::apps(pair(), [var("a"), var("b")]));
+        assert_eq!(built, church_pair(var("a"), var("b")));
+        assert_eq!(normalize(app(fst(), built.clone())), var("a"));
+        assert_eq!(normalize(app(snd(), built.clone())), var("b"));
+        assert_eq!(decode_church_pair(&built), Some((var("a"), var("b"))));
+        // A component that uses s gets a renamed selector, and still decodes.
+        let tricky = church_pair(var("s"), church_nat(2));
+        assert_eq!(decode_church_pair(&tricky), Some((var("s"), church_nat(2))));
+        assert_eq!(normalize(app(fst(), tricky)), var("s"));
+        assert_eq!(decode_church_pair(&parse("λs. s s b").unwrap()), None);
+    }
+
+    #[test]
+    fn lists_fold_and_decode() {
+        let elements: Vec<LambdaTerm> = (1..=3).map(church_nat).collect();
+        let list = church_list(elements.clone());
+        assert_eq!(decode_church_list(&list), Some(elements.clone()));
+        assert_eq!(decode_church_list(&church_list([])), Some(Vec::new()));
+        let consed = normalize(crate::apps(
+            cons(),
+            [
+                church_nat(1),
+                crate::apps(
+                    cons(),
+                    [church_nat(2), app(app(cons(), church_nat(3)), nil())],
+                ),
+            ],
+        ));
+        assert!(consed.alpha_equivalent(&list));
+        assert!(normalize(nil()).alpha_equivalent(&church_list([])));
+        // Folding with plus and 0 sums the list.
+        assert_eq!(evaluate::<u64>(list.clone(), &[plus(), church_nat(0)]), 6);
+        // Elements that use the list's own binders get them renamed.
+        let tricky = church_list([var("c"), var("n")]);
+        assert_eq!(decode_church_list(&tricky), Some(vec![var("c"), var("n")]));
+        assert_eq!(decode_church_list(&parse("λc. λn. c n n").unwrap()), None);
+    }
+
+    #[test]
+    fn decodes_nested_values() {
+        let list = church_list((1..=3).map(church_nat));
+        assert_eq!(Vec::<u64>::decode(&list), Ok(vec![1, 2, 3]));
+        let nested = church_list([
+            church_pair(church_nat(0), church_true()),
+            church_pair(church_nat(4), church_false()),
+        ]);
+        assert_eq!(
+            Vec::<(u64, bool)>::decode(&nested),
+            Ok(vec![(0, true), (4, false)])
+        );
+
+        let bad = church_list([
+            church_pair(church_nat(0), church_true()),
+            church_pair(church_nat(4), var("oops")),
+        ]);
+        let err = Vec::<(u64, bool)>::decode(&bad).unwrap_err();
+        assert_eq!(err.expected, "a Church boolean");
+        assert_eq!(err.found, var("oops"));
+        assert_eq!(err.positions, [Position::Element(1), Position::Second]);
+        assert_eq!(
+            err.to_string(),
+            "expected a Church boolean in the second component of a pair \
+             at element 1 of a list, found oops"
+        );
+        let err = Vec::<u64>::decode(&church_nat(2)).unwrap_err();
+        assert_eq!(err.expected, "a Church list of a Church numeral");
+        assert!(err.positions.is_empty());
+    }
+}
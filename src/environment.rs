@@ -0,0 +1,270 @@
+use crate::json::{self, Json, JsonError};
+use crate::rewrite::{binders_along, RewriteError, Rewriter, Rewritten};
+use crate::visit::Order;
+use crate::{app, var, DBIndices, LambdaTerm};
+use std::collections::{BTreeMap, HashMap};
+
+// When evaluation in an environment expands a defined name, its δ-step,
+// relative to the rewriter's own rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expansion {
+    // Before any other rule, so every definition is expanded first.
+    Eager,
+    // Only where it is applied, at the point the rewriter's strategy reaches
+    // it, or once nothing else applies. With a top-down rewriter a
+    // definition that evaluation throws away is never expanded, and
+    // recursive definitions unfold only as far as they're used.
+    Lazy,
+}
+
+#[derive(Debug, Default)]
+pub struct Environment {
+    definitions: BTreeMap<String, LambdaTerm>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    pub fn define(&mut self, name: impl Into<String>, term: LambdaTerm) -> Option<LambdaTerm> {
+        self.definitions.insert(name.into(), term)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LambdaTerm> {
+        self.definitions.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<LambdaTerm> {
+        self.definitions.remove(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LambdaTerm)> {
+        self.definitions
+            .iter()
+            .map(|(name, term)| (name.as_str(), term))
+    }
+
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    // Replaces every subterm α-equivalent to a definition with the defined
+    // name, outermost first. A subterm is left alone when folding it would
+    // change its meaning: when it mentions a variable bound further out, or
+    // when the name itself is shadowed at that point.
+    pub fn fold(&self, term: &LambdaTerm) -> LambdaTerm {
+        fn alpha_key(term: &LambdaTerm) -> String {
+            DBIndices::from(term.clone()).to_string()
+        }
+
+        fn fold_mut<'a>(
+            term: &'a LambdaTerm,
+            table: &HashMap<String, (&str, &LambdaTerm)>,
+            scope: &mut Vec<&'a str>,
+        ) -> LambdaTerm {
+            if !matches!(term, LambdaTerm::Variable(_))
+                && !term
+                    .free_variables()
+                    .iter()
+                    .any(|id| scope.contains(&id.as_str()))
+            {
+                if let Some((name, _)) = table.get(&alpha_key(term)).filter(|(name, definition)| {
+                    !scope.contains(name) && term.alpha_equivalent(definition)
+                }) {
+                    return var(*name);
+                }
+            }
+            match term {
+                LambdaTerm::Variable(_) => term.clone(),
+                LambdaTerm::Application { function, argument } => LambdaTerm::Application {
+                    function: Box::new(fold_mut(function, table, scope)),
+                    argument: Box::new(fold_mut(argument, table, scope)),
+                },
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    scope.push(bound_variable);
+                    let folded = fold_mut(return_term, table, scope);
+                    scope.pop();
+                    LambdaTerm::Abstraction {
+                        bound_variable: bound_variable.clone(),
+                        return_term: Box::new(folded),
+                    }
+                }
+            }
+        }
+
+        let mut table = HashMap::new();
+        for (name, definition) in self.iter() {
+            if !matches!(definition, LambdaTerm::Variable(_)) {
+                table
+                    .entry(alpha_key(definition))
+                    .or_insert((name, definition));
+            }
+        }
+        fold_mut(term, &table, &mut Vec::new())
+    }
+
+    // δ: the leftmost outermost free occurrence of a defined name ⇒ its
+    // definition. Occurrences under a binder that would capture one of the
+    // definition's free variables are skipped, as the Rewriter skips them.
+    pub fn delta(&self, term: &LambdaTerm) -> Option<Rewritten<'static>> {
+        for (path, subterm) in term.iter_subterms(Order::Pre) {
+            let (name, definition) = match subterm {
+                LambdaTerm::Variable(name) => match self.get(name) {
+                    Some(definition) => (name, definition),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let binders = binders_along(term, &path);
+            if binders.contains(&name.as_str())
+                || definition
+                    .free_variables()
+                    .iter()
+                    .any(|id| binders.contains(&id.as_str()))
+            {
+                continue;
+            }
+            let mut expanded = term.clone();
+            let _ = expanded.replace(&path, definition.clone());
+            return Some(Rewritten {
+                rule: "δ",
+                path,
+                term: expanded,
+            });
+        }
+        None
+    }
+
+    // One step of `rewriter` or one δ-step, whichever `expansion` puts first.
+    pub fn step<'r>(
+        &self,
+        rewriter: &'r Rewriter,
+        term: &LambdaTerm,
+        expansion: Expansion,
+    ) -> Option<Rewritten<'r>> {
+        match expansion {
+            Expansion::Eager => self.delta(term).or_else(|| rewriter.step(term)),
+            // A defined name applied to an argument is expanded where the
+            // rules would find a redex, in the same traversal, so that δ
+            // keeps the rewriter's order. Other occurrences wait until
+            // nothing else applies.
+            Expansion::Lazy => rewriter
+                .step_with(term, |path, subterm| match subterm {
+                    LambdaTerm::Application { function, argument } => match &**function {
+                        LambdaTerm::Variable(name)
+                            if !binders_along(term, path).contains(&name.as_str()) =>
+                        {
+                            let definition = self.get(name)?;
+                            Some(("δ", app(definition.clone(), (**argument).clone())))
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .or_else(|| self.delta(term)),
+        }
+    }
+
+    // Steps until neither the rewriter's rules nor δ apply, so the result
+    // mentions no defined name free, except where expanding it would capture.
+    // δ-steps count towards the rewriter's limit.
+    pub fn normalize(
+        &self,
+        rewriter: &Rewriter,
+        term: &LambdaTerm,
+        expansion: Expansion,
+    ) -> Result<LambdaTerm, RewriteError> {
+        let mut term = term.clone();
+        for _ in 0..rewriter.limit {
+            match self.step(rewriter, &term, expansion) {
+                Some(rewritten) => term = rewritten.term,
+                None => return Ok(term),
+            }
+        }
+        match self.step(rewriter, &term, expansion) {
+            Some(_) => Err(RewriteError::StepLimit(rewriter.limit)),
+            None => Ok(term),
+        }
+    }
+
+    // Dumps look like `{"definitions":{"I":{"Abstraction":...},...}}`.
+    pub fn to_json(&self) -> String {
+        Json::Object(vec![(
+            String::from("definitions"),
+            Json::Object(
+                self.definitions
+                    .iter()
+                    .map(|(name, term)| (name.clone(), term.to_json()))
+                    .collect(),
+            ),
+        )])
+        .to_string()
+    }
+
+    pub fn from_json(text: &str) -> Result<Environment, JsonError> {
+        let dump = json::parse(text)?;
+        let definitions = dump
+            .get("definitions")
+            .and_then(Json::as_object)
+            .ok_or_else(|| JsonError::InvalidShape(dump.to_string()))?;
+        let mut environment = Environment::new();
+        for (name, term) in definitions {
+            environment.define(name.clone(), LambdaTerm::from_json(term)?);
+        }
+        Ok(environment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::rewrite::{Beta, Strategy};
+
+    fn environment() -> Environment {
+        let mut environment = Environment::new();
+        environment.define("K", parse("λx. λy. x").unwrap());
+        environment.define("I", parse("λx. x").unwrap());
+        environment.define("Ω", parse("(λx. x x) (λx. x x)").unwrap());
+        environment.define("loop", parse("loop").unwrap());
+        environment
+    }
+
+    fn normalize(code: &str, expansion: Expansion) -> Result<LambdaTerm, RewriteError> {
+        let rewriter = Rewriter::new(Strategy::TopDown, 200).rule(Beta);
+        environment().normalize(&rewriter, &parse(code).unwrap(), expansion)
+    }
+
+    #[test]
+    fn lazy_expansion_keeps_normal_order() {
+        for expansion in [Expansion::Eager, Expansion::Lazy] {
+            let term = normalize("K a ((λx. x x) (λx. x x))", expansion).unwrap();
+            assert_eq!(term, parse("a").unwrap());
+            let term = normalize("K I Ω", expansion).unwrap();
+            assert_eq!(term, parse("λx. x").unwrap());
+        }
+    }
+
+    #[test]
+    fn lazy_expansion_skips_discarded_definitions() {
+        let term = normalize("K I loop", Expansion::Lazy).unwrap();
+        assert_eq!(term, parse("λx. x").unwrap());
+        assert!(normalize("K I loop", Expansion::Eager).is_err());
+    }
+
+    #[test]
+    fn bound_names_are_not_expanded() {
+        for expansion in [Expansion::Eager, Expansion::Lazy] {
+            let term = normalize("λK. K a I", expansion).unwrap();
+            assert_eq!(term, parse("λK. K a (λx. x)").unwrap());
+        }
+    }
+}
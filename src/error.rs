@@ -0,0 +1,310 @@
+use crate::blc::BlcError;
+use crate::diagnostic::Diagnostic;
+use crate::json::JsonError;
+use crate::pipeline::PipelineError;
+use crate::rewrite::RewriteError;
+use crate::store::StoreError;
+use crate::ParserError;
+use std::error::Error;
+use std::fmt;
+
+// Any of the crate's failures, so that an application can use `?` across
+// parsing, decoding and evaluation and still match on what went wrong.
+#[derive(Debug)]
+pub enum LambdaError {
+    Parser(ParserError),
+    Rewrite(RewriteError),
+    Store(StoreError),
+    Blc(BlcError),
+    Json(JsonError),
+    Pipeline(PipelineError),
+}
+
+impl LambdaError {
+    // Stable across releases, unlike the messages.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LambdaError::Parser(err) => err.code(),
+            LambdaError::Rewrite(err) => err.code(),
+            LambdaError::Store(err) => err.code(),
+            LambdaError::Blc(err) => err.code(),
+            LambdaError::Json(err) => err.code(),
+            LambdaError::Pipeline(err) => err.code(),
+        }
+    }
+}
+
+// Only says which part failed; the details are left to `source()` so that
+// reporters walking the chain don't print them twice.
+impl fmt::Display for LambdaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LambdaError::Parser(_) => write!(f, "parse error"),
+            LambdaError::Rewrite(_) => write!(f, "rewrite error"),
+            LambdaError::Store(_) => write!(f, "evaluation error"),
+            LambdaError::Blc(_) => write!(f, "BLC decoding error"),
+            LambdaError::Json(_) => write!(f, "JSON error"),
+            LambdaError::Pipeline(_) => write!(f, "pipeline error"),
+        }
+    }
+}
+
+impl Error for LambdaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LambdaError::Parser(err) => Some(err),
+            LambdaError::Rewrite(err) => Some(err),
+            LambdaError::Store(err) => Some(err),
+            LambdaError::Blc(err) => Some(err),
+            LambdaError::Json(err) => Some(err),
+            LambdaError::Pipeline(err) => Some(err),
+        }
+    }
+}
+
+impl From<ParserError> for LambdaError {
+    fn from(err: ParserError) -> LambdaError {
+        LambdaError::Parser(err)
+    }
+}
+
+impl From<RewriteError> for LambdaError {
+    fn from(err: RewriteError) -> LambdaError {
+        LambdaError::Rewrite(err)
+    }
+}
+
+impl From<StoreError> for LambdaError {
+    fn from(err: StoreError) -> LambdaError {
+        LambdaError::Store(err)
+    }
+}
+
+impl From<BlcError> for LambdaError {
+    fn from(err: BlcError) -> LambdaError {
+        LambdaError::Blc(err)
+    }
+}
+
+impl From<JsonError> for LambdaError {
+    fn from(err: JsonError) -> LambdaError {
+        LambdaError::Json(err)
+    }
+}
+
+impl From<PipelineError> for LambdaError {
+    fn from(err: PipelineError) -> LambdaError {
+        LambdaError::Pipeline(err)
+    }
+}
+
+impl From<&LambdaError> for Diagnostic {
+    fn from(err: &LambdaError) -> Diagnostic {
+        // The innermost error has the specific message.
+        let mut inner: &dyn Error = err;
+        while let Some(source) = inner.source() {
+            inner = source;
+        }
+        Diagnostic {
+            code: err.code(),
+            message: inner.to_string(),
+            span: None,
+        }
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl Error for ParserError {}
+
+impl RewriteError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RewriteError::StepLimit(_) => "step-limit",
+        }
+    }
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::StepLimit(limit) => {
+                write!(f, "no normal form found within {} steps", limit)
+            }
+        }
+    }
+}
+
+impl Error for RewriteError {}
+
+impl StoreError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            StoreError::StepLimit(_) => "step-limit",
+        }
+    }
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::StepLimit(limit) => {
+                write!(f, "no normal form found within {} steps", limit)
+            }
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+impl BlcError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            BlcError::PrematureEnd => "blc-premature-end",
+            BlcError::InvalidBit(..) => "blc-invalid-bit",
+            BlcError::IndexOutOfScope { .. } => "blc-index-out-of-scope",
+            BlcError::TrailingBits(_) => "blc-trailing-bits",
+        }
+    }
+}
+
+impl fmt::Display for BlcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlcError::PrematureEnd => write!(f, "the bits ended in the middle of a term"),
+            BlcError::InvalidBit(ch, position) => {
+                write!(f, "{:?} at byte {} isn't a bit", ch, position)
+            }
+            BlcError::IndexOutOfScope {
+                index,
+                abstraction_depth,
+            } => write!(
+                f,
+                "index {} refers past the {} enclosing abstractions",
+                index, abstraction_depth
+            ),
+            BlcError::TrailingBits(position) => {
+                write!(f, "bits left over after the term ends at bit {}", position)
+            }
+        }
+    }
+}
+
+impl Error for BlcError {}
+
+impl JsonError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            JsonError::PrematureEnd => "json-premature-end",
+            JsonError::UnexpectedChar(..) => "json-unexpected-char",
+            JsonError::InvalidNumber(_) => "json-invalid-number",
+            JsonError::InvalidEscape(_) => "json-invalid-escape",
+            JsonError::TrailingCharacters(_) => "json-trailing-characters",
+            JsonError::InvalidShape(_) => "json-invalid-shape",
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::PrematureEnd => write!(f, "the JSON ended in the middle of a value"),
+            JsonError::UnexpectedChar(ch, position) => {
+                write!(f, "unexpected {:?} at byte {}", ch, position)
+            }
+            JsonError::InvalidNumber(position) => write!(f, "invalid number at byte {}", position),
+            JsonError::InvalidEscape(position) => write!(f, "invalid escape at byte {}", position),
+            JsonError::TrailingCharacters(position) => {
+                write!(
+                    f,
+                    "characters left over after the value at byte {}",
+                    position
+                )
+            }
+            JsonError::InvalidShape(json) => write!(f, "{} doesn't encode a term", json),
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+impl PipelineError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PipelineError::Parser(err) => err.code(),
+            PipelineError::Mismatch(_) => "pipeline-mismatch",
+            PipelineError::Unresolved(_) => "unresolved-name",
+            PipelineError::StepLimit(_) => "step-limit",
+            PipelineError::NotANumeral => "not-a-numeral",
+        }
+    }
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Parser(_) => write!(f, "parse error"),
+            PipelineError::Mismatch(stage) => {
+                write!(f, "the {} stage can't take the previous artifact", stage)
+            }
+            PipelineError::Unresolved(name) => write!(f, "{} isn't defined", name),
+            PipelineError::StepLimit(limit) => {
+                write!(f, "no normal form found within {} steps", limit)
+            }
+            PipelineError::NotANumeral => write!(f, "the result isn't a Church numeral"),
+        }
+    }
+}
+
+impl Error for PipelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PipelineError::Parser(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+
+    fn lambda_error(source: &str) -> LambdaError {
+        crate::parse(source).unwrap_err().into()
+    }
+
+    #[test]
+    fn wrappers_leave_the_details_to_their_source() {
+        let err = lambda_error("λx.");
+        let source = err.source().unwrap().to_string();
+        assert_eq!(err.to_string(), "parse error");
+        assert!(!err.to_string().contains(&source));
+        assert_eq!(err.code(), "premature-end");
+        let diagnostic = Diagnostic::from(&err);
+        assert_eq!(diagnostic.message, source);
+    }
+
+    #[test]
+    fn pipeline_errors_convert() {
+        let run = Pipeline::new().parse().convert().run("(λx. x");
+        let err = LambdaError::from(run.error.unwrap());
+        assert_eq!(err.code(), "unmatched-parens");
+        let pipeline = err.source().unwrap();
+        assert_eq!(pipeline.to_string(), "parse error");
+        let parser = pipeline.source().unwrap();
+        assert_eq!(Diagnostic::from(&err).message, parser.to_string());
+
+        let run = Pipeline::new().convert().run("λx. x");
+        let err = LambdaError::from(run.error.unwrap());
+        assert_eq!(err.code(), "pipeline-mismatch");
+        assert_eq!(
+            Diagnostic::from(&err).message,
+            "the convert stage can't take the previous artifact"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::names::NameSupply;
+use crate::small::SmallName;
+use crate::systemf::Type;
+use crate::{app, lam, var, LambdaTerm};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EtaError {
+    // The term has a β-redex; normalize it first.
+    NotNormal,
+    UnboundVariable(String),
+    // A variable was applied to more arguments than its type takes.
+    TooManyArguments(String),
+    // An abstraction stands where the type calls for a base type.
+    UnexpectedAbstraction(Type),
+    Mismatch { expected: Type, found: Type },
+    // Only simple types are supported, not ∀.
+    Polymorphic(Type),
+}
+
+// A variable applied to zero or more arguments.
+fn spine(term: &LambdaTerm) -> Result<(&str, Vec<&LambdaTerm>), EtaError> {
+    let mut arguments = Vec::new();
+    let mut term = term;
+    loop {
+        match term {
+            LambdaTerm::Variable(head) => {
+                arguments.reverse();
+                return Ok((head, arguments));
+            }
+            LambdaTerm::Application { function, argument } => {
+                arguments.push(&**argument);
+                term = function;
+            }
+            LambdaTerm::Abstraction { .. } => return Err(EtaError::NotNormal),
+        }
+    }
+}
+
+struct Expander<'a> {
+    context: &'a HashMap<String, Type>,
+    // Binders enclosing the current subterm, innermost last.
+    scope: Vec<(SmallName, Type)>,
+}
+
+impl<'a> Expander<'a> {
+    fn lookup(&self, id: &str) -> Result<Type, EtaError> {
+        self.scope
+            .iter()
+            .rev()
+            .find(|(name, _)| name == id)
+            .map(|(_, ty)| ty)
+            .or_else(|| self.context.get(id))
+            .cloned()
+            .ok_or_else(|| EtaError::UnboundVariable(String::from(id)))
+    }
+
+    // The type of a variable applied to arguments, taking the arguments'
+    // types on trust; expanding them checks them.
+    fn infer(&self, term: &LambdaTerm) -> Result<Type, EtaError> {
+        let (head, arguments) = spine(term)?;
+        let mut head_type = self.lookup(head)?;
+        for _ in arguments {
+            head_type = match head_type {
+                Type::Function { codomain, .. } => *codomain,
+                Type::Forall { .. } => return Err(EtaError::Polymorphic(head_type)),
+                Type::Variable(_) => return Err(EtaError::TooManyArguments(String::from(head))),
+            };
+        }
+        Ok(head_type)
+    }
+
+    fn fresh(&self, term: &LambdaTerm) -> SmallName {
+        let mut names = NameSupply::new(term.free_variables());
+        for (name, _) in &self.scope {
+            names.avoid(name.clone());
+        }
+        for name in self.context.keys() {
+            names.avoid(name.clone());
+        }
+        names.fresh("x")
+    }
+
+    fn expand(&mut self, term: &LambdaTerm, ty: &Type) -> Result<LambdaTerm, EtaError> {
+        match ty {
+            Type::Forall { .. } => Err(EtaError::Polymorphic(ty.clone())),
+            Type::Function { domain, codomain } => {
+                let (bound_variable, body) = match term {
+                    LambdaTerm::Abstraction {
+                        bound_variable,
+                        return_term,
+                    } => (bound_variable.clone(), (**return_term).clone()),
+                    _ => {
+                        let found = self.infer(term)?;
+                        if found != *ty {
+                            return Err(EtaError::Mismatch {
+                                expected: ty.clone(),
+                                found,
+                            });
+                        }
+                        let fresh = self.fresh(term);
+                        (fresh.clone(), app(term.clone(), var(fresh)))
+                    }
+                };
+                self.scope
+                    .push((bound_variable.clone(), (**domain).clone()));
+                let body = self.expand(&body, codomain);
+                self.scope.pop();
+                Ok(lam(bound_variable, body?))
+            }
+            Type::Variable(_) => {
+                if let LambdaTerm::Abstraction { .. } = term {
+                    return Err(EtaError::UnexpectedAbstraction(ty.clone()));
+                }
+                let (head, arguments) = spine(term)?;
+                let mut head_type = self.lookup(head)?;
+                let mut expanded = var(head);
+                for argument in arguments {
+                    let (domain, codomain) = match head_type {
+                        Type::Function { domain, codomain } => (*domain, *codomain),
+                        Type::Forall { .. } => return Err(EtaError::Polymorphic(head_type)),
+                        Type::Variable(_) => {
+                            return Err(EtaError::TooManyArguments(String::from(head)))
+                        }
+                    };
+                    expanded = app(expanded, self.expand(argument, &domain)?);
+                    head_type = codomain;
+                }
+                if head_type == *ty {
+                    Ok(expanded)
+                } else {
+                    Err(EtaError::Mismatch {
+                        expected: ty.clone(),
+                        found: head_type,
+                    })
+                }
+            }
+        }
+    }
+}
+
+// The η-long form of a β-normal term of type `ty`, with `context` giving the
+// types of its free variables: every variable is applied to as many
+// arguments as its type takes, and every subterm of function type is an
+// abstraction, so `f` at `o → o` becomes `λx. f x`. Two simply typed terms
+// are βη-equal exactly when their β-normal η-long forms are α-equivalent.
+pub fn long_normal_form(
+    term: &LambdaTerm,
+    ty: &Type,
+    context: &HashMap<String, Type>,
+) -> Result<LambdaTerm, EtaError> {
+    Expander {
+        context,
+        scope: Vec::new(),
+    }
+    .expand(term, ty)
+}
+
+// η-expands each occurrence of a variable listed in `arities` until it is
+// applied to that many arguments, for when only how many arguments each
+// symbol takes is known and not a full type. Unlisted variables, including
+// the binders introduced by expansion, are left as they are.
+pub fn expand_arities(
+    term: &LambdaTerm,
+    arities: &HashMap<String, usize>,
+) -> Result<LambdaTerm, EtaError> {
+    fn expand_arities_mut(
+        term: &LambdaTerm,
+        arities: &HashMap<String, usize>,
+        bound: &mut Vec<SmallName>,
+    ) -> Result<LambdaTerm, EtaError> {
+        if let LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } = term
+        {
+            bound.push(bound_variable.clone());
+            let body = expand_arities_mut(return_term, arities, bound);
+            bound.pop();
+            return Ok(lam(bound_variable, body?));
+        }
+        let (head, arguments) = spine(term)?;
+        let mut expanded = var(head);
+        for argument in &arguments {
+            expanded = app(expanded, expand_arities_mut(argument, arities, bound)?);
+        }
+        // A binder of the same name hides the symbol.
+        let arity = match arities.get(head) {
+            Some(&arity) if !bound.iter().any(|id| id == head) => arity,
+            _ => return Ok(expanded),
+        };
+        let mut names = NameSupply::new(expanded.free_variables());
+        for id in bound.iter() {
+            names.avoid(id);
+        }
+        for id in arities.keys() {
+            names.avoid(id);
+        }
+        let fresh: Vec<SmallName> = (arguments.len()..arity).map(|_| names.fresh("x")).collect();
+        for id in &fresh {
+            expanded = app(expanded, var(id));
+        }
+        Ok(fresh.iter().rev().fold(expanded, |body, id| lam(id, body)))
+    }
+    expand_arities_mut(term, arities, &mut Vec::new())
+}
@@ -0,0 +1,421 @@
+use crate::path::Path;
+use crate::small::SmallName;
+use crate::visit::Order;
+use crate::{DBIndices, DBTerm, LambdaTerm};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// A source of random numbers, so that callers can drive the generators with
+// whatever generator their test framework provides.
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+
+    // A number below `n`, which must be positive. The slight bias of taking
+    // a remainder doesn't matter for generating test cases.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// Steele, Lea and Flood's SplitMix64: small, fast, and good enough to
+// generate test cases from a seed.
+#[derive(Debug, Clone)]
+pub struct SplitMix64(pub u64);
+
+impl RandomSource for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+// Binders are named after their depth, with one in four reusing an outer
+// binder's name so that shadowing gets exercised too.
+fn binder_name<R: RandomSource>(scope: &[SmallName], rng: &mut R) -> SmallName {
+    if !scope.is_empty() && rng.below(4) == 0 {
+        scope[rng.below(scope.len())].clone()
+    } else {
+        SmallName::from(format!("x{}", scope.len()))
+    }
+}
+
+// A random closed term with exactly `size` nodes, counted as
+// LambdaTerm::size counts them. Sizes below 2 are raised to 2, the smallest
+// closed term being λx. x.
+pub fn gen_closed_term<R: RandomSource>(size: usize, rng: &mut R) -> LambdaTerm {
+    fn gen_mut<R: RandomSource>(
+        size: usize,
+        scope: &mut Vec<SmallName>,
+        rng: &mut R,
+    ) -> LambdaTerm {
+        // With nothing in scope every leaf needs an abstraction above it, so
+        // an application needs at least two nodes on each side.
+        let smallest = if scope.is_empty() { 2 } else { 1 };
+        if size == 1 {
+            // Only a name that isn't shadowed can be chosen, so the variable
+            // is bound by the binder picked.
+            let visible: Vec<&SmallName> = scope
+                .iter()
+                .enumerate()
+                .filter(|(i, name)| !scope[i + 1..].contains(name))
+                .map(|(_, name)| name)
+                .collect();
+            return LambdaTerm::Variable(visible[rng.below(visible.len())].clone());
+        }
+        let can_apply = size > 2 * smallest;
+        if can_apply && rng.below(2) == 0 {
+            let function_size = smallest + rng.below(size - 2 * smallest);
+            let function = gen_mut(function_size, scope, rng);
+            let argument = gen_mut(size - 1 - function_size, scope, rng);
+            LambdaTerm::Application {
+                function: Box::new(function),
+                argument: Box::new(argument),
+            }
+        } else {
+            let bound_variable = binder_name(scope, rng);
+            scope.push(bound_variable.clone());
+            let return_term = gen_mut(size - 1, scope, rng);
+            scope.pop();
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term: Box::new(return_term),
+            }
+        }
+    }
+    gen_mut(size.max(2), &mut Vec::new(), rng)
+}
+
+// Strictly smaller variants of `term`, the most drastic first, each closed
+// if `term` is. They are the term's closed proper subterms, then the term
+// with one of its subterms replaced by a variable bound above it.
+pub fn shrink(term: &LambdaTerm) -> Vec<LambdaTerm> {
+    let closed = term.free_variables().is_empty();
+    let mut candidates: Vec<LambdaTerm> = term
+        .iter_subterms(Order::Pre)
+        .skip(1)
+        .map(|(_, subterm)| subterm.clone())
+        .filter(|subterm| !closed || subterm.free_variables().is_empty())
+        .collect();
+    let compound: Vec<(Path, usize)> = term
+        .iter_subterms(Order::Pre)
+        .filter(|(_, subterm)| !matches!(subterm, LambdaTerm::Variable(_)))
+        .map(|(path, subterm)| (path, subterm.size()))
+        .collect();
+    for (path, _) in compound {
+        if let Some(binder) = innermost_binder(term, &path) {
+            let mut smaller = term.clone();
+            let _ = smaller.replace(&path, LambdaTerm::Variable(binder));
+            candidates.push(smaller);
+        }
+    }
+    candidates.sort_by_key(LambdaTerm::size);
+    candidates.dedup();
+    candidates
+}
+
+fn innermost_binder(term: &LambdaTerm, path: &Path) -> Option<SmallName> {
+    let mut binder = None;
+    let mut term = term;
+    for step in &path.0 {
+        if let LambdaTerm::Abstraction { bound_variable, .. } = term {
+            binder = Some(bound_variable.clone());
+        }
+        term = term.get(&Path(vec![*step]))?;
+    }
+    binder
+}
+
+// Shrinks `term` for as long as some smaller variant still `fails`, giving
+// the smallest failing term found. Use it to cut a failing generated case
+// down to something readable.
+pub fn minimize(term: &LambdaTerm, fails: impl Fn(&LambdaTerm) -> bool) -> LambdaTerm {
+    let mut term = term.clone();
+    while let Some(smaller) = shrink(&term).into_iter().find(|candidate| fails(candidate)) {
+        term = smaller;
+    }
+    term
+}
+
+// Every closed term with at most `max_size` nodes, in de Bruijn form, as
+// DBTerm::size counts them. Terms come smallest first, and within a size
+// variables before abstractions before applications, so the sequence is the
+// same on every run. The count grows exponentially with the size: there are
+// 707 closed terms of size 8 or less and 753,908 of size 13 or less.
+pub fn closed_terms(max_size: usize) -> ClosedTerms {
+    ClosedTerms {
+        max_size,
+        size: 1,
+        current: Rc::new(Vec::new()),
+        next: 0,
+        memo: HashMap::new(),
+    }
+}
+
+pub struct ClosedTerms {
+    max_size: usize,
+    size: usize,
+    current: Rc<Vec<DBTerm>>,
+    next: usize,
+    // All terms of a size whose free indices are at most a depth, keyed by
+    // (size, depth), shared by the larger terms built from them.
+    memo: HashMap<(usize, usize), Rc<Vec<DBTerm>>>,
+}
+
+impl ClosedTerms {
+    fn terms(&mut self, size: usize, depth: usize) -> Rc<Vec<DBTerm>> {
+        if let Some(terms) = self.memo.get(&(size, depth)) {
+            return Rc::clone(terms);
+        }
+        let mut terms = Vec::new();
+        if size == 1 {
+            terms.extend((1..=depth).map(DBTerm::Variable));
+        } else {
+            for body in self.terms(size - 1, depth + 1).iter() {
+                terms.push(DBTerm::Abstraction(Box::new(body.clone())));
+            }
+            for function_size in 1..size - 1 {
+                let functions = self.terms(function_size, depth);
+                let arguments = self.terms(size - 1 - function_size, depth);
+                for function in functions.iter() {
+                    for argument in arguments.iter() {
+                        terms.push(DBTerm::Application {
+                            function: Box::new(function.clone()),
+                            argument: Box::new(argument.clone()),
+                        });
+                    }
+                }
+            }
+        }
+        let terms = Rc::new(terms);
+        self.memo.insert((size, depth), Rc::clone(&terms));
+        terms
+    }
+}
+
+impl Iterator for ClosedTerms {
+    type Item = DBIndices;
+
+    fn next(&mut self) -> Option<DBIndices> {
+        while self.next == self.current.len() {
+            if self.size > self.max_size {
+                return None;
+            }
+            self.current = self.terms(self.size, 0);
+            self.next = 0;
+            self.size += 1;
+        }
+        self.next += 1;
+        Some(DBIndices(self.current[self.next - 1].clone()))
+    }
+}
+
+// proptest support: Arbitrary for LambdaTerm and DBIndices, drawing closed
+// terms from gen_closed_term and shrinking them with `shrink`.
+#[cfg(feature = "proptest")]
+pub mod strategy {
+    use super::{gen_closed_term, shrink, RandomSource};
+    use crate::{DBIndices, LambdaTerm};
+    use proptest::arbitrary::Arbitrary;
+    use proptest::prelude::Rng;
+    use proptest::strategy::{Map, NewTree, Strategy, ValueTree};
+    use proptest::test_runner::{TestRng, TestRunner};
+    use std::ops::RangeInclusive;
+    use std::vec;
+
+    // The sizes `any::<LambdaTerm>()` picks from.
+    pub const DEFAULT_SIZES: RangeInclusive<usize> = 2..=32;
+
+    impl RandomSource for TestRng {
+        fn next_u64(&mut self) -> u64 {
+            Rng::next_u64(self)
+        }
+    }
+
+    // Closed terms with a size drawn from `sizes`.
+    pub fn closed_term(sizes: RangeInclusive<usize>) -> ClosedTermStrategy {
+        ClosedTermStrategy { sizes }
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct ClosedTermStrategy {
+        sizes: RangeInclusive<usize>,
+    }
+
+    impl Strategy for ClosedTermStrategy {
+        type Tree = ClosedTermTree;
+        type Value = LambdaTerm;
+
+        fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+            let rng = runner.rng();
+            let (low, high) = (*self.sizes.start(), *self.sizes.end());
+            let size = low + rng.below(high.saturating_sub(low) + 1);
+            Ok(ClosedTermTree::new(gen_closed_term(size, rng)))
+        }
+    }
+
+    // Shrinks by trying the candidates `shrink` gives for the current term in
+    // turn, moving on to a candidate's own candidates whenever it still fails.
+    #[derive(Debug, Clone)]
+    pub struct ClosedTermTree {
+        current: LambdaTerm,
+        candidates: vec::IntoIter<LambdaTerm>,
+        // The term `current` was shrunk from and its untried candidates, kept
+        // until the test says whether `current` still fails.
+        parent: Option<(LambdaTerm, vec::IntoIter<LambdaTerm>)>,
+    }
+
+    impl ClosedTermTree {
+        fn new(term: LambdaTerm) -> ClosedTermTree {
+            ClosedTermTree {
+                candidates: shrink(&term).into_iter(),
+                current: term,
+                parent: None,
+            }
+        }
+    }
+
+    impl ValueTree for ClosedTermTree {
+        type Value = LambdaTerm;
+
+        fn current(&self) -> LambdaTerm {
+            self.current.clone()
+        }
+
+        fn simplify(&mut self) -> bool {
+            match self.candidates.next() {
+                Some(candidate) => {
+                    let parent = std::mem::replace(self, ClosedTermTree::new(candidate));
+                    self.parent = Some((parent.current, parent.candidates));
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn complicate(&mut self) -> bool {
+            match self.parent.take() {
+                Some((current, candidates)) => {
+                    self.current = current;
+                    self.candidates = candidates;
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    impl Arbitrary for LambdaTerm {
+        type Parameters = ();
+        type Strategy = ClosedTermStrategy;
+
+        fn arbitrary_with(_: ()) -> ClosedTermStrategy {
+            closed_term(DEFAULT_SIZES)
+        }
+    }
+
+    impl Arbitrary for DBIndices {
+        type Parameters = ();
+        type Strategy = Map<ClosedTermStrategy, fn(LambdaTerm) -> DBIndices>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            closed_term(DEFAULT_SIZES).prop_map(DBIndices::from)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NameScheme;
+    use std::collections::HashSet;
+
+    #[test]
+    fn generates_closed_terms_of_the_requested_size() {
+        let mut rng = SplitMix64(362);
+        for i in 0..5000 {
+            let size = 2 + i % 40;
+            let term = gen_closed_term(size, &mut rng);
+            assert_eq!(term.size(), size, "{}", term);
+            assert!(term.free_variables().is_empty(), "{}", term);
+            let named = DBIndices::from(term.clone()).to_named(NameScheme::Numbered);
+            assert!(named.alpha_equivalent(&term), "{}", term);
+        }
+        assert_eq!(gen_closed_term(0, &mut rng).size(), 2);
+    }
+
+    #[test]
+    fn shrinks_to_smaller_closed_terms() {
+        let mut rng = SplitMix64(363);
+        for i in 0..500 {
+            let term = gen_closed_term(2 + i % 20, &mut rng);
+            for smaller in shrink(&term) {
+                assert!(smaller.size() < term.size(), "{} to {}", term, smaller);
+                assert!(
+                    smaller.free_variables().is_empty(),
+                    "{} to {}",
+                    term,
+                    smaller
+                );
+            }
+        }
+        let term = gen_closed_term(30, &mut rng);
+        let minimal = minimize(&term, |term| term.size() >= 5);
+        assert_eq!(minimal.size(), 5);
+    }
+
+    #[test]
+    fn enumerates_every_closed_term_once() {
+        let terms: Vec<DBIndices> = closed_terms(8).collect();
+        assert_eq!(terms.len(), 707);
+        assert_eq!(terms.iter().collect::<HashSet<_>>().len(), 707);
+        for DBIndices(term) in &terms {
+            assert!(term.size() <= 8);
+            // Closed, so there's no free index for lowering to push below 1.
+            assert!(DBIndices(term.clone()).shift(-1, 0).is_some());
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    mod strategy {
+        use super::super::strategy::closed_term;
+        use crate::{DBIndices, LambdaTerm, NameScheme};
+        use proptest::prelude::*;
+        use proptest::strategy::ValueTree;
+        use proptest::test_runner::TestRunner;
+
+        proptest! {
+            #[test]
+            fn de_bruijn_round_trips(term in any::<LambdaTerm>()) {
+                let named = DBIndices::from(term.clone()).to_named(NameScheme::Numbered);
+                prop_assert!(named.alpha_equivalent(&term));
+            }
+
+            #[test]
+            fn stays_within_the_sizes(term in closed_term(5..=9)) {
+                prop_assert!((5..=9).contains(&term.size()));
+                prop_assert!(term.free_variables().is_empty());
+            }
+        }
+
+        #[test]
+        fn shrinks_failures_to_the_smallest_term() {
+            let mut runner = TestRunner::deterministic();
+            let result = runner.run(&closed_term(20..=30), |term| {
+                prop_assert!(term.size() < 5);
+                Ok(())
+            });
+            match result {
+                Err(proptest::test_runner::TestError::Fail(_, term)) => {
+                    assert_eq!(term.size(), 5, "{}", term)
+                }
+                other => panic!("expected a failure, got {:?}", other),
+            }
+            let mut tree = closed_term(10..=10).new_tree(&mut runner).unwrap();
+            while tree.simplify() {}
+            assert!(tree.current().size() < 10);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::lift::{lift, SupercombinatorProgram};
+use crate::names::{Decoration, NameSupply};
+use crate::rewrite::RewriteError;
+use crate::small::SmallName;
+use crate::visit::Order;
+use crate::{app, lam, var, LambdaTerm};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Application(usize, usize),
+    Supercombinator(usize),
+    Variable(SmallName),
+    // Left where a redex was, pointing to its result, so that everything
+    // sharing the redex sees it reduced.
+    Indirection(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramError {
+    // A body still has an abstraction in it: the named supercombinator's,
+    // or main's for None.
+    Abstraction(Option<SmallName>),
+}
+
+fn has_abstraction(term: &LambdaTerm) -> bool {
+    term.iter_subterms(Order::Pre)
+        .any(|(_, subterm)| matches!(subterm, LambdaTerm::Abstraction { .. }))
+}
+
+// A template instantiation machine: the program is a graph in `heap`, and a
+// supercombinator applied to enough arguments is reduced by building a
+// fresh copy of its body with the parameters pointing at the argument
+// graphs, which are shared rather than copied. That makes evaluation lazy,
+// reducing each argument at most once, where substitution on trees copies
+// the argument into every place it's used and reduces each copy anew.
+pub struct Machine<'p> {
+    program: &'p SupercombinatorProgram,
+    heap: Vec<Node>,
+    // The one node for each supercombinator, by name.
+    globals: HashMap<&'p str, usize>,
+    names: NameSupply,
+    pub steps: usize,
+    pub limit: usize,
+}
+
+impl<'p> Machine<'p> {
+    // `limit` caps the supercombinator reductions made.
+    pub fn new(
+        program: &'p SupercombinatorProgram,
+        limit: usize,
+    ) -> Result<Machine<'p>, ProgramError> {
+        // instantiate has nothing to build an abstraction from; lift never
+        // leaves one, but a program can be put together by hand.
+        if has_abstraction(&program.main) {
+            return Err(ProgramError::Abstraction(None));
+        }
+        if let Some(definition) = program
+            .definitions
+            .iter()
+            .find(|definition| has_abstraction(&definition.body))
+        {
+            return Err(ProgramError::Abstraction(Some(definition.name.clone())));
+        }
+        let mut used = program.main.free_variables();
+        for definition in &program.definitions {
+            used.insert(definition.name.clone());
+            used.extend(definition.parameters.iter().cloned());
+            used.extend(definition.body.free_variables());
+        }
+        let mut machine = Machine {
+            program,
+            heap: Vec::new(),
+            globals: HashMap::new(),
+            names: NameSupply::with_decoration(used, Decoration::Subscripts),
+            steps: 0,
+            limit,
+        };
+        for (i, definition) in program.definitions.iter().enumerate() {
+            let node = machine.alloc(Node::Supercombinator(i));
+            machine.globals.insert(&definition.name, node);
+        }
+        Ok(machine)
+    }
+
+    fn alloc(&mut self, node: Node) -> usize {
+        self.heap.push(node);
+        self.heap.len() - 1
+    }
+
+    fn resolve(&self, mut address: usize) -> usize {
+        while let Node::Indirection(next) = self.heap[address] {
+            address = next;
+        }
+        address
+    }
+
+    fn instantiate(&mut self, term: &LambdaTerm, environment: &[(&str, usize)]) -> usize {
+        match term {
+            LambdaTerm::Variable(id) => {
+                match environment.iter().rev().find(|(name, _)| name == id) {
+                    Some(&(_, address)) => address,
+                    None => match self.globals.get(id.as_str()) {
+                        Some(&address) => address,
+                        None => self.alloc(Node::Variable(id.clone())),
+                    },
+                }
+            }
+            LambdaTerm::Application { function, argument } => {
+                let function = self.instantiate(function, environment);
+                let argument = self.instantiate(argument, environment);
+                self.alloc(Node::Application(function, argument))
+            }
+            LambdaTerm::Abstraction { .. } => {
+                unreachable!("supercombinator bodies have no abstractions")
+            }
+        }
+    }
+
+    // The head of the spine at `address` and the application nodes along
+    // it, outermost first.
+    fn unwind(&self, address: usize) -> (usize, Vec<usize>) {
+        let mut spine = Vec::new();
+        let mut address = self.resolve(address);
+        while let Node::Application(function, _) = self.heap[address] {
+            spine.push(address);
+            address = self.resolve(function);
+        }
+        (address, spine)
+    }
+
+    fn argument(&self, application: usize) -> usize {
+        match self.heap[application] {
+            Node::Application(_, argument) => argument,
+            _ => unreachable!("spines are made of applications"),
+        }
+    }
+
+    // Reduces the graph at `address` to weak head normal form: a variable
+    // applied to arguments, or a supercombinator applied to too few.
+    pub fn whnf(&mut self, address: usize) -> Result<(), RewriteError> {
+        loop {
+            let (head, spine) = self.unwind(address);
+            let index = match self.heap[head] {
+                Node::Supercombinator(index) => index,
+                _ => return Ok(()),
+            };
+            let definition = &self.program.definitions[index];
+            let arity = definition.parameters.len();
+            if spine.len() < arity {
+                return Ok(());
+            }
+            if self.steps == self.limit {
+                return Err(RewriteError::StepLimit(self.limit));
+            }
+            self.steps += 1;
+            // spine[len - 1] applies the head to the first argument, and
+            // spine[len - arity] is the root of the redex.
+            let environment: Vec<(&str, usize)> = definition
+                .parameters
+                .iter()
+                .zip(spine.iter().rev())
+                .map(|(parameter, &application)| (parameter.as_str(), self.argument(application)))
+                .collect();
+            let result = self.instantiate(&definition.body, &environment);
+            let result = self.resolve(result);
+            let root = match arity {
+                0 => head,
+                _ => spine[spine.len() - arity],
+            };
+            // A body that is just a loop back to the redex leaves it as is,
+            // to be reduced again until the limit.
+            if result != root {
+                self.heap[root] = Node::Indirection(result);
+            }
+        }
+    }
+
+    // Reads the graph at `address` back as a term in normal form, reducing
+    // as it goes. A partially applied supercombinator is read as an
+    // abstraction over the arguments it's missing.
+    pub fn read_back(&mut self, address: usize) -> Result<LambdaTerm, RewriteError> {
+        self.whnf(address)?;
+        let (head, spine) = self.unwind(address);
+        match &self.heap[head] {
+            Node::Variable(id) => {
+                let mut term = var(id.clone());
+                for &application in spine.iter().rev() {
+                    let argument = self.argument(application);
+                    term = app(term, self.read_back(argument)?);
+                }
+                Ok(term)
+            }
+            &Node::Supercombinator(index) => {
+                let parameters = &self.program.definitions[index].parameters[spine.len()..];
+                let mut applied = self.resolve(address);
+                let mut binders = Vec::new();
+                for parameter in parameters {
+                    let id = self.names.fresh(parameter);
+                    let argument = self.alloc(Node::Variable(id.clone()));
+                    applied = self.alloc(Node::Application(applied, argument));
+                    binders.push(id);
+                }
+                let body = self.read_back(applied)?;
+                Ok(binders.iter().rev().fold(body, |body, id| lam(id, body)))
+            }
+            Node::Application(..) | Node::Indirection(_) => {
+                unreachable!("unwinding stops at a head")
+            }
+        }
+    }
+
+    // The normal form of the program's main expression.
+    pub fn run(&mut self) -> Result<LambdaTerm, RewriteError> {
+        let main = self.instantiate(&self.program.main, &[]);
+        self.read_back(main)
+    }
+}
+
+// Lambda lifts `term` and runs it on a Machine, giving its normal form as
+// lazy evaluation finds it, or an error after `limit` reductions.
+pub fn evaluate(term: &LambdaTerm, limit: usize) -> Result<LambdaTerm, RewriteError> {
+    let program = lift(term);
+    Machine::new(&program, limit)
+        .expect("lifted programs have no abstractions")
+        .run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::lift::Supercombinator;
+    use crate::parse;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+
+    #[test]
+    fn agrees_with_normal_order_rewriting() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 300).rule(Beta);
+        let mut rng = SplitMix64(382);
+        let mut compared = 0;
+        for i in 0..2000 {
+            let term = gen_closed_term(1 + i % 25, &mut rng);
+            let term = app(app(term, var("p")), lam("q", var("q")));
+            if let Ok(expected) = rewriter.normalize(&term) {
+                let normal = evaluate(&term, 100_000).unwrap();
+                assert!(normal.alpha_equivalent(&expected), "{}", term);
+                compared += 1;
+            }
+        }
+        assert!(compared > 1000);
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let omega = parse("(λx. x x) (λx. x x)").unwrap();
+        assert!(matches!(
+            evaluate(&omega, 100),
+            Err(RewriteError::StepLimit(100))
+        ));
+        let discarded = parse("(λx. λy. x) a ((λx. x x) (λx. x x))").unwrap();
+        assert_eq!(evaluate(&discarded, 100).unwrap(), var("a"));
+    }
+
+    #[test]
+    fn loops_on_a_supercombinator_that_is_its_own_body() {
+        let program = SupercombinatorProgram {
+            definitions: vec![Supercombinator {
+                name: SmallName::from("sc"),
+                parameters: Vec::new(),
+                body: var("sc"),
+            }],
+            main: var("sc"),
+        };
+        assert!(matches!(
+            Machine::new(&program, 10).unwrap().run(),
+            Err(RewriteError::StepLimit(10))
+        ));
+    }
+
+    #[test]
+    fn refuses_programs_with_abstractions_left_in() {
+        let identity = Supercombinator {
+            name: SmallName::from("id"),
+            parameters: Vec::new(),
+            body: lam("x", var("x")),
+        };
+        let program = SupercombinatorProgram {
+            definitions: vec![identity.clone()],
+            main: var("id"),
+        };
+        assert_eq!(
+            Machine::new(&program, 10).err(),
+            Some(ProgramError::Abstraction(Some(SmallName::from("id"))))
+        );
+        let program = SupercombinatorProgram {
+            definitions: Vec::new(),
+            main: app(lam("x", var("x")), var("y")),
+        };
+        assert_eq!(
+            Machine::new(&program, 10).err(),
+            Some(ProgramError::Abstraction(None))
+        );
+    }
+}
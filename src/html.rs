@@ -0,0 +1,119 @@
+use crate::small::SmallName;
+use crate::{Backticks, LambdaTerm, Quoting};
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn identifier(id: &str, out: &mut String) {
+    let mut quoted = String::new();
+    let _ = Backticks.write_identifier(&mut quoted, id);
+    escape(&quoted, out);
+}
+
+// Highlights every occurrence of a variable together with its binder while
+// the pointer is over any one of them. Include it once per page, along with
+// a rule for `.highlight`, such as the one in STYLE.
+pub const SCRIPT: &str = r#"<script>
+document.addEventListener("mouseover", (event) => {
+  const binder = event.target.dataset && event.target.dataset.binder;
+  if (!binder) return;
+  const scope = event.target.closest(".lambda-term");
+  const linked = scope.querySelectorAll(`[data-binder="${binder}"]`);
+  linked.forEach((element) => element.classList.add("highlight"));
+  event.target.addEventListener(
+    "mouseout",
+    () => linked.forEach((element) => element.classList.remove("highlight")),
+    { once: true },
+  );
+});
+</script>"#;
+
+pub const STYLE: &str = r#"<style>
+.lambda-term .free { font-style: italic; }
+.lambda-term .highlight { background: #ffe08a; }
+</style>"#;
+
+impl LambdaTerm {
+    // Renders the term as HTML, printed as Display prints it. Each binder is
+    // a span with class `binder`, and each bound occurrence a link with class
+    // `bound` to its binder's id. A binder and its occurrences share a
+    // `data-binder` value, which is how SCRIPT finds them together. Free
+    // variables are spans with class `free`. Ids are numbered from b0 in
+    // every term, so they are only unique within one term.
+    pub fn to_html(&self) -> String {
+        fn to_html_mut(
+            term: &LambdaTerm,
+            binders: &mut Vec<(SmallName, usize)>,
+            next: &mut usize,
+            out: &mut String,
+        ) {
+            match term {
+                LambdaTerm::Variable(id) => {
+                    match binders.iter().rev().find(|(bound, _)| bound == id) {
+                        Some(&(_, binder)) => out.push_str(&format!(
+                            "<a class=\"bound\" href=\"#b{0}\" data-binder=\"b{0}\">",
+                            binder
+                        )),
+                        None => {
+                            out.push_str("<span class=\"free\">");
+                            identifier(id, out);
+                            out.push_str("</span>");
+                            return;
+                        }
+                    }
+                    identifier(id, out);
+                    out.push_str("</a>");
+                }
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    let binder = *next;
+                    *next += 1;
+                    out.push_str(&format!(
+                        "λ<span class=\"binder\" id=\"b{0}\" data-binder=\"b{0}\">",
+                        binder
+                    ));
+                    identifier(bound_variable, out);
+                    out.push_str("</span>. ");
+                    binders.push((bound_variable.clone(), binder));
+                    to_html_mut(return_term, binders, next, out);
+                    binders.pop();
+                }
+                LambdaTerm::Application { function, argument } => {
+                    match **function {
+                        LambdaTerm::Abstraction { .. } => {
+                            out.push('(');
+                            to_html_mut(function, binders, next, out);
+                            out.push(')');
+                        }
+                        _ => to_html_mut(function, binders, next, out),
+                    }
+                    out.push(' ');
+                    match **argument {
+                        LambdaTerm::Variable(_) => to_html_mut(argument, binders, next, out),
+                        _ => {
+                            out.push('(');
+                            to_html_mut(argument, binders, next, out);
+                            out.push(')');
+                        }
+                    }
+                }
+            }
+        }
+        let mut out = String::from("<code class=\"lambda-term\">");
+        to_html_mut(self, &mut Vec::new(), &mut 0, &mut out);
+        out.push_str("</code>");
+        out
+    }
+}
@@ -0,0 +1,472 @@
+use crate::small::SmallName;
+use crate::{DBIndices, DBLevels, DBTerm, LambdaTerm};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug)]
+pub enum JsonError {
+    PrematureEnd,
+    UnexpectedChar(char, usize),
+    InvalidNumber(usize),
+    InvalidEscape(usize),
+    TrailingCharacters(usize),
+    InvalidShape(String),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in string.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            _ if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            _ => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(number) if number.is_finite() => write!(f, "{}", number),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(string) => write_string(f, string),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while let Some((_, ch)) = self.chars.peek() {
+            if ch.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.chars.next() {
+            Some((_, ch)) if ch == expected => Ok(()),
+            Some((i, ch)) => Err(JsonError::UnexpectedChar(ch, i)),
+            None => Err(JsonError::PrematureEnd),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            None => Err(JsonError::PrematureEnd),
+            Some((_, 'n')) => self.keyword("null", Json::Null),
+            Some((_, 't')) => self.keyword("true", Json::Bool(true)),
+            Some((_, 'f')) => self.keyword("false", Json::Bool(false)),
+            Some((_, '"')) => Ok(Json::String(self.string()?)),
+            Some((_, '[')) => {
+                self.chars.next();
+                let mut elements = Vec::new();
+                self.skip_whitespace();
+                if let Some((_, ']')) = self.chars.peek() {
+                    self.chars.next();
+                    return Ok(Json::Array(elements));
+                }
+                loop {
+                    elements.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some((_, ',')) => (),
+                        Some((_, ']')) => break Ok(Json::Array(elements)),
+                        Some((i, ch)) => break Err(JsonError::UnexpectedChar(ch, i)),
+                        None => break Err(JsonError::PrematureEnd),
+                    }
+                }
+            }
+            Some((_, '{')) => {
+                self.chars.next();
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if let Some((_, '}')) = self.chars.peek() {
+                    self.chars.next();
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let name = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(':')?;
+                    members.push((name, self.value()?));
+                    self.skip_whitespace();
+                    match self.chars.next() {
+                        Some((_, ',')) => (),
+                        Some((_, '}')) => break Ok(Json::Object(members)),
+                        Some((i, ch)) => break Err(JsonError::UnexpectedChar(ch, i)),
+                        None => break Err(JsonError::PrematureEnd),
+                    }
+                }
+            }
+            Some((start, ch)) if ch == '-' || ch.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some((_, ch)) = self.chars.peek().copied() {
+                    if ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E') {
+                        number.push(ch);
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                number
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| JsonError::InvalidNumber(start))
+            }
+            Some((i, ch)) => Err(JsonError::UnexpectedChar(ch, i)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                None => break Err(JsonError::PrematureEnd),
+                Some((_, '"')) => break Ok(string),
+                Some((i, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => string.push('"'),
+                    Some((_, '\\')) => string.push('\\'),
+                    Some((_, '/')) => string.push('/'),
+                    Some((_, 'b')) => string.push('\u{8}'),
+                    Some((_, 'f')) => string.push('\u{c}'),
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 'r')) => string.push('\r'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, 'u')) => string.push(self.unicode_escape(i)?),
+                    Some(_) => break Err(JsonError::InvalidEscape(i)),
+                    None => break Err(JsonError::PrematureEnd),
+                },
+                Some((_, ch)) => string.push(ch),
+            }
+        }
+    }
+
+    fn hex4(&mut self, escape_start: usize) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.chars.next() {
+                Some((_, ch)) => match ch.to_digit(16) {
+                    Some(digit) => code = code * 16 + digit,
+                    None => return Err(JsonError::InvalidEscape(escape_start)),
+                },
+                None => return Err(JsonError::PrematureEnd),
+            }
+        }
+        Ok(code)
+    }
+
+    fn unicode_escape(&mut self, escape_start: usize) -> Result<char, JsonError> {
+        let high = self.hex4(escape_start)?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let low = self.hex4(escape_start)?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(JsonError::InvalidEscape(escape_start));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or(JsonError::InvalidEscape(escape_start))
+    }
+}
+
+pub fn parse(text: &str) -> Result<Json, JsonError> {
+    let mut parser = JsonParser {
+        chars: text.char_indices().peekable(),
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        Some((i, _)) => Err(JsonError::TrailingCharacters(i)),
+        None => Ok(value),
+    }
+}
+
+// Terms use the externally tagged layout, e.g. `{"Variable":"x"}` or
+// `{"Abstraction":{"bound_variable":"x","return_term":...}}`.
+impl LambdaTerm {
+    pub fn to_json(&self) -> Json {
+        match self {
+            LambdaTerm::Variable(id) => Json::Object(vec![(
+                String::from("Variable"),
+                Json::String(id.to_string()),
+            )]),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => Json::Object(vec![(
+                String::from("Abstraction"),
+                Json::Object(vec![
+                    (
+                        String::from("bound_variable"),
+                        Json::String(bound_variable.to_string()),
+                    ),
+                    (String::from("return_term"), return_term.to_json()),
+                ]),
+            )]),
+            LambdaTerm::Application { function, argument } => Json::Object(vec![(
+                String::from("Application"),
+                Json::Object(vec![
+                    (String::from("function"), function.to_json()),
+                    (String::from("argument"), argument.to_json()),
+                ]),
+            )]),
+        }
+    }
+
+    pub fn from_json(json: &Json) -> Result<LambdaTerm, JsonError> {
+        let invalid = || JsonError::InvalidShape(json.to_string());
+        let (tag, contents) = match json.as_object() {
+            Some([(tag, contents)]) => (tag.as_str(), contents),
+            _ => return Err(invalid()),
+        };
+        let field = |name| contents.get(name).ok_or_else(invalid);
+        match tag {
+            "Variable" => contents
+                .as_str()
+                .map(|id| LambdaTerm::Variable(SmallName::from(id)))
+                .ok_or_else(invalid),
+            "Abstraction" => Ok(LambdaTerm::Abstraction {
+                bound_variable: SmallName::from(
+                    field("bound_variable")?.as_str().ok_or_else(invalid)?,
+                ),
+                return_term: Box::new(LambdaTerm::from_json(field("return_term")?)?),
+            }),
+            "Application" => Ok(LambdaTerm::Application {
+                function: Box::new(LambdaTerm::from_json(field("function")?)?),
+                argument: Box::new(LambdaTerm::from_json(field("argument")?)?),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// The same externally tagged layout, where newtype variants hold their
+// contents directly: `λ 1` is {"Abstraction":{"Variable":1}}.
+impl DBTerm {
+    pub fn to_json(&self) -> Json {
+        let (tag, contents) = match self {
+            DBTerm::Variable(index) => ("Variable", Json::Number(*index as f64)),
+            DBTerm::FreeVariable(id) => ("FreeVariable", Json::String(id.clone())),
+            DBTerm::Abstraction(return_term) => ("Abstraction", return_term.to_json()),
+            DBTerm::Application { function, argument } => (
+                "Application",
+                Json::Object(vec![
+                    (String::from("function"), function.to_json()),
+                    (String::from("argument"), argument.to_json()),
+                ]),
+            ),
+        };
+        Json::Object(vec![(String::from(tag), contents)])
+    }
+
+    pub fn from_json(json: &Json) -> Result<DBTerm, JsonError> {
+        let invalid = || JsonError::InvalidShape(json.to_string());
+        let (tag, contents) = match json.as_object() {
+            Some([(tag, contents)]) => (tag.as_str(), contents),
+            _ => return Err(invalid()),
+        };
+        let field = |name| contents.get(name).ok_or_else(invalid);
+        match tag {
+            "Variable" => match contents.as_f64() {
+                Some(index) if index >= 1.0 && index.fract() == 0.0 => {
+                    Ok(DBTerm::Variable(index as usize))
+                }
+                _ => Err(invalid()),
+            },
+            "FreeVariable" => contents
+                .as_str()
+                .map(|id| DBTerm::FreeVariable(String::from(id)))
+                .ok_or_else(invalid),
+            "Abstraction" => Ok(DBTerm::Abstraction(Box::new(DBTerm::from_json(contents)?))),
+            "Application" => Ok(DBTerm::Application {
+                function: Box::new(DBTerm::from_json(field("function")?)?),
+                argument: Box::new(DBTerm::from_json(field("argument")?)?),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// The wrappers are transparent, as serde lays out newtype structs, but
+// reading one also checks that every index refers to an enclosing
+// abstraction.
+impl DBIndices {
+    pub fn to_json(&self) -> Json {
+        self.0.to_json()
+    }
+
+    pub fn from_json(json: &Json) -> Result<DBIndices, JsonError> {
+        closed_term(json).map(DBIndices)
+    }
+}
+
+impl DBLevels {
+    pub fn to_json(&self) -> Json {
+        self.0.to_json()
+    }
+
+    pub fn from_json(json: &Json) -> Result<DBLevels, JsonError> {
+        closed_term(json).map(DBLevels)
+    }
+}
+
+fn closed_term(json: &Json) -> Result<DBTerm, JsonError> {
+    let term = DBTerm::from_json(json)?;
+    if term.is_locally_closed() {
+        Ok(term)
+    } else {
+        Err(JsonError::InvalidShape(json.to_string()))
+    }
+}
+
+// The serde feature derives the layout the functions above write by hand.
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::{app, lam, var};
+
+    #[test]
+    fn serde_writes_the_same_layout() {
+        let mut rng = SplitMix64(315);
+        let mut terms = vec![app(lam("x", var("x")), var("y"))];
+        terms.extend((1..40).map(|size| gen_closed_term(size, &mut rng)));
+        for term in terms {
+            let by_hand: serde_json::Value =
+                serde_json::from_str(&term.to_json().to_string()).unwrap();
+            assert_eq!(serde_json::to_value(&term).unwrap(), by_hand);
+            assert_eq!(serde_json::from_value::<LambdaTerm>(by_hand).unwrap(), term);
+
+            let db = DBIndices::from(term).0;
+            let by_hand: serde_json::Value =
+                serde_json::from_str(&db.to_json().to_string()).unwrap();
+            assert_eq!(serde_json::to_value(&db).unwrap(), by_hand);
+            assert_eq!(serde_json::from_value::<DBTerm>(by_hand).unwrap(), db);
+        }
+    }
+
+    #[test]
+    fn serde_checks_the_wrappers_scope() {
+        let indices: DBIndices = "λ λ 2 1 y".parse().unwrap();
+        let levels = DBLevels::from(indices.clone());
+        for (written, by_hand) in [
+            (serde_json::to_value(&indices).unwrap(), indices.to_json()),
+            (serde_json::to_value(&levels).unwrap(), levels.to_json()),
+        ] {
+            assert_eq!(
+                written,
+                serde_json::from_str::<serde_json::Value>(&by_hand.to_string()).unwrap()
+            );
+        }
+        let round_trip: DBIndices =
+            serde_json::from_value(serde_json::to_value(&indices).unwrap()).unwrap();
+        assert_eq!(round_trip, indices);
+        let round_trip: DBLevels =
+            serde_json::from_value(serde_json::to_value(&levels).unwrap()).unwrap();
+        assert_eq!(round_trip, levels);
+
+        let open = r#"{"Abstraction":{"Variable":2}}"#;
+        assert!(serde_json::from_str::<DBTerm>(open).is_ok());
+        assert!(serde_json::from_str::<DBIndices>(open).is_err());
+        assert!(serde_json::from_str::<DBLevels>(open).is_err());
+    }
+}
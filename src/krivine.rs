@@ -0,0 +1,203 @@
+use crate::json::Json;
+use crate::{DBIndices, DBTerm};
+use std::fmt;
+use std::rc::Rc;
+
+// The Krivine machine evaluates a de Bruijn term to weak head normal form by
+// call-by-name. A closure pairs a subterm of the program with the environment
+// its free indices refer to; index n looks up the nth entry.
+#[derive(Clone)]
+pub struct Closure<'a> {
+    pub term: &'a DBTerm,
+    pub environment: Environment<'a>,
+}
+
+#[derive(Clone, Default)]
+pub struct Environment<'a>(Option<Rc<(Closure<'a>, Environment<'a>)>>);
+
+impl<'a> Environment<'a> {
+    fn push(&self, closure: Closure<'a>) -> Environment<'a> {
+        Environment(Some(Rc::new((closure, self.clone()))))
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Closure<'a>> {
+        let mut environment = self;
+        let mut index = index;
+        loop {
+            let (closure, rest) = environment.0.as_deref()?;
+            if index == 1 {
+                break Some(closure);
+            }
+            index -= 1;
+            environment = rest;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Closure<'a>> {
+        let mut environment = self;
+        std::iter::from_fn(move || {
+            let (closure, rest) = environment.0.as_deref()?;
+            environment = rest;
+            Some(closure)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Push,
+    Grab,
+    Access,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Transition::Push => "push",
+            Transition::Grab => "grab",
+            Transition::Access => "access",
+        })
+    }
+}
+
+pub struct Machine<'a> {
+    pub control: &'a DBTerm,
+    pub environment: Environment<'a>,
+    // The top of the stack is the last element.
+    pub stack: Vec<Closure<'a>>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(program: &'a DBIndices) -> Machine<'a> {
+        let DBIndices(term) = program;
+        Machine {
+            control: term,
+            environment: Environment::default(),
+            stack: Vec::new(),
+        }
+    }
+
+    // Returns None once the machine has halted: on an abstraction with an
+    // empty stack, or on a free variable.
+    pub fn step(&mut self) -> Option<Transition> {
+        match self.control {
+            DBTerm::Application { function, argument } => {
+                self.stack.push(Closure {
+                    term: argument,
+                    environment: self.environment.clone(),
+                });
+                self.control = function;
+                Some(Transition::Push)
+            }
+            DBTerm::Abstraction(return_term) => {
+                let argument = self.stack.pop()?;
+                self.environment = self.environment.push(argument);
+                self.control = return_term;
+                Some(Transition::Grab)
+            }
+            DBTerm::Variable(index) => {
+                let Closure { term, environment } = self.environment.get(*index)?.clone();
+                self.control = term;
+                self.environment = environment;
+                Some(Transition::Access)
+            }
+            DBTerm::FreeVariable(_) => None,
+        }
+    }
+
+    // Substitutes the environment back into the control term and reapplies
+    // the stack, giving the term the machine currently stands for.
+    pub fn readback(&self) -> DBIndices {
+        let head = readback(self.control, &self.environment, 0);
+        DBIndices(
+            self.stack
+                .iter()
+                .rev()
+                .fold(head, |function, argument| DBTerm::Application {
+                    function: Box::new(function),
+                    argument: Box::new(readback(argument.term, &argument.environment, 0)),
+                }),
+        )
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            (
+                String::from("control"),
+                Json::String(self.control.to_string()),
+            ),
+            (
+                String::from("environment"),
+                environment_json(&self.environment),
+            ),
+            (
+                String::from("stack"),
+                Json::Array(self.stack.iter().rev().map(closure_json).collect()),
+            ),
+        ])
+    }
+}
+
+fn readback(term: &DBTerm, environment: &Environment<'_>, depth: usize) -> DBTerm {
+    match term {
+        DBTerm::Variable(index) if *index <= depth => DBTerm::Variable(*index),
+        DBTerm::Variable(index) => match environment.get(index - depth) {
+            Some(closure) => readback(closure.term, &closure.environment, 0).lift(depth, 0),
+            None => DBTerm::Variable(*index - environment.iter().count()),
+        },
+        DBTerm::FreeVariable(id) => DBTerm::FreeVariable(id.clone()),
+        DBTerm::Abstraction(return_term) => {
+            DBTerm::Abstraction(Box::new(readback(return_term, environment, depth + 1)))
+        }
+        DBTerm::Application { function, argument } => DBTerm::Application {
+            function: Box::new(readback(function, environment, depth)),
+            argument: Box::new(readback(argument, environment, depth)),
+        },
+    }
+}
+
+fn closure_json(closure: &Closure<'_>) -> Json {
+    Json::Object(vec![
+        (String::from("term"), Json::String(closure.term.to_string())),
+        (
+            String::from("environment"),
+            environment_json(&closure.environment),
+        ),
+    ])
+}
+
+fn environment_json(environment: &Environment<'_>) -> Json {
+    Json::Array(environment.iter().map(closure_json).collect())
+}
+
+impl<'a> fmt::Display for Closure<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.term, self.environment)
+    }
+}
+
+impl<'a> fmt::Display for Environment<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, closure) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", closure)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<'a> fmt::Display for Machine<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "⟨{} | {} | [", self.control, self.environment)?;
+        for (i, closure) in self.stack.iter().rev().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", closure)?;
+        }
+        write!(f, "]⟩")
+    }
+}
@@ -0,0 +1,118 @@
+use crate::rewrite::{RewriteError, Rewriter};
+use crate::LambdaTerm;
+
+// Single letters, optionally followed by digits or primes, are set as math
+// italics already; longer names are wrapped in \mathit so that they don't
+// read as a product of variables.
+fn identifier(id: &str) -> String {
+    let mut escaped = String::new();
+    for c in id.chars() {
+        match c {
+            '_' | '#' | '$' | '%' | '&' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\backslash{}"),
+            '^' => escaped.push_str("\\hat{}"),
+            '~' => escaped.push_str("\\sim{}"),
+            _ => escaped.push(c),
+        }
+    }
+    let mut chars = id.chars();
+    let single = chars.next().is_some_and(char::is_alphabetic)
+        && chars.all(|c| c.is_ascii_digit() || c == '\'');
+    if single {
+        escaped
+    } else {
+        format!("\\mathit{{{}}}", escaped)
+    }
+}
+
+// The name of a rewrite rule as a subscript to an arrow.
+fn rule_name(name: &str) -> String {
+    match name {
+        "β" => String::from("\\beta"),
+        "η" => String::from("\\eta"),
+        "δ" => String::from("\\delta"),
+        name => format!("\\text{{{}}}", name),
+    }
+}
+
+impl LambdaTerm {
+    // The term as LaTeX math, parenthesized as Display does it, e.g.
+    // `\lambda x.\, x\; (\lambda y.\, y)`.
+    pub fn to_latex(&self) -> String {
+        fn to_latex_mut(term: &LambdaTerm, out: &mut String) {
+            match term {
+                LambdaTerm::Variable(id) => out.push_str(&identifier(id)),
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    out.push_str("\\lambda ");
+                    out.push_str(&identifier(bound_variable));
+                    out.push_str(".\\, ");
+                    to_latex_mut(return_term, out);
+                }
+                LambdaTerm::Application { function, argument } => {
+                    match **function {
+                        LambdaTerm::Abstraction { .. } => {
+                            out.push('(');
+                            to_latex_mut(function, out);
+                            out.push(')');
+                        }
+                        _ => to_latex_mut(function, out),
+                    }
+                    out.push_str("\\; ");
+                    match **argument {
+                        LambdaTerm::Variable(_) => to_latex_mut(argument, out),
+                        _ => {
+                            out.push('(');
+                            to_latex_mut(argument, out);
+                            out.push(')');
+                        }
+                    }
+                }
+            }
+        }
+        let mut out = String::new();
+        to_latex_mut(self, &mut out);
+        out
+    }
+}
+
+// Rewrites `term` to normal form with `rewriter`, giving the reduction
+// sequence as an align* environment with one step per line, each arrow
+// labelled with the rule that fired:
+//
+//   \begin{align*}
+//     & (\lambda x.\, x)\; y \\
+//     \to_{\beta} {} & y
+//   \end{align*}
+pub fn derivation(rewriter: &Rewriter, term: &LambdaTerm) -> Result<String, RewriteError> {
+    let mut out = format!("\\begin{{align*}}\n  & {}", term.to_latex());
+    let mut term = term.clone();
+    for _ in 0..rewriter.limit {
+        match rewriter.step(&term) {
+            Some(rewritten) => {
+                out.push_str(&format!(
+                    " \\\\\n  \\to_{{{}}} {{}} & {}",
+                    rule_name(rewritten.rule),
+                    rewritten.term.to_latex()
+                ));
+                term = rewritten.term;
+            }
+            None => {
+                out.push_str("\n\\end{align*}\n");
+                return Ok(out);
+            }
+        }
+    }
+    match rewriter.step(&term) {
+        Some(_) => Err(RewriteError::StepLimit(rewriter.limit)),
+        None => {
+            out.push_str("\n\\end{align*}\n");
+            Ok(out)
+        }
+    }
+}
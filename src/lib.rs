@@ -1,155 +1,810 @@
+pub mod anf;
+pub mod annotated;
+pub mod blc;
+pub mod bohm;
+pub mod capture;
+pub mod closure;
+pub mod combinators;
+pub mod confluence;
+pub mod corpus;
+pub mod cursor;
+pub mod dag;
+pub mod diagnostic;
+pub mod diagram;
+pub mod diff;
+pub mod dot;
+#[cfg(feature = "line-editing")]
+pub mod editor;
+pub mod encodings;
+pub mod environment;
+pub mod error;
+pub mod eta;
+pub mod generate;
+pub mod graph;
+pub mod html;
+pub mod json;
+pub mod krivine;
+pub mod latex;
+pub mod lift;
+pub mod linear;
+pub mod lint;
+mod macros;
+pub mod metrics;
+pub mod nameless;
+pub mod names;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod path;
+pub mod pattern;
+pub mod pipeline;
+pub mod print;
+pub mod provenance;
+pub mod quote;
+pub mod repl;
+pub mod rewrite;
+pub mod shared;
+pub mod ski;
+pub mod small;
+pub mod step;
+pub mod store;
+pub mod symbol;
+pub mod systemf;
+pub mod tour;
+pub mod trace;
+pub mod visit;
+
+// The token stream simply ends with the input; there's no end-of-input token.
 #[derive(Debug)]
 pub enum Token {
     LParen,
     RParen,
     Lambda,
     Dot,
+    Hash,
     Identifier(String),
-    Eof,
+    QuotedIdentifier(String),
+    Number(String),
+    UnterminatedQuote(String),
+}
+
+// A character the grammar has no place for. The lexer steps past it, so
+// lexing can carry on after one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    StrayCharacter(char),
+    EmbeddedNul,
+}
+
+fn is_prime(ch: char) -> bool {
+    matches!(ch, '\'' | '′' | '″' | '‴' | '⁗')
+}
+
+fn is_subscript(ch: char) -> bool {
+    ('₀'..='₉').contains(&ch) || ('ₐ'..='ₜ').contains(&ch)
+}
+
+// identifier ::= start continue*
+// start      ::= letter | '_'
+// continue   ::= letter | digit | '_' | prime | subscript
+fn is_identifier_start(ch: char) -> bool {
+    ch != 'λ' && !is_subscript(ch) && (ch.is_alphabetic() || ch == '_')
+}
+
+fn is_identifier_continue(ch: char) -> bool {
+    ch != 'λ' && (ch.is_alphanumeric() || ch == '_' || is_prime(ch) || is_subscript(ch))
+}
+
+pub fn is_plain_identifier(id: &str) -> bool {
+    let mut chars = id.chars();
+    match chars.next() {
+        Some(ch) => is_identifier_start(ch) && chars.all(is_identifier_continue),
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LexerConfig {
+    pub lambda_symbols: Vec<String>,
+    pub lambda_keywords: Vec<String>,
+    pub dot_symbols: Vec<String>,
+    pub application_symbols: Vec<String>,
+    pub pipe_binders: bool,
 }
 
-use std::iter::Peekable;
-use std::str::Chars;
+impl Default for LexerConfig {
+    fn default() -> LexerConfig {
+        LexerConfig {
+            lambda_symbols: vec![String::from("λ"), String::from("\\")],
+            lambda_keywords: Vec::new(),
+            dot_symbols: vec![String::from(".")],
+            application_symbols: Vec::new(),
+            pipe_binders: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Delimiter {
+    Lambda,
+    Dot,
+    Application,
+}
+
+use std::collections::VecDeque;
 
+// The lexer works on bytes, decoding a full character only when it meets
+// one outside ASCII, which in practice means `λ` or an exotic identifier.
 pub struct Lexer<'a> {
-    chars_peekable: Peekable<Chars<'a>>,
-    buffer: String,
+    input: &'a str,
+    position: usize,
+    config: LexerConfig,
+    // Marks the bytes that begin a configured symbol, so the symbol tables
+    // are only searched when one could match.
+    symbol_starts: [bool; 256],
+    pending: VecDeque<Token>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new<'b>(code: &'b str) -> Lexer<'b> {
+        Lexer::with_config(code, LexerConfig::default())
+    }
+
+    pub fn with_config<'b>(code: &'b str, config: LexerConfig) -> Lexer<'b> {
+        let mut symbol_starts = [false; 256];
+        for symbol in config
+            .lambda_symbols
+            .iter()
+            .chain(&config.dot_symbols)
+            .chain(&config.application_symbols)
+        {
+            if let Some(&byte) = symbol.as_bytes().first() {
+                symbol_starts[byte as usize] = true;
+            }
+        }
         Lexer {
-            chars_peekable: code.chars().peekable(),
-            buffer: String::new(),
+            input: code,
+            position: 0,
+            config,
+            symbol_starts,
+            pending: VecDeque::new(),
         }
     }
-}
 
-impl<'a> Iterator for Lexer<'a> {
-    type Item = Token;
-
-    fn next(&mut self) -> Option<Token> {
-        return loop {
-            match self.chars_peekable.next() {
-                None => break None,
-                Some(ch) => match ch {
-                    '(' => break Some(Token::LParen),
-                    ')' => break Some(Token::RParen),
-                    'λ' | '\\' => break Some(Token::Lambda),
-                    '.' => break Some(Token::Dot),
-                    '\0' => break Some(Token::Eof),
-                    _ if ch.is_alphanumeric() || ch == '_' => {
-                        self.buffer.push(ch);
-                        match self.chars_peekable.peek() {
-                            Some(nch) if nch.is_alphanumeric() && nch != &'λ' => (),
-                            _ => {
-                                let id_str = self.buffer.clone();
-                                self.buffer.clear();
-                                break Some(Token::Identifier(id_str));
-                            }
-                        }
-                    }
-                    _ => (),
+    // The byte offset of the first character not yet read.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek_char()?;
+        self.position += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn match_symbol(&self) -> Option<(usize, Delimiter)> {
+        let rest = self.rest();
+        let candidates = [
+            (&self.config.lambda_symbols, Delimiter::Lambda),
+            (&self.config.dot_symbols, Delimiter::Dot),
+            (&self.config.application_symbols, Delimiter::Application),
+        ];
+        let mut longest: Option<(usize, Delimiter)> = None;
+        for (symbols, kind) in candidates {
+            for symbol in symbols.iter().filter(|symbol| !symbol.is_empty()) {
+                if rest.starts_with(symbol.as_str())
+                    && longest.as_ref().is_none_or(|(n, _)| symbol.len() > *n)
+                {
+                    longest = Some((symbol.len(), kind));
+                }
+            }
+        }
+        longest
+    }
+
+    // Expects the current character to start an identifier.
+    fn identifier(&mut self) -> Token {
+        let start = self.position;
+        let bytes = self.input.as_bytes();
+        while let Some(&byte) = bytes.get(self.position) {
+            if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'\'' {
+                self.position += 1;
+            } else if byte.is_ascii() {
+                break;
+            } else {
+                match self.peek_char() {
+                    Some(ch) if is_identifier_continue(ch) => self.position += ch.len_utf8(),
+                    _ => break,
+                }
+            }
+        }
+        let id = &self.input[start..self.position];
+        if self
+            .config
+            .lambda_keywords
+            .iter()
+            .any(|keyword| keyword == id)
+        {
+            Token::Lambda
+        } else {
+            Token::Identifier(String::from(id))
+        }
+    }
+
+    fn number(&mut self) -> Token {
+        let start = self.position;
+        let bytes = self.input.as_bytes();
+        while bytes.get(self.position).is_some_and(u8::is_ascii_digit) {
+            self.position += 1;
+        }
+        Token::Number(String::from(&self.input[start..self.position]))
+    }
+
+    fn quoted_identifier(&mut self, quote: char) -> Token {
+        let mut id = String::new();
+        loop {
+            match self.next_char() {
+                Some(ch) if ch == quote => break Token::QuotedIdentifier(id),
+                Some('\\') => match self.next_char() {
+                    Some(escaped) => id.push(escaped),
+                    None => break Token::UnterminatedQuote(id),
                 },
+                Some(ch) => id.push(ch),
+                None => break Token::UnterminatedQuote(id),
             }
-        };
+        }
+    }
+
+    fn pipe_binder(&mut self) {
+        while let Some(ch) = self.next_char() {
+            let binder = match ch {
+                '|' => break,
+                '`' | '"' => self.quoted_identifier(ch),
+                _ if is_identifier_start(ch) => {
+                    self.position -= ch.len_utf8();
+                    self.identifier()
+                }
+                _ => continue,
+            };
+            self.pending.push_back(Token::Lambda);
+            self.pending.push_back(binder);
+            self.pending.push_back(Token::Dot);
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token, LexError>> {
+        loop {
+            let byte = *self.input.as_bytes().get(self.position)?;
+            if self.symbol_starts[byte as usize] {
+                if let Some((length, symbol)) = self.match_symbol() {
+                    self.position += length;
+                    match symbol {
+                        Delimiter::Lambda => break Some(Ok(Token::Lambda)),
+                        Delimiter::Dot => break Some(Ok(Token::Dot)),
+                        Delimiter::Application => continue,
+                    }
+                }
+            }
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'_' => break Some(Ok(self.identifier())),
+                b'0'..=b'9' => break Some(Ok(self.number())),
+                b' ' | b'\t' | b'\n' | b'\r' => self.position += 1,
+                b'(' | b')' | b'#' => {
+                    self.position += 1;
+                    break Some(Ok(match byte {
+                        b'(' => Token::LParen,
+                        b')' => Token::RParen,
+                        _ => Token::Hash,
+                    }));
+                }
+                b'|' if self.config.pipe_binders => {
+                    self.position += 1;
+                    self.pipe_binder();
+                    if let Some(token) = self.pending.pop_front() {
+                        break Some(Ok(token));
+                    }
+                }
+                b'`' | b'"' => {
+                    self.position += 1;
+                    break Some(Ok(self.quoted_identifier(byte as char)));
+                }
+                _ => {
+                    let ch = self.peek_char()?;
+                    if is_identifier_start(ch) {
+                        break Some(Ok(self.identifier()));
+                    }
+                    self.position += ch.len_utf8();
+                    if ch == '\0' {
+                        break Some(Err(LexError::EmbeddedNul));
+                    } else if !ch.is_whitespace() {
+                        break Some(Err(LexError::StrayCharacter(ch)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Result<Token, LexError>> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(Ok(token));
+        }
+        self.next_token()
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, self.chars_peekable.size_hint().1)
+        let remaining = self.input.len() - self.position;
+        (self.pending.len(), Some(remaining + self.pending.len()))
     }
 }
 
 impl<'a> std::iter::FusedIterator for Lexer<'a> {}
 
-#[derive(Debug)]
-pub enum LambdaTerm {
+// Generic in how a variable is represented, so that another crate can give
+// its variables a Symbol, a source span or a number and still share the one
+// syntax tree. Nearly everything here works on LambdaTerm, whose variables
+// are plain names.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Term<V> {
     Abstraction {
-        bound_variable: String,
-        return_term: Box<LambdaTerm>,
+        bound_variable: V,
+        return_term: Box<Term<V>>,
     },
     Application {
-        function: Box<LambdaTerm>,
-        argument: Box<LambdaTerm>,
+        function: Box<Term<V>>,
+        argument: Box<Term<V>>,
     },
-    Variable(String),
+    Variable(V),
+}
+
+// Names are SmallNames, so the short names most terms use never allocate.
+pub type LambdaTerm = Term<SmallName>;
+
+pub fn var(id: impl Into<SmallName>) -> LambdaTerm {
+    LambdaTerm::Variable(id.into())
+}
+
+pub fn lam(bound_variable: impl Into<SmallName>, return_term: LambdaTerm) -> LambdaTerm {
+    LambdaTerm::Abstraction {
+        bound_variable: bound_variable.into(),
+        return_term: Box::new(return_term),
+    }
+}
+
+pub fn app(function: LambdaTerm, argument: LambdaTerm) -> LambdaTerm {
+    LambdaTerm::Application {
+        function: Box::new(function),
+        argument: Box::new(argument),
+    }
+}
+
+// Applies `head` to each argument in turn, so apps(f, [x, y]) is f x y.
+pub fn apps(head: LambdaTerm, arguments: impl IntoIterator<Item = LambdaTerm>) -> LambdaTerm {
+    arguments.into_iter().fold(head, app)
 }
 
+use names::NameSupply;
+use small::SmallName;
 use std::collections::HashSet;
 
-impl LambdaTerm {
-    pub fn free_variables(&self) -> HashSet<String> {
-        fn free_variables_mut(term: &LambdaTerm, set: &mut HashSet<String>) {
+impl<V> Term<V> {
+    // Rebuilds the term with `f` applied to every variable, binders included.
+    pub fn map_variables<W, F: FnMut(&V) -> W>(&self, mut f: F) -> Term<W> {
+        fn map_mut<V, W>(term: &Term<V>, f: &mut dyn FnMut(&V) -> W) -> Term<W> {
             match term {
-                LambdaTerm::Variable(id) => {
-                    set.insert(id.clone());
+                Term::Variable(id) => Term::Variable(f(id)),
+                Term::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => Term::Abstraction {
+                    bound_variable: f(bound_variable),
+                    return_term: Box::new(map_mut(return_term, f)),
+                },
+                Term::Application { function, argument } => Term::Application {
+                    function: Box::new(map_mut(function, f)),
+                    argument: Box::new(map_mut(argument, f)),
+                },
+            }
+        }
+        map_mut(self, &mut f)
+    }
+}
+
+impl<V: Clone + Eq + Hash> Term<V> {
+    // Both walks keep their own stack of pending subterms rather than
+    // recursing, so they cope with terms nested deeper than the call stack
+    // would allow, such as large Church numerals.
+    pub fn free_variables(&self) -> HashSet<V> {
+        let mut set = HashSet::new();
+        self.for_each_free_variable(|id| {
+            set.insert(id.clone());
+        });
+        set
+    }
+
+    // Calls `f` on every free occurrence of a variable, leftmost first.
+    pub fn for_each_free_variable(&self, mut f: impl FnMut(&V)) {
+        enum Work<'a, V> {
+            Term(&'a Term<V>),
+            // Leaves the scope of the innermost binder.
+            Unbind,
+        }
+        let mut bound: Vec<&V> = Vec::new();
+        let mut stack = vec![Work::Term(self)];
+        while let Some(work) = stack.pop() {
+            match work {
+                Work::Term(Term::Variable(id)) => {
+                    if !bound.contains(&id) {
+                        f(id);
+                    }
                 }
-                LambdaTerm::Application { function, argument } => {
-                    free_variables_mut(function, set);
-                    free_variables_mut(argument, set);
+                Work::Term(Term::Application { function, argument }) => {
+                    stack.extend([Work::Term(argument), Work::Term(function)]);
                 }
-                LambdaTerm::Abstraction {
+                Work::Term(Term::Abstraction {
                     bound_variable,
                     return_term,
-                } => {
-                    free_variables_mut(return_term, set);
-                    set.remove(bound_variable);
+                }) => {
+                    bound.push(bound_variable);
+                    stack.extend([Work::Unbind, Work::Term(return_term)]);
+                }
+                Work::Unbind => {
+                    bound.pop();
                 }
             }
         }
+    }
+
+    pub fn bound_variables(&self) -> HashSet<V> {
         let mut set = HashSet::new();
-        free_variables_mut(self, &mut set);
+        let mut stack = vec![self];
+        while let Some(term) = stack.pop() {
+            match term {
+                Term::Variable(_) => (),
+                Term::Application { function, argument } => {
+                    stack.extend([&**argument, &**function]);
+                }
+                Term::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    set.insert(bound_variable.clone());
+                    stack.push(return_term);
+                }
+            }
+        }
         set
     }
 
-    pub fn bound_variables(&self) -> HashSet<String> {
-        fn bound_variables_mut(term: &LambdaTerm, set: &mut HashSet<String>) {
+    pub fn alpha_equivalent(&self, other: &Term<V>) -> bool {
+        fn alpha_equivalent_mut<'a, V: Eq>(
+            left: &'a Term<V>,
+            right: &'a Term<V>,
+            binders: &mut Vec<(&'a V, &'a V)>,
+        ) -> bool {
+            match (left, right) {
+                (Term::Variable(left_id), Term::Variable(right_id)) => {
+                    let left_binder = binders.iter().rposition(|(id, _)| *id == left_id);
+                    let right_binder = binders.iter().rposition(|(_, id)| *id == right_id);
+                    match (left_binder, right_binder) {
+                        (None, None) => left_id == right_id,
+                        (left_binder, right_binder) => left_binder == right_binder,
+                    }
+                }
+                (
+                    Term::Application {
+                        function: left_function,
+                        argument: left_argument,
+                    },
+                    Term::Application {
+                        function: right_function,
+                        argument: right_argument,
+                    },
+                ) => {
+                    alpha_equivalent_mut(left_function, right_function, binders)
+                        && alpha_equivalent_mut(left_argument, right_argument, binders)
+                }
+                (
+                    Term::Abstraction {
+                        bound_variable: left_variable,
+                        return_term: left_return,
+                    },
+                    Term::Abstraction {
+                        bound_variable: right_variable,
+                        return_term: right_return,
+                    },
+                ) => {
+                    binders.push((left_variable, right_variable));
+                    let equivalent = alpha_equivalent_mut(left_return, right_return, binders);
+                    binders.pop();
+                    equivalent
+                }
+                _ => false,
+            }
+        }
+        alpha_equivalent_mut(self, other, &mut Vec::new())
+    }
+}
+
+impl LambdaTerm {
+    pub fn substitute(&self, variable: &str, replacement: &LambdaTerm) -> LambdaTerm {
+        fn substitute_mut(
+            term: &LambdaTerm,
+            variable: &str,
+            replacement: &LambdaTerm,
+            replacement_free: &HashSet<SmallName>,
+        ) -> LambdaTerm {
+            match term {
+                LambdaTerm::Variable(id) if id == variable => replacement.clone(),
+                LambdaTerm::Variable(_) => term.clone(),
+                LambdaTerm::Application { function, argument } => LambdaTerm::Application {
+                    function: Box::new(substitute_mut(
+                        function,
+                        variable,
+                        replacement,
+                        replacement_free,
+                    )),
+                    argument: Box::new(substitute_mut(
+                        argument,
+                        variable,
+                        replacement,
+                        replacement_free,
+                    )),
+                },
+                LambdaTerm::Abstraction { bound_variable, .. } if bound_variable == variable => {
+                    term.clone()
+                }
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    let return_free = return_term.free_variables();
+                    if replacement_free.contains(bound_variable) && return_free.contains(variable) {
+                        let mut names = NameSupply::new(return_free);
+                        for id in replacement_free {
+                            names.avoid(id.clone());
+                        }
+                        let fresh = names.fresh(bound_variable);
+                        let renamed = return_term
+                            .substitute(bound_variable, &LambdaTerm::Variable(fresh.clone()));
+                        LambdaTerm::Abstraction {
+                            bound_variable: fresh,
+                            return_term: Box::new(substitute_mut(
+                                &renamed,
+                                variable,
+                                replacement,
+                                replacement_free,
+                            )),
+                        }
+                    } else {
+                        LambdaTerm::Abstraction {
+                            bound_variable: bound_variable.clone(),
+                            return_term: Box::new(substitute_mut(
+                                return_term,
+                                variable,
+                                replacement,
+                                replacement_free,
+                            )),
+                        }
+                    }
+                }
+            }
+        }
+        substitute_mut(self, variable, replacement, &replacement.free_variables())
+    }
+
+    // Replaces every subterm α-equivalent to `target`. An occurrence only
+    // matches where none of the target's free variables are bound by an
+    // enclosing abstraction, and enclosing binders that would capture a free
+    // variable of the replacement are renamed.
+    pub fn replace_all(&self, target: &LambdaTerm, replacement: &LambdaTerm) -> LambdaTerm {
+        struct Replace<'a> {
+            target: &'a LambdaTerm,
+            replacement: &'a LambdaTerm,
+            target_free: HashSet<SmallName>,
+            replacement_free: HashSet<SmallName>,
+        }
+
+        fn replace_mut(
+            term: &LambdaTerm,
+            on: &Replace,
+            bound: &mut Vec<SmallName>,
+        ) -> (LambdaTerm, bool) {
+            if !bound.iter().any(|id| on.target_free.contains(id))
+                && term.alpha_equivalent(on.target)
+            {
+                return (on.replacement.clone(), true);
+            }
             match term {
-                LambdaTerm::Variable(_) => (),
+                LambdaTerm::Variable(_) => (term.clone(), false),
                 LambdaTerm::Application { function, argument } => {
-                    bound_variables_mut(function, set);
-                    bound_variables_mut(argument, set);
+                    let (function, function_replaced) = replace_mut(function, on, bound);
+                    let (argument, argument_replaced) = replace_mut(argument, on, bound);
+                    (
+                        LambdaTerm::Application {
+                            function: Box::new(function),
+                            argument: Box::new(argument),
+                        },
+                        function_replaced || argument_replaced,
+                    )
                 }
                 LambdaTerm::Abstraction {
                     bound_variable,
                     return_term,
                 } => {
-                    bound_variables_mut(return_term, set);
-                    set.insert(bound_variable.clone());
+                    bound.push(bound_variable.clone());
+                    let (mut new_return, replaced) = replace_mut(return_term, on, bound);
+                    bound.pop();
+                    let mut bound_variable = bound_variable.clone();
+                    if replaced && on.replacement_free.contains(&bound_variable) {
+                        let mut names = NameSupply::new(
+                            return_term
+                                .free_variables()
+                                .into_iter()
+                                .chain(on.replacement_free.iter().cloned())
+                                .chain(on.target_free.iter().cloned()),
+                        );
+                        let fresh = names.fresh(&bound_variable);
+                        let renamed = return_term
+                            .substitute(&bound_variable, &LambdaTerm::Variable(fresh.clone()));
+                        bound.push(fresh.clone());
+                        new_return = replace_mut(&renamed, on, bound).0;
+                        bound.pop();
+                        bound_variable = fresh;
+                    }
+                    (
+                        LambdaTerm::Abstraction {
+                            bound_variable,
+                            return_term: Box::new(new_return),
+                        },
+                        replaced,
+                    )
                 }
             }
         }
-        let mut set = HashSet::new();
-        bound_variables_mut(self, &mut set);
-        set
+        let on = Replace {
+            target,
+            replacement,
+            target_free: target.free_variables(),
+            replacement_free: replacement.free_variables(),
+        };
+        replace_mut(self, &on, &mut Vec::new()).0
     }
 }
 
 use std::fmt;
 
-impl fmt::Display for LambdaTerm {
+pub trait Quoting {
+    fn needs_quoting(&self, id: &str) -> bool {
+        !is_plain_identifier(id)
+    }
+
+    fn write_quoted(&self, f: &mut dyn fmt::Write, id: &str) -> fmt::Result;
+
+    fn write_identifier(&self, f: &mut dyn fmt::Write, id: &str) -> fmt::Result {
+        if self.needs_quoting(id) {
+            self.write_quoted(f, id)
+        } else {
+            f.write_str(id)
+        }
+    }
+}
+
+fn write_delimited(f: &mut dyn fmt::Write, id: &str, quote: char) -> fmt::Result {
+    write!(f, "{}", quote)?;
+    for ch in id.chars() {
+        if ch == quote || ch == '\\' {
+            write!(f, "\\")?;
+        }
+        write!(f, "{}", ch)?;
+    }
+    write!(f, "{}", quote)
+}
+
+pub struct Backticks;
+
+impl Quoting for Backticks {
+    fn write_quoted(&self, f: &mut dyn fmt::Write, id: &str) -> fmt::Result {
+        write_delimited(f, id, '`')
+    }
+}
+
+pub struct DoubleQuotes;
+
+impl Quoting for DoubleQuotes {
+    fn write_quoted(&self, f: &mut dyn fmt::Write, id: &str) -> fmt::Result {
+        write_delimited(f, id, '"')
+    }
+}
+
+pub struct QuotedDisplay<'a> {
+    term: &'a LambdaTerm,
+    quoting: &'a dyn Quoting,
+}
+
+impl<'a> fmt::Display for QuotedDisplay<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            LambdaTerm::Variable(id) => write!(f, "{}", id)?,
-            LambdaTerm::Application { function, argument } => {
-                match **function {
-                    LambdaTerm::Abstraction { .. } => write!(f, "({}) ", function)?,
-                    _ => write!(f, "{} ", function)?,
+        self.term.fmt_quoted(f, self.quoting)
+    }
+}
+
+impl LambdaTerm {
+    pub fn display_quoted<'a>(&'a self, quoting: &'a dyn Quoting) -> QuotedDisplay<'a> {
+        QuotedDisplay {
+            term: self,
+            quoting,
+        }
+    }
+
+    // Prints from a stack of pending work, like the walks above, so that
+    // printing a deeply nested term doesn't overflow the call stack. Work is
+    // pushed in reverse, so that it pops off the stack in print order.
+    fn fmt_quoted(&self, f: &mut fmt::Formatter<'_>, quoting: &dyn Quoting) -> fmt::Result {
+        enum Work<'a> {
+            Term(&'a LambdaTerm),
+            Text(&'static str),
+        }
+        let mut stack = vec![Work::Term(self)];
+        while let Some(work) = stack.pop() {
+            let term = match work {
+                Work::Term(term) => term,
+                Work::Text(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+            };
+            match term {
+                LambdaTerm::Variable(id) => quoting.write_identifier(f, id)?,
+                LambdaTerm::Application { function, argument } => {
+                    match **argument {
+                        LambdaTerm::Variable(_) => stack.push(Work::Term(argument)),
+                        _ => stack.extend([Work::Text(")"), Work::Term(argument), Work::Text("(")]),
+                    }
+                    match **function {
+                        LambdaTerm::Abstraction { .. } => {
+                            stack.extend([Work::Text(") "), Work::Term(function), Work::Text("(")])
+                        }
+                        _ => stack.extend([Work::Text(" "), Work::Term(function)]),
+                    }
                 }
-                match **argument {
-                    LambdaTerm::Variable(_) => write!(f, "{}", argument)?,
-                    _ => write!(f, "({})", argument)?,
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    write!(f, "λ")?;
+                    quoting.write_identifier(f, bound_variable)?;
+                    write!(f, ". ")?;
+                    stack.push(Work::Term(return_term));
                 }
             }
-            LambdaTerm::Abstraction {
-                bound_variable,
-                return_term,
-            } => write!(f, "λ{}. {}", bound_variable, return_term)?,
         }
         Ok(())
     }
 }
 
+// Display and parse are inverses: parsing the printed form of any term gives
+// back that same term, not just an alpha-equivalent one. Names that aren't
+// plain identifiers are quoted, arguments other than variables and
+// functions that are abstractions are parenthesized, and bodies extend as
+// far right as they can. The one exception is a term nested more deeply
+// than the parser's max_depth, which prints but is refused on the way back.
+impl fmt::Display for LambdaTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_quoted(f, &Backticks)
+    }
+}
+
 #[derive(Debug)]
 pub enum ParserError {
     PrematureEnd,
@@ -161,21 +816,142 @@ pub enum ParserError {
     ExpectedGot(Token, Token),
     Unexpected(Token),
     UnmatchedParens(isize),
+    ExpectedIndexGot(Token),
+    UnterminatedQuote(String),
+    IndexOutOfScope {
+        index: usize,
+        abstraction_depth: usize,
+    },
+    StrayCharacter(char),
+    EmbeddedNul,
+    EmptyParens,
+    TooDeep(usize),
+    InputTooLong(usize),
+    IdentifierTooLong(usize),
+}
+
+impl From<LexError> for ParserError {
+    fn from(err: LexError) -> ParserError {
+        match err {
+            LexError::StrayCharacter(ch) => ParserError::StrayCharacter(ch),
+            LexError::EmbeddedNul => ParserError::EmbeddedNul,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    // Rejects anything that isn't part of the grammar.
+    Strict,
+    // Skips stray characters, noting each as a warning. NUL bytes are still
+    // an error.
+    Permissive,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParserConfig {
+    pub mode: ParseMode,
+    // How far parentheses and abstractions may nest before parsing gives up
+    // with TooDeep, since each level costs a frame of the parser's stack.
+    pub max_depth: usize,
+    // The most bytes of source parse_with_config and parse_db_with_config
+    // accept, failing with InputTooLong beyond that. The parsers only see
+    // tokens, so they can't check this themselves.
+    pub max_input_length: Option<usize>,
+    // The most characters a variable name may have, failing with
+    // IdentifierTooLong beyond that.
+    pub max_identifier_length: Option<usize>,
+}
+
+// Deep enough for anything written by hand, and shallow enough that parsing,
+// printing and converting the result fit on the main thread's stack, even in
+// a debug build.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+impl Default for ParserConfig {
+    fn default() -> ParserConfig {
+        ParserConfig {
+            mode: ParseMode::Strict,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_input_length: None,
+            max_identifier_length: None,
+        }
+    }
+}
+
+impl ParserConfig {
+    // Limits for input from people who might be hostile, such as visitors
+    // to a web playground, under which parsing takes little time and
+    // memory whatever the input.
+    pub fn untrusted() -> ParserConfig {
+        ParserConfig {
+            mode: ParseMode::Strict,
+            max_depth: 256,
+            max_input_length: Some(64 * 1024),
+            max_identifier_length: Some(64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    StrayCharacter(char),
 }
 
-pub struct Parser<'a> {
-    lexer: Lexer<'a>,
+pub struct Parser<I: Iterator<Item = Result<Token, LexError>>> {
+    lexer: I,
     paren_index: isize,
+    config: ParserConfig,
+    warnings: Vec<ParseWarning>,
+    after_lparen: bool,
+    depth: usize,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new<'b>(lexer: Lexer<'b>) -> Parser<'b> {
+impl<I: Iterator<Item = Result<Token, LexError>>> Parser<I> {
+    pub fn new(lexer: I) -> Parser<I> {
+        Parser::with_config(lexer, ParserConfig::default())
+    }
+
+    pub fn with_config(lexer: I, config: ParserConfig) -> Parser<I> {
         Parser {
             lexer,
             paren_index: 0,
+            config,
+            warnings: Vec::new(),
+            after_lparen: false,
+            depth: 0,
         }
     }
 
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, ParserError> {
+        let strict = self.config.mode == ParseMode::Strict;
+        let token = loop {
+            match self.lexer.next() {
+                Some(Err(LexError::StrayCharacter(ch))) if !strict => {
+                    self.warnings.push(ParseWarning::StrayCharacter(ch))
+                }
+                Some(Err(err)) => Err(err)?,
+                Some(Ok(Token::Identifier(id) | Token::QuotedIdentifier(id)))
+                    if self
+                        .config
+                        .max_identifier_length
+                        .is_some_and(|limit| id.chars().count() > limit) =>
+                {
+                    Err(ParserError::IdentifierTooLong(
+                        self.config.max_identifier_length.unwrap(),
+                    ))?
+                }
+                token => break token.transpose()?,
+            }
+        };
+        self.after_lparen = matches!(token, Some(Token::LParen));
+        Ok(token)
+    }
+
     pub fn parse(&mut self) -> Result<LambdaTerm, ParserError> {
         let root_term = self.parse_term(self.paren_index)?;
         if self.paren_index != 0 {
@@ -185,24 +961,44 @@ impl<'a> Parser<'a> {
         }
     }
 
+    pub fn parse_tracked(&mut self) -> Result<provenance::Tracked<LambdaTerm>, ParserError> {
+        Ok(self.parse()?.tracked())
+    }
+
     fn parse_term(&mut self, paren_index_bound: isize) -> Result<LambdaTerm, ParserError> {
+        if self.depth == self.config.max_depth {
+            return Err(ParserError::TooDeep(self.config.max_depth));
+        }
+        self.depth += 1;
+        let term = self.parse_nested_term(paren_index_bound);
+        self.depth -= 1;
+        term
+    }
+
+    fn parse_nested_term(&mut self, paren_index_bound: isize) -> Result<LambdaTerm, ParserError> {
         self.check_bounds(paren_index_bound)?;
-        let mut term = match self.lexer.next() {
+        let after_lparen = self.after_lparen;
+        let mut term = match self.next_token()? {
             Some(token) => match token {
                 Token::Lambda => self.parse_abstraction(self.paren_index),
                 Token::Dot => Err(ParserError::Unexpected(Token::Dot)),
+                Token::Hash => Err(ParserError::Unexpected(Token::Hash)),
+                Token::Number(digits) => Err(ParserError::Unexpected(Token::Number(digits))),
+                Token::RParen if after_lparen => Err(ParserError::EmptyParens),
                 Token::RParen => Err(ParserError::Unexpected(Token::RParen)),
                 Token::LParen => {
                     self.paren_index += 1;
                     self.parse_term(self.paren_index)
                 }
-                Token::Identifier(id) => Ok(LambdaTerm::Variable(id)),
-                Token::Eof => Err(ParserError::PrematureEnd),
+                Token::Identifier(id) | Token::QuotedIdentifier(id) => {
+                    Ok(LambdaTerm::Variable(id.into()))
+                }
+                Token::UnterminatedQuote(id) => Err(ParserError::UnterminatedQuote(id)),
             },
             None => Err(ParserError::PrematureEnd),
         }?;
         while self.paren_index >= paren_index_bound {
-            match self.lexer.next() {
+            match self.next_token()? {
                 Some(token) => match token {
                     Token::LParen => {
                         self.paren_index += 1;
@@ -220,14 +1016,16 @@ impl<'a> Parser<'a> {
                             argument: Box::new(self.parse_abstraction(self.paren_index)?),
                         }
                     }
-                    Token::Identifier(id) => {
+                    Token::Identifier(id) | Token::QuotedIdentifier(id) => {
                         term = LambdaTerm::Application {
                             function: Box::new(term),
-                            argument: Box::new(LambdaTerm::Variable(id)),
+                            argument: Box::new(LambdaTerm::Variable(id.into())),
                         }
                     }
-                    Token::Eof => (),
+                    Token::UnterminatedQuote(id) => Err(ParserError::UnterminatedQuote(id))?,
                     Token::Dot => Err(ParserError::Unexpected(Token::Dot))?,
+                    Token::Hash => Err(ParserError::Unexpected(Token::Hash))?,
+                    Token::Number(digits) => Err(ParserError::Unexpected(Token::Number(digits)))?,
                 },
                 None => break,
             }
@@ -237,24 +1035,50 @@ impl<'a> Parser<'a> {
 
     fn parse_abstraction(&mut self, paren_index_bound: isize) -> Result<LambdaTerm, ParserError> {
         self.check_bounds(paren_index_bound)?;
-        match self.lexer.next() {
+        match self.next_token()? {
             Some(expected_identifier) => match expected_identifier {
-                Token::Identifier(bound_variable) => match self.lexer.next() {
-                    Some(expected_dot) => match expected_dot {
-                        Token::Dot => Ok(LambdaTerm::Abstraction {
-                            bound_variable,
-                            return_term: Box::new(self.parse_term(self.paren_index)?),
-                        }),
-                        _ => Err(ParserError::ExpectedGot(Token::Dot, expected_dot)),
-                    },
-                    None => Err(ParserError::PrematureEnd),
-                },
+                Token::Identifier(bound_variable) | Token::QuotedIdentifier(bound_variable) => {
+                    self.parse_binders(bound_variable)
+                }
+                Token::UnterminatedQuote(id) => Err(ParserError::UnterminatedQuote(id)),
                 _ => Err(ParserError::ExpectedIdentifierGot(expected_identifier)),
             },
             None => Err(ParserError::PrematureEnd),
         }
     }
 
+    // The rest of an abstraction after its first binder, so that `λx y. M`
+    // reads as `λx. λy. M`. Each binder counts against max_depth as a λ of
+    // its own would, since the abstractions it builds nest just as deeply.
+    fn parse_binders(&mut self, bound_variable: String) -> Result<LambdaTerm, ParserError> {
+        let mut binders = vec![bound_variable];
+        loop {
+            match self.next_token()? {
+                Some(Token::Dot) => break,
+                Some(Token::Identifier(next) | Token::QuotedIdentifier(next)) => binders.push(next),
+                Some(Token::UnterminatedQuote(id)) => Err(ParserError::UnterminatedQuote(id))?,
+                Some(expected_dot) => Err(ParserError::ExpectedGot(Token::Dot, expected_dot))?,
+                None => Err(ParserError::PrematureEnd)?,
+            }
+            if self.depth + binders.len() > self.config.max_depth {
+                return Err(ParserError::TooDeep(self.config.max_depth));
+            }
+        }
+        let extra = binders.len() - 1;
+        self.depth += extra;
+        let return_term = self.parse_term(self.paren_index);
+        self.depth -= extra;
+        Ok(binders
+            .into_iter()
+            .rev()
+            .fold(return_term?, |return_term, bound_variable| {
+                LambdaTerm::Abstraction {
+                    bound_variable: bound_variable.into(),
+                    return_term: Box::new(return_term),
+                }
+            }))
+    }
+
     fn check_bounds(&self, paren_index_bound: isize) -> Result<(), ParserError> {
         if self.paren_index < paren_index_bound {
             Err(ParserError::ParenOutOfBounds {
@@ -267,6 +1091,105 @@ impl<'a> Parser<'a> {
     }
 }
 
+pub fn parse(code: &str) -> Result<LambdaTerm, ParserError> {
+    Parser::new(Lexer::new(code)).parse()
+}
+
+// parse, within the limits `config` sets, including on the input's length.
+pub fn parse_with_config(code: &str, config: ParserConfig) -> Result<LambdaTerm, ParserError> {
+    if let Some(limit) = config.max_input_length {
+        if code.len() > limit {
+            return Err(ParserError::InputTooLong(limit));
+        }
+    }
+    Parser::with_config(Lexer::new(code), config).parse()
+}
+
+// parse_with_config for de Bruijn notation.
+pub fn parse_db_with_config(code: &str, config: ParserConfig) -> Result<DBIndices, ParserError> {
+    if let Some(limit) = config.max_input_length {
+        if code.len() > limit {
+            return Err(ParserError::InputTooLong(limit));
+        }
+    }
+    DBParser::with_config(Lexer::new(code), config).parse()
+}
+
+impl std::str::FromStr for LambdaTerm {
+    type Err = ParserError;
+
+    fn from_str(code: &str) -> Result<LambdaTerm, ParserError> {
+        parse(code)
+    }
+}
+
+impl ParserError {
+    pub fn needs_more_input(&self) -> bool {
+        match self {
+            ParserError::PrematureEnd | ParserError::UnterminatedQuote(_) => true,
+            ParserError::UnmatchedParens(paren_index) => *paren_index > 0,
+            _ => false,
+        }
+    }
+}
+
+pub enum ParseStatus {
+    Complete(LambdaTerm),
+    Incomplete,
+    Failed(ParserError),
+}
+
+// Accumulates input until it forms a whole term. Each push reparses the
+// buffered text, which is cheap at the sizes a person types into a REPL.
+#[derive(Default)]
+pub struct IncrementalParser {
+    buffer: String,
+    config: LexerConfig,
+}
+
+impl IncrementalParser {
+    pub fn new() -> IncrementalParser {
+        IncrementalParser::default()
+    }
+
+    pub fn with_config(config: LexerConfig) -> IncrementalParser {
+        IncrementalParser {
+            buffer: String::new(),
+            config,
+        }
+    }
+
+    pub fn push(&mut self, chunk: &str) -> ParseStatus {
+        self.buffer.push_str(chunk);
+        let lexer = Lexer::with_config(&self.buffer, self.config.clone());
+        match Parser::new(lexer).parse() {
+            Ok(term) => {
+                self.buffer.clear();
+                ParseStatus::Complete(term)
+            }
+            Err(err) if err.needs_more_input() => ParseStatus::Incomplete,
+            Err(err) => {
+                self.buffer.clear();
+                ParseStatus::Failed(err)
+            }
+        }
+    }
+
+    pub fn pending(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.buffer.trim().is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DBTerm {
     Variable(usize),
     Application {
@@ -277,47 +1200,136 @@ pub enum DBTerm {
     FreeVariable(String),
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct DBLevels(pub DBTerm);
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct DBIndices(pub DBTerm);
 
 impl DBTerm {
     pub fn free_variables(&self) -> HashSet<String> {
-        fn free_variables_mut(term: &DBTerm, set: &mut HashSet<String>) {
+        let mut set = HashSet::new();
+        let mut stack = vec![self];
+        while let Some(term) = stack.pop() {
             match term {
                 DBTerm::FreeVariable(id) => {
                     set.insert(id.clone());
                 }
                 DBTerm::Variable(_) => (),
-                DBTerm::Abstraction(return_term) => free_variables_mut(return_term, set),
+                DBTerm::Abstraction(return_term) => stack.push(return_term),
                 DBTerm::Application { function, argument } => {
-                    free_variables_mut(function, set);
-                    free_variables_mut(argument, set);
+                    stack.extend([&**argument, &**function]);
                 }
             }
         }
-        let mut set = HashSet::new();
-        free_variables_mut(self, &mut set);
         set
     }
 }
 
+// Shifting moves the indices that reach out past `cutoff` enclosing binders
+// by `amount`, leaving those bound within the term, or within the cutoff,
+// alone. It is what keeps indices pointing at the right binder when a term
+// is moved under more abstractions (a positive amount) or out from under
+// some (a negative one).
+impl DBTerm {
+    pub fn shift(&self, amount: isize, cutoff: usize) -> Option<DBTerm> {
+        fn shift_mut(term: &DBTerm, amount: isize, cutoff: usize) -> Option<DBTerm> {
+            Some(match term {
+                DBTerm::Variable(index) if *index > cutoff => {
+                    let shifted = index.checked_add_signed(amount)?;
+                    // Lowering must not make a free index refer to one of
+                    // the binders it was reaching past.
+                    if shifted <= cutoff {
+                        return None;
+                    }
+                    DBTerm::Variable(shifted)
+                }
+                DBTerm::Variable(_) | DBTerm::FreeVariable(_) => term.clone(),
+                DBTerm::Abstraction(return_term) => {
+                    DBTerm::Abstraction(Box::new(shift_mut(return_term, amount, cutoff + 1)?))
+                }
+                DBTerm::Application { function, argument } => DBTerm::Application {
+                    function: Box::new(shift_mut(function, amount, cutoff)?),
+                    argument: Box::new(shift_mut(argument, amount, cutoff)?),
+                },
+            })
+        }
+        shift_mut(self, amount, cutoff)
+    }
+
+    // A shift upwards, which can't fail.
+    pub fn lift(&self, amount: usize, cutoff: usize) -> DBTerm {
+        match self {
+            DBTerm::Variable(index) if *index > cutoff => DBTerm::Variable(index + amount),
+            DBTerm::Variable(_) | DBTerm::FreeVariable(_) => self.clone(),
+            DBTerm::Abstraction(return_term) => {
+                DBTerm::Abstraction(Box::new(return_term.lift(amount, cutoff + 1)))
+            }
+            DBTerm::Application { function, argument } => DBTerm::Application {
+                function: Box::new(function.lift(amount, cutoff)),
+                argument: Box::new(argument.lift(amount, cutoff)),
+            },
+        }
+    }
+}
+
+impl DBIndices {
+    // Gives None when lowering would take a free index to or below the
+    // cutoff.
+    pub fn shift(&self, amount: isize, cutoff: usize) -> Option<DBIndices> {
+        let DBIndices(term) = self;
+        term.shift(amount, cutoff).map(DBIndices)
+    }
+
+    pub fn lift(&self, amount: usize, cutoff: usize) -> DBIndices {
+        let DBIndices(term) = self;
+        DBIndices(term.lift(amount, cutoff))
+    }
+}
+
 impl fmt::Display for DBTerm {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            DBTerm::Variable(id) => write!(f, "{}", id)?,
-            DBTerm::FreeVariable(id) => write!(f, "{}", id)?,
-            DBTerm::Application { function, argument } => {
-                match **function {
-                    DBTerm::Abstraction { .. } => write!(f, "({}) ", function)?,
-                    _ => write!(f, "{} ", function)?,
+        enum Work<'a> {
+            Term(&'a DBTerm),
+            Text(&'static str),
+        }
+        let mut stack = vec![Work::Term(self)];
+        while let Some(work) = stack.pop() {
+            let term = match work {
+                Work::Term(term) => term,
+                Work::Text(text) => {
+                    f.write_str(text)?;
+                    continue;
+                }
+            };
+            match term {
+                DBTerm::Variable(id) => write!(f, "{}", id)?,
+                DBTerm::FreeVariable(id) => {
+                    if Backticks.needs_quoting(id) {
+                        Backticks.write_quoted(f, id)?
+                    } else {
+                        write!(f, "{}", id)?
+                    }
                 }
-                match **argument {
-                    DBTerm::Variable(_) | DBTerm::FreeVariable(_) => write!(f, "{}", argument)?,
-                    _ => write!(f, "({})", argument)?,
+                DBTerm::Application { function, argument } => {
+                    match **argument {
+                        DBTerm::Variable(_) | DBTerm::FreeVariable(_) => {
+                            stack.push(Work::Term(argument))
+                        }
+                        _ => stack.extend([Work::Text(")"), Work::Term(argument), Work::Text("(")]),
+                    }
+                    match **function {
+                        DBTerm::Abstraction { .. } => {
+                            stack.extend([Work::Text(") "), Work::Term(function), Work::Text("(")])
+                        }
+                        _ => stack.extend([Work::Text(" "), Work::Term(function)]),
+                    }
+                }
+                DBTerm::Abstraction(return_term) => {
+                    write!(f, "λ ")?;
+                    stack.push(Work::Term(return_term));
                 }
             }
-            DBTerm::Abstraction(return_term) => write!(f, "λ {}", return_term)?,
         }
         Ok(())
     }
@@ -363,6 +1375,135 @@ impl From<DBIndices> for DBLevels {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameScheme {
+    // a, b, …, z, a1, b1, …
+    Letters,
+    // x0, x1, x2, …
+    Numbered,
+}
+
+impl NameScheme {
+    fn name(self, abstraction_depth: usize) -> String {
+        match self {
+            NameScheme::Letters => {
+                let letter = char::from(b'a' + (abstraction_depth % 26) as u8);
+                match abstraction_depth / 26 {
+                    0 => letter.to_string(),
+                    round => format!("{}{}", letter, round),
+                }
+            }
+            NameScheme::Numbered => format!("x{}", abstraction_depth),
+        }
+    }
+}
+
+impl DBIndices {
+    // Names each binder after how deeply it is nested, primed where that
+    // would clash with a free variable or an enclosing binder.
+    pub fn to_named(&self, scheme: NameScheme) -> LambdaTerm {
+        fn name(
+            term: &DBTerm,
+            scheme: NameScheme,
+            free: &HashSet<String>,
+            scope: &mut Vec<SmallName>,
+        ) -> LambdaTerm {
+            match term {
+                DBTerm::FreeVariable(id) => LambdaTerm::Variable(id.into()),
+                DBTerm::Variable(index) => LambdaTerm::Variable(scope[scope.len() - index].clone()),
+                DBTerm::Application { function, argument } => LambdaTerm::Application {
+                    function: Box::new(name(function, scheme, free, scope)),
+                    argument: Box::new(name(argument, scheme, free, scope)),
+                },
+                DBTerm::Abstraction(return_term) => {
+                    let mut names = NameSupply::new(
+                        free.iter()
+                            .map(SmallName::from)
+                            .chain(scope.iter().cloned()),
+                    );
+                    let bound_variable = names.fresh(&scheme.name(scope.len()));
+                    scope.push(bound_variable.clone());
+                    let return_term = name(return_term, scheme, free, scope);
+                    scope.pop();
+                    LambdaTerm::Abstraction {
+                        bound_variable,
+                        return_term: Box::new(return_term),
+                    }
+                }
+            }
+        }
+        let DBIndices(term) = self;
+        name(term, scheme, &term.free_variables(), &mut Vec::new())
+    }
+}
+
+impl From<DBIndices> for LambdaTerm {
+    fn from(indices: DBIndices) -> LambdaTerm {
+        indices.to_named(NameScheme::Letters)
+    }
+}
+
+// Levels are written just like indices, and a level is in scope exactly
+// when an index with the same number would be, so both parse the same way.
+impl std::str::FromStr for DBIndices {
+    type Err = ParserError;
+
+    fn from_str(code: &str) -> Result<DBIndices, ParserError> {
+        DBParser::new(Lexer::new(code)).parse()
+    }
+}
+
+impl std::str::FromStr for DBLevels {
+    type Err = ParserError;
+
+    fn from_str(code: &str) -> Result<DBLevels, ParserError> {
+        let DBIndices(term) = code.parse()?;
+        Ok(DBLevels(term))
+    }
+}
+
+// As with json::from_json, the wrappers serialize as the bare DBTerm and
+// refuse to read back an index that points past its binders.
+#[cfg(feature = "serde")]
+fn deserialize_closed<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DBTerm, D::Error> {
+    let term = <DBTerm as serde::Deserialize>::deserialize(deserializer)?;
+    if term.is_locally_closed() {
+        Ok(term)
+    } else {
+        Err(serde::de::Error::custom("an index refers past its binders"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DBIndices {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DBIndices {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<DBIndices, D::Error> {
+        deserialize_closed(deserializer).map(DBIndices)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DBLevels {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DBLevels {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<DBLevels, D::Error> {
+        deserialize_closed(deserializer).map(DBLevels)
+    }
+}
+
 impl fmt::Display for DBLevels {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let DBLevels(term) = self;
@@ -384,7 +1525,7 @@ impl From<LambdaTerm> for DBLevels {
         fn convert(
             term: LambdaTerm,
             abstraction_depth: usize,
-            level_map: &mut HashMap<String, usize>,
+            level_map: &mut HashMap<SmallName, usize>,
         ) -> DBTerm {
             match term {
                 LambdaTerm::Abstraction {
@@ -409,7 +1550,7 @@ impl From<LambdaTerm> for DBLevels {
                     if let Some(level) = level_map.get(&id) {
                         DBTerm::Variable(*level)
                     } else {
-                        DBTerm::FreeVariable(id)
+                        DBTerm::FreeVariable(id.into())
                     }
                 }
             }
@@ -424,3 +1565,464 @@ impl From<LambdaTerm> for DBIndices {
         DBLevels::from(lambda).into()
     }
 }
+
+use std::hash::{Hash, Hasher};
+
+impl LambdaTerm {
+    // Hashes the de Bruijn form, so α-equivalent terms hash alike.
+    pub fn alpha_hash(&self) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        DBIndices::from(self.clone()).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Renames every binder after its nesting depth, as DBIndices::to_named
+    // does with the Letters scheme, so α-equivalent terms canonicalize to the
+    // same term. Sorting or deduplicating canonical forms is then stable
+    // under α-renaming.
+    pub fn canonicalize(&self) -> LambdaTerm {
+        DBIndices::from(self.clone()).to_named(NameScheme::Letters)
+    }
+}
+
+pub struct DBParser<I: Iterator<Item = Result<Token, LexError>>> {
+    lexer: I,
+    paren_index: isize,
+    config: ParserConfig,
+    warnings: Vec<ParseWarning>,
+    depth: usize,
+}
+
+impl<I: Iterator<Item = Result<Token, LexError>>> DBParser<I> {
+    pub fn new(lexer: I) -> DBParser<I> {
+        DBParser::with_config(lexer, ParserConfig::default())
+    }
+
+    pub fn with_config(lexer: I, config: ParserConfig) -> DBParser<I> {
+        DBParser {
+            lexer,
+            paren_index: 0,
+            config,
+            warnings: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    // The next token, with the mode and identifier limit applied as the
+    // named Parser applies them.
+    fn next_token(&mut self) -> Result<Option<Token>, ParserError> {
+        let strict = self.config.mode == ParseMode::Strict;
+        loop {
+            match self.lexer.next() {
+                Some(Err(LexError::StrayCharacter(ch))) if !strict => {
+                    self.warnings.push(ParseWarning::StrayCharacter(ch))
+                }
+                Some(Err(err)) => Err(err)?,
+                Some(Ok(Token::Identifier(id) | Token::QuotedIdentifier(id)))
+                    if self
+                        .config
+                        .max_identifier_length
+                        .is_some_and(|limit| id.chars().count() > limit) =>
+                {
+                    Err(ParserError::IdentifierTooLong(
+                        self.config.max_identifier_length.unwrap(),
+                    ))?
+                }
+                token => return token.transpose().map_err(ParserError::from),
+            }
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<DBIndices, ParserError> {
+        let root_term = self.parse_term(self.paren_index, 0)?;
+        if self.paren_index != 0 {
+            Err(ParserError::UnmatchedParens(self.paren_index))
+        } else {
+            Ok(DBIndices(root_term))
+        }
+    }
+
+    fn parse_term(
+        &mut self,
+        paren_index_bound: isize,
+        abstraction_depth: usize,
+    ) -> Result<DBTerm, ParserError> {
+        match self.next_token()? {
+            Some(token) => self.parse_term_from(token, paren_index_bound, abstraction_depth),
+            None => Err(ParserError::PrematureEnd),
+        }
+    }
+
+    fn parse_term_from(
+        &mut self,
+        first_token: Token,
+        paren_index_bound: isize,
+        abstraction_depth: usize,
+    ) -> Result<DBTerm, ParserError> {
+        if self.depth == self.config.max_depth {
+            return Err(ParserError::TooDeep(self.config.max_depth));
+        }
+        self.depth += 1;
+        let term = self.parse_nested_term(first_token, paren_index_bound, abstraction_depth);
+        self.depth -= 1;
+        term
+    }
+
+    fn parse_nested_term(
+        &mut self,
+        first_token: Token,
+        paren_index_bound: isize,
+        abstraction_depth: usize,
+    ) -> Result<DBTerm, ParserError> {
+        self.check_bounds(paren_index_bound)?;
+        let mut term = self.parse_atom(first_token, abstraction_depth)?;
+        while self.paren_index >= paren_index_bound {
+            match self.next_token()? {
+                Some(Token::RParen) => self.paren_index -= 1,
+                Some(token) => {
+                    term = DBTerm::Application {
+                        function: Box::new(term),
+                        argument: Box::new(self.parse_atom(token, abstraction_depth)?),
+                    }
+                }
+                None => break,
+            }
+        }
+        Ok(term)
+    }
+
+    fn parse_atom(
+        &mut self,
+        token: Token,
+        abstraction_depth: usize,
+    ) -> Result<DBTerm, ParserError> {
+        match token {
+            Token::Lambda => self.parse_abstraction(abstraction_depth),
+            Token::LParen => {
+                self.paren_index += 1;
+                self.parse_term(self.paren_index, abstraction_depth)
+            }
+            Token::Hash => match self.next_token()? {
+                Some(Token::Number(digits)) => Self::index(digits, abstraction_depth),
+                Some(token) => Err(ParserError::ExpectedIndexGot(token)),
+                None => Err(ParserError::PrematureEnd),
+            },
+            Token::Number(digits) => Self::index(digits, abstraction_depth),
+            Token::Identifier(id) | Token::QuotedIdentifier(id) => Ok(DBTerm::FreeVariable(id)),
+            Token::UnterminatedQuote(id) => Err(ParserError::UnterminatedQuote(id)),
+            token => Err(ParserError::Unexpected(token)),
+        }
+    }
+
+    fn parse_abstraction(&mut self, abstraction_depth: usize) -> Result<DBTerm, ParserError> {
+        let return_term = match self.next_token()? {
+            Some(Token::Dot) => self.parse_term(self.paren_index, abstraction_depth + 1),
+            Some(token) => self.parse_term_from(token, self.paren_index, abstraction_depth + 1),
+            None => Err(ParserError::PrematureEnd),
+        }?;
+        Ok(DBTerm::Abstraction(Box::new(return_term)))
+    }
+
+    fn index(digits: String, abstraction_depth: usize) -> Result<DBTerm, ParserError> {
+        let index = match digits.parse() {
+            Ok(index) => index,
+            Err(_) => Err(ParserError::ExpectedIndexGot(Token::Number(digits)))?,
+        };
+        if index == 0 || index > abstraction_depth {
+            Err(ParserError::IndexOutOfScope {
+                index,
+                abstraction_depth,
+            })
+        } else {
+            Ok(DBTerm::Variable(index))
+        }
+    }
+
+    fn check_bounds(&self, paren_index_bound: isize) -> Result<(), ParserError> {
+        if self.paren_index < paren_index_bound {
+            Err(ParserError::ParenOutOfBounds {
+                paren_index: self.paren_index,
+                paren_index_bound,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_variables_keeps_names_shadowed_elsewhere() {
+        let term = app(var("y"), lam("y", var("y")));
+        assert_eq!(term.free_variables(), HashSet::from([SmallName::from("y")]));
+        let term = app(lam("x", var("x")), app(var("x"), var("z")));
+        assert_eq!(
+            term.free_variables(),
+            HashSet::from([SmallName::from("x"), SmallName::from("z")])
+        );
+    }
+
+    fn binder_chain(binders: usize) -> String {
+        let names: Vec<String> = (0..binders).map(|i| format!("x{}", i)).collect();
+        format!("λ{}. x0", names.join(" "))
+    }
+
+    #[test]
+    fn binder_chains_count_against_max_depth() {
+        let term = parse(&binder_chain(500)).unwrap();
+        assert_eq!(term.count_binders(), 500);
+        assert!(matches!(
+            parse(&binder_chain(2000)),
+            Err(ParserError::TooDeep(DEFAULT_MAX_DEPTH))
+        ));
+        assert!(matches!(
+            parse(&binder_chain(20000)),
+            Err(ParserError::TooDeep(DEFAULT_MAX_DEPTH))
+        ));
+        let chain = binder_chain(10000);
+        assert!(chain.len() < 64 * 1024);
+        assert!(matches!(
+            parse_with_config(&chain, ParserConfig::untrusted()),
+            Err(ParserError::TooDeep(256))
+        ));
+    }
+
+    #[test]
+    fn de_bruijn_parser_applies_the_config_limits() {
+        let chain = "λ ".repeat(300) + "1";
+        assert!(parse_db_with_config(&chain, ParserConfig::default()).is_ok());
+        assert!(matches!(
+            parse_db_with_config(&chain, ParserConfig::untrusted()),
+            Err(ParserError::TooDeep(256))
+        ));
+        let nested = "(".repeat(300) + "λ 1" + &")".repeat(300);
+        assert!(matches!(
+            parse_db_with_config(&nested, ParserConfig::untrusted()),
+            Err(ParserError::TooDeep(256))
+        ));
+        let long_name = format!("λ 1 {}", "x".repeat(65));
+        assert!(matches!(
+            parse_db_with_config(&long_name, ParserConfig::untrusted()),
+            Err(ParserError::IdentifierTooLong(64))
+        ));
+        let long_input = format!("λ 1{}", " ".repeat(64 * 1024));
+        assert!(matches!(
+            parse_db_with_config(&long_input, ParserConfig::untrusted()),
+            Err(ParserError::InputTooLong(65536))
+        ));
+        let config = ParserConfig {
+            mode: ParseMode::Permissive,
+            ..ParserConfig::default()
+        };
+        let mut parser = DBParser::with_config(Lexer::new("λ 1 ! x"), config);
+        assert_eq!(parser.parse().unwrap(), "λ 1 x".parse().unwrap());
+        assert_eq!(parser.warnings(), [ParseWarning::StrayCharacter('!')]);
+        assert!(matches!(
+            "λ 1 ! x".parse::<DBIndices>(),
+            Err(ParserError::StrayCharacter('!'))
+        ));
+    }
+
+    #[test]
+    fn collapsed_binders_nest_like_separate_ones() {
+        assert_eq!(
+            parse("λx y z. x z (y z)").unwrap(),
+            parse("λx. λy. λz. x z (y z)").unwrap()
+        );
+        let depth = |code: &str| {
+            let config = ParserConfig {
+                max_depth: 3,
+                ..ParserConfig::default()
+            };
+            parse_with_config(code, config).is_ok()
+        };
+        assert_eq!(depth("λx y z. x"), depth("λx. λy. λz. x"));
+        assert_eq!(depth("λx y. x"), depth("λx. λy. x"));
+    }
+
+    // Names that need quoting, or that look like other tokens.
+    const AWKWARD_NAMES: [&str; 12] = [
+        "x",
+        "y'",
+        "x₁",
+        "foo_bar",
+        "two words",
+        "λ",
+        "`",
+        "\\",
+        "a.b",
+        "",
+        "0",
+        "#",
+    ];
+
+    // A random term with names drawn from AWKWARD_NAMES, renamed
+    // consistently so that some binders shadow others.
+    fn awkward_term(size: usize, rng: &mut generate::SplitMix64) -> LambdaTerm {
+        let term = generate::gen_closed_term(size, rng);
+        let mut names = HashMap::new();
+        term.map_variables(|id: &SmallName| {
+            let next = names.len();
+            let name = names.entry(id.clone()).or_insert(next);
+            SmallName::from(AWKWARD_NAMES[*name % AWKWARD_NAMES.len()])
+        })
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        use crate::print::{Parens, PrintOptions, Wrap};
+        let mut rng = generate::SplitMix64(348);
+        let options = [
+            PrintOptions::default(),
+            PrintOptions {
+                parens: Parens::Explicit,
+                ..PrintOptions::default()
+            },
+            PrintOptions {
+                parens: Parens::Clarifying,
+                collapse_binders: true,
+                space_after_dot: false,
+                ..PrintOptions::default()
+            },
+            PrintOptions {
+                ascii: true,
+                width: Some(20),
+                wrap: Wrap::Nested,
+                ..PrintOptions::default()
+            },
+            PrintOptions {
+                width: Some(12),
+                wrap: Wrap::Fill,
+                collapse_binders: true,
+                ..PrintOptions::default()
+            },
+        ];
+        for i in 0..2000 {
+            let term = awkward_term(1 + i % 40, &mut rng);
+            assert_eq!(parse(&term.to_string()).unwrap(), term, "{}", term);
+            for options in &options {
+                let printed = term.display_with(options).to_string();
+                assert_eq!(parse(&printed).unwrap(), term, "{}", printed);
+            }
+            let indices = DBIndices::from(term.clone());
+            assert_eq!(indices.to_string().parse::<DBIndices>().unwrap(), indices);
+        }
+    }
+
+    fn db_var(index: usize) -> DBTerm {
+        DBTerm::Variable(index)
+    }
+
+    fn db_lam(return_term: DBTerm) -> DBTerm {
+        DBTerm::Abstraction(Box::new(return_term))
+    }
+
+    fn db_app(function: DBTerm, argument: DBTerm) -> DBTerm {
+        DBTerm::Application {
+            function: Box::new(function),
+            argument: Box::new(argument),
+        }
+    }
+
+    #[test]
+    fn shift_moves_only_indices_past_the_cutoff() {
+        // λ 1 3
+        let term = db_lam(db_app(db_var(1), db_var(3)));
+        assert_eq!(
+            term.shift(-1, 0),
+            Some(db_lam(db_app(db_var(1), db_var(2))))
+        );
+        assert_eq!(term.shift(-2, 0), None);
+        assert_eq!(term.shift(-1, 1), None);
+        assert_eq!(term.shift(2, 0), Some(db_lam(db_app(db_var(1), db_var(5)))));
+        assert_eq!(term.shift(2, 2), Some(term.clone()));
+        assert_eq!(term.shift(0, 0), Some(term.clone()));
+        let free = db_lam(DBTerm::FreeVariable(String::from("y")));
+        assert_eq!(free.shift(-5, 0), Some(free.clone()));
+    }
+
+    #[test]
+    fn shift_refuses_to_underflow() {
+        assert_eq!(db_var(1).shift(-1, 0), None);
+        assert_eq!(db_var(1).shift(-2, 0), None);
+        assert_eq!(db_var(2).shift(isize::MIN, 0), None);
+        assert_eq!(db_var(usize::MAX).shift(1, 0), None);
+        assert_eq!(db_var(3).shift(-2, 0), Some(db_var(1)));
+    }
+
+    #[test]
+    fn lift_agrees_with_positive_shifts() {
+        let term = db_lam(db_app(db_var(1), db_app(db_var(2), db_lam(db_var(4)))));
+        for amount in 0..4 {
+            for cutoff in 0..4 {
+                assert_eq!(
+                    Some(term.lift(amount, cutoff)),
+                    term.shift(amount as isize, cutoff)
+                );
+                assert_eq!(
+                    term.lift(amount, cutoff).shift(-(amount as isize), cutoff),
+                    Some(term.clone())
+                );
+            }
+        }
+        let indices = DBIndices(term.clone());
+        assert_eq!(indices.lift(1, 0).shift(-1, 0), Some(indices));
+    }
+
+    fn tokens(code: &str) -> String {
+        let tokens: Result<Vec<Token>, LexError> = Lexer::new(code).collect();
+        format!("{:?}", tokens.unwrap())
+    }
+
+    #[test]
+    fn reports_stray_characters_and_carries_on() {
+        let lexed: Vec<_> = Lexer::new("x @ y\0").collect();
+        assert!(matches!(
+            lexed.as_slice(),
+            [
+                Ok(Token::Identifier(_)),
+                Err(LexError::StrayCharacter('@')),
+                Ok(Token::Identifier(_)),
+                Err(LexError::EmbeddedNul),
+            ]
+        ));
+        assert!(matches!(
+            parse("λx. x : y"),
+            Err(ParserError::StrayCharacter(':'))
+        ));
+        assert!(matches!(parse("x\0"), Err(ParserError::EmbeddedNul)));
+    }
+
+    #[test]
+    fn lexes_identifiers() {
+        for id in [
+            "foo_bar", "x'", "x₁", "x2y", "abc123", "_", "_x", "x′″", "élan",
+        ] {
+            assert!(is_plain_identifier(id), "{}", id);
+            assert_eq!(tokens(id), format!("[Identifier({:?})]", id));
+        }
+        assert_eq!(
+            tokens("λx₁. x₁ y'"),
+            r#"[Lambda, Identifier("x₁"), Dot, Identifier("x₁"), Identifier("y'")]"#
+        );
+    }
+
+    #[test]
+    fn rejects_identifiers_starting_with_a_digit() {
+        for id in ["1x", "0", "₁x", "'x", "", "x y", "λx"] {
+            assert!(!is_plain_identifier(id), "{}", id);
+        }
+        assert_eq!(tokens("1x"), r#"[Number("1"), Identifier("x")]"#);
+        assert!(matches!(
+            parse("λ1x. x"),
+            Err(ParserError::ExpectedIdentifierGot(Token::Number(_)))
+        ));
+    }
+}
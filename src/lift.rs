@@ -0,0 +1,354 @@
+use crate::names::{Decoration, NameSupply};
+use crate::provenance::{NodeId, Provenance, Tracked};
+use crate::small::SmallName;
+use crate::{app, apps, lam, var, LambdaTerm};
+use std::collections::HashSet;
+use std::fmt;
+
+// A closed function of several parameters whose body has no abstractions.
+// Its body refers only to its parameters and to earlier supercombinators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Supercombinator {
+    pub name: SmallName,
+    pub parameters: Vec<SmallName>,
+    pub body: LambdaTerm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupercombinatorProgram {
+    // Innermost abstractions first, so each definition only refers to the
+    // ones before it.
+    pub definitions: Vec<Supercombinator>,
+    pub main: LambdaTerm,
+}
+
+// Free variables in order of first occurrence, leaving out supercombinators.
+fn free_in_order(term: &LambdaTerm, globals: &HashSet<SmallName>, free: &mut Vec<SmallName>) {
+    match term {
+        LambdaTerm::Variable(id) => {
+            if !globals.contains(id) && !free.contains(id) {
+                free.push(id.clone());
+            }
+        }
+        LambdaTerm::Application { function, argument } => {
+            free_in_order(function, globals, free);
+            free_in_order(argument, globals, free);
+        }
+        LambdaTerm::Abstraction { .. } => unreachable!("lifted terms have no abstractions"),
+    }
+}
+
+struct Lifter {
+    names: NameSupply,
+    definitions: Vec<Supercombinator>,
+    globals: HashSet<SmallName>,
+}
+
+impl Lifter {
+    fn lift(&mut self, term: &LambdaTerm) -> LambdaTerm {
+        match term {
+            LambdaTerm::Variable(_) => term.clone(),
+            LambdaTerm::Application { function, argument } => {
+                app(self.lift(function), self.lift(argument))
+            }
+            LambdaTerm::Abstraction { .. } => {
+                // A run of abstractions becomes one supercombinator, ending
+                // early at a binder that shadows one before it.
+                let mut binders: Vec<SmallName> = Vec::new();
+                let mut body = term;
+                while let LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } = body
+                {
+                    if binders.contains(bound_variable) {
+                        break;
+                    }
+                    binders.push(bound_variable.clone());
+                    body = return_term;
+                }
+                let body = self.lift(body);
+                let mut free = Vec::new();
+                free_in_order(&body, &self.globals, &mut free);
+                free.retain(|id| !binders.contains(id));
+                let name = self.names.fresh("sc");
+                self.globals.insert(name.clone());
+                self.definitions.push(Supercombinator {
+                    name: name.clone(),
+                    parameters: free.iter().chain(&binders).cloned().collect(),
+                    body,
+                });
+                apps(var(name), free.iter().map(var))
+            }
+        }
+    }
+}
+
+// Lambda lifting: every abstraction becomes a supercombinator that takes
+// the abstraction's free variables as extra leading parameters, and is
+// replaced by that supercombinator applied to those variables. The
+// supercombinators are named sc, sc₁, sc₂, … avoiding the term's own names.
+pub fn lift(term: &LambdaTerm) -> SupercombinatorProgram {
+    let mut used = term.free_variables();
+    used.extend(term.bound_variables());
+    let mut lifter = Lifter {
+        names: NameSupply::with_decoration(used, Decoration::Subscripts),
+        definitions: Vec::new(),
+        globals: HashSet::new(),
+    };
+    let main = lifter.lift(term);
+    SupercombinatorProgram {
+        definitions: lifter.definitions,
+        main,
+    }
+}
+
+// Where each node of a lifted program came from: `main` numbers the nodes
+// of the main term and `bodies[i]` those of the body of `definitions[i]`.
+// A supercombinator applied to its free variables comes, as a whole, from
+// the abstraction it replaced.
+pub struct LiftProvenance {
+    pub main: Provenance,
+    pub bodies: Vec<Provenance>,
+}
+
+pub fn lift_tracked(term: &Tracked<LambdaTerm>) -> (SupercombinatorProgram, LiftProvenance) {
+    let program = lift(&term.term);
+    let mut bodies = vec![Provenance::new(); program.definitions.len()];
+    let mut main = Provenance::new();
+    align(
+        &program,
+        &term.term,
+        0,
+        &program.main,
+        &mut 0,
+        &mut main,
+        &mut bodies,
+    );
+    let provenance = LiftProvenance {
+        main: term.provenance.then(&main),
+        bodies: bodies
+            .iter()
+            .map(|body| term.provenance.then(body))
+            .collect(),
+    };
+    (program, provenance)
+}
+
+// Walks a term and what lifting made of it side by side, numbering the
+// output from `next` and the input from `id`.
+fn align(
+    program: &SupercombinatorProgram,
+    input: &LambdaTerm,
+    id: usize,
+    output: &LambdaTerm,
+    next: &mut usize,
+    provenance: &mut Provenance,
+    bodies: &mut [Provenance],
+) {
+    match (input, output) {
+        (
+            LambdaTerm::Application { function, argument },
+            LambdaTerm::Application {
+                function: lifted_function,
+                argument: lifted_argument,
+            },
+        ) => {
+            provenance.record(NodeId(*next), NodeId(id));
+            *next += 1;
+            align(
+                program,
+                function,
+                id + 1,
+                lifted_function,
+                next,
+                provenance,
+                bodies,
+            );
+            let id = id + 1 + function.node_count();
+            align(
+                program,
+                argument,
+                id,
+                lifted_argument,
+                next,
+                provenance,
+                bodies,
+            );
+        }
+        (LambdaTerm::Abstraction { .. }, _) => {
+            for offset in 0..output.node_count() {
+                provenance.record(NodeId(*next + offset), NodeId(id));
+            }
+            *next += output.node_count();
+            let mut head = output;
+            let mut free = 0;
+            while let LambdaTerm::Application { function, .. } = head {
+                head = function;
+                free += 1;
+            }
+            let index = program
+                .definitions
+                .iter()
+                .position(|definition| matches!(head, LambdaTerm::Variable(name) if *name == definition.name))
+                .expect("abstractions become supercombinators");
+            let definition = &program.definitions[index];
+            let mut body = input;
+            for _ in free..definition.parameters.len() {
+                if let LambdaTerm::Abstraction { return_term, .. } = body {
+                    body = return_term;
+                }
+            }
+            let id = id + definition.parameters.len() - free;
+            let mut provenance = Provenance::new();
+            align(
+                program,
+                body,
+                id,
+                &definition.body,
+                &mut 0,
+                &mut provenance,
+                bodies,
+            );
+            bodies[index] = provenance;
+        }
+        _ => {
+            provenance.record(NodeId(*next), NodeId(id));
+            *next += 1;
+        }
+    }
+}
+
+impl SupercombinatorProgram {
+    // Back to a single term by substituting each supercombinator's
+    // definition for its name. The result is β-equal to the lifted term.
+    pub fn to_lambda(&self) -> LambdaTerm {
+        let mut expanded: Vec<(&str, LambdaTerm)> = Vec::new();
+        let inline = |term: &LambdaTerm, expanded: &[(&str, LambdaTerm)]| {
+            expanded
+                .iter()
+                .rev()
+                .fold(term.clone(), |term, (name, definition)| {
+                    term.substitute(name, definition)
+                })
+        };
+        for definition in &self.definitions {
+            let body = inline(&definition.body, &expanded);
+            let function = definition
+                .parameters
+                .iter()
+                .rev()
+                .fold(body, |body, id| lam(id, body));
+            expanded.push((&definition.name, function));
+        }
+        inline(&self.main, &expanded)
+    }
+}
+
+//   sc f x = f (f x)
+//   sc₁ y = g y
+//   main = sc sc₁
+impl fmt::Display for SupercombinatorProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for definition in &self.definitions {
+            write!(f, "{}", definition.name)?;
+            for parameter in &definition.parameters {
+                write!(f, " {}", parameter)?;
+            }
+            writeln!(f, " = {}", definition.body)?;
+        }
+        write!(f, "main = {}", self.main)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::parse;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+    use crate::visit::Order;
+    use crate::{app, lam, var};
+
+    fn has_abstraction(term: &LambdaTerm) -> bool {
+        term.iter_subterms(Order::Pre)
+            .any(|(_, subterm)| matches!(subterm, LambdaTerm::Abstraction { .. }))
+    }
+
+    #[test]
+    fn lifts_free_variables_into_parameters() {
+        let program = lift(&parse("λf. λx. f (f x) y").unwrap());
+        assert_eq!(program.to_string(), "sc y f x = f (f x) y\nmain = sc y");
+        let program = lift(&parse("λx. λx. x").unwrap());
+        assert_eq!(program.definitions.len(), 2);
+    }
+
+    #[test]
+    fn bodies_have_no_abstractions_and_stay_beta_equal() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 500).rule(Beta);
+        let mut rng = SplitMix64(381);
+        let mut compared = 0;
+        for i in 0..1000 {
+            let term = gen_closed_term(1 + i % 25, &mut rng);
+            let term = app(app(term, var("p")), lam("q", var("q")));
+            let program = lift(&term);
+            assert!(!has_abstraction(&program.main), "{}", term);
+            for definition in &program.definitions {
+                assert!(!has_abstraction(&definition.body), "{}", term);
+            }
+            let Ok(expected) = rewriter.normalize(&term) else {
+                continue;
+            };
+            let normal = rewriter.normalize(&program.to_lambda()).unwrap();
+            assert!(normal.alpha_equivalent(&expected), "{}", term);
+            compared += 1;
+        }
+        assert!(compared > 500);
+    }
+
+    fn check_origins(output: &LambdaTerm, provenance: &Provenance, input: &LambdaTerm) {
+        for id in 0..output.node_count() {
+            let node = output.node(NodeId(id)).unwrap();
+            let origin = provenance
+                .origin(NodeId(id))
+                .expect("every node has an origin");
+            match (node, input.node(origin).unwrap()) {
+                (LambdaTerm::Variable(name), LambdaTerm::Variable(origin)) => {
+                    assert_eq!(name, origin, "{}", input)
+                }
+                (_, LambdaTerm::Abstraction { .. })
+                | (LambdaTerm::Application { .. }, LambdaTerm::Application { .. }) => (),
+                (node, origin) => panic!("{} came from {} in {}", node, origin, input),
+            }
+        }
+    }
+
+    #[test]
+    fn tracks_where_lifted_nodes_came_from() {
+        let term = parse("λf. λx. f (f x) y").unwrap();
+        let (program, provenance) = lift_tracked(&term.clone().tracked());
+        assert_eq!(program, lift(&term));
+        // main = sc y, all of which stands for the outer abstraction.
+        for id in 0..3 {
+            assert_eq!(provenance.main.origin(NodeId(id)), Some(NodeId(0)));
+        }
+        // The body f (f x) y starts below the two binders.
+        for id in 0..7 {
+            assert_eq!(
+                provenance.bodies[0].origin(NodeId(id)),
+                Some(NodeId(id + 2))
+            );
+        }
+
+        let mut rng = SplitMix64(290);
+        for i in 0..300 {
+            let term = gen_closed_term(1 + i % 25, &mut rng);
+            let term = app(term, var("p"));
+            let (program, provenance) = lift_tracked(&term.clone().tracked());
+            check_origins(&program.main, &provenance.main, &term);
+            for (definition, body) in program.definitions.iter().zip(&provenance.bodies) {
+                check_origins(&definition.body, body, &term);
+            }
+        }
+    }
+}
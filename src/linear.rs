@@ -0,0 +1,92 @@
+use crate::path::{Path, Step};
+use crate::small::SmallName;
+use crate::visit::Order;
+use crate::LambdaTerm;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Discipline {
+    // Every bound variable is used exactly once.
+    Linear,
+    // Every bound variable is used at most once.
+    Affine,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViolationKind {
+    Unused,
+    UsedMoreThanOnce,
+}
+
+// `binder` is the path to the offending abstraction and `uses` the paths to
+// each occurrence of the variable it binds, in left to right order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub bound_variable: SmallName,
+    pub binder: Path,
+    pub uses: Vec<Path>,
+}
+
+// Finds the uses of `id` in `term`, which sits at `path`, stopping at any
+// abstraction that binds the same name again.
+fn uses_of(term: &LambdaTerm, id: &str, path: Path, uses: &mut Vec<Path>) {
+    match term {
+        LambdaTerm::Variable(variable) if variable == id => uses.push(path),
+        LambdaTerm::Variable(_) => (),
+        LambdaTerm::Abstraction { bound_variable, .. } if bound_variable == id => (),
+        LambdaTerm::Abstraction { return_term, .. } => {
+            uses_of(return_term, id, path.child(Step::Body), uses)
+        }
+        LambdaTerm::Application { function, argument } => {
+            uses_of(function, id, path.child(Step::Function), uses);
+            uses_of(argument, id, path.child(Step::Argument), uses);
+        }
+    }
+}
+
+// Checks every abstraction in `term`, outermost first, giving all the
+// violations found.
+pub fn check(term: &LambdaTerm, discipline: Discipline) -> Result<(), Vec<Violation>> {
+    let mut violations = Vec::new();
+    for (path, subterm) in term.iter_subterms(Order::Pre) {
+        if let LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } = subterm
+        {
+            let mut uses = Vec::new();
+            uses_of(
+                return_term,
+                bound_variable,
+                path.child(Step::Body),
+                &mut uses,
+            );
+            let kind = match uses.len() {
+                0 if discipline == Discipline::Linear => ViolationKind::Unused,
+                0 | 1 => continue,
+                _ => ViolationKind::UsedMoreThanOnce,
+            };
+            violations.push(Violation {
+                kind,
+                bound_variable: bound_variable.clone(),
+                binder: path,
+                uses,
+            });
+        }
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+impl LambdaTerm {
+    pub fn is_linear(&self) -> bool {
+        check(self, Discipline::Linear).is_ok()
+    }
+
+    pub fn is_affine(&self) -> bool {
+        check(self, Discipline::Affine).is_ok()
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{Lexer, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    // An abstraction body made of several items, as in `λx. f x`, which
+    // reaches as far right as possible rather than stopping at `f`.
+    AbstractionBodyExtends,
+    // Three or more items applied in a row, as in `f x y`, which groups to
+    // the left as `(f x) y`.
+    LeftAssociativeChain,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub kind: LintKind,
+    pub token_index: usize,
+    pub message: String,
+}
+
+// Walks the tokens group by group. Inside a group each item is an atom, a
+// parenthesised group, or an abstraction, which swallows the rest of the
+// group as its body. The scan is lenient: malformed input is left for the
+// parser to report.
+struct Scanner<'t> {
+    tokens: &'t [Token],
+    position: usize,
+    lints: Vec<Lint>,
+}
+
+impl<'t> Scanner<'t> {
+    // Returns the number of items in the group, stopping before the `)` or
+    // the end of input that closes it.
+    fn group(&mut self) -> usize {
+        let start = self.position;
+        let mut items = 0;
+        while let Some(token) = self.tokens.get(self.position) {
+            match token {
+                Token::RParen => break,
+                Token::LParen => {
+                    self.position += 1;
+                    self.group();
+                    if let Some(Token::RParen) = self.tokens.get(self.position) {
+                        self.position += 1;
+                    }
+                }
+                Token::Lambda => {
+                    let lambda = self.position;
+                    while let Some(token) = self.tokens.get(self.position) {
+                        self.position += 1;
+                        if let Token::Dot = token {
+                            break;
+                        }
+                    }
+                    if self.group() > 1 {
+                        self.lints.push(Lint {
+                            kind: LintKind::AbstractionBodyExtends,
+                            token_index: lambda,
+                            message: format!(
+                                "the body of this abstraction extends to token {}; \
+                                 parenthesise it to make the grouping explicit",
+                                self.position
+                            ),
+                        });
+                    }
+                }
+                Token::Hash => {
+                    self.position += 1;
+                    continue;
+                }
+                _ => self.position += 1,
+            }
+            items += 1;
+        }
+        if items > 2 {
+            self.lints.push(Lint {
+                kind: LintKind::LeftAssociativeChain,
+                token_index: start,
+                message: format!(
+                    "{} items applied in a row group to the left, as ((a b) c ...)",
+                    items
+                ),
+            });
+        }
+        items
+    }
+}
+
+pub fn lint_tokens(tokens: &[Token]) -> Vec<Lint> {
+    let mut scanner = Scanner {
+        tokens,
+        position: 0,
+        lints: Vec::new(),
+    };
+    while scanner.position < tokens.len() {
+        scanner.group();
+        // Skip a stray `)` so the rest of the input still gets looked at.
+        scanner.position += 1;
+    }
+    scanner.lints.sort_by_key(|lint| lint.token_index);
+    scanner.lints
+}
+
+pub fn lint(code: &str) -> Vec<Lint> {
+    // Stray characters are left for the parser to report.
+    let tokens: Vec<Token> = Lexer::new(code).filter_map(Result::ok).collect();
+    lint_tokens(&tokens)
+}
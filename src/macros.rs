@@ -0,0 +1,92 @@
+// Builds a LambdaTerm from Rust tokens, with no parsing at run time. Binders
+// use the pipe syntax, so `lambda!(|f, x| f (f x))` is `λf. λx. f (f x)`.
+// Application is left associative and an abstraction reaches as far right
+// as it can, as in the text syntax. A Rust expression in braces is spliced
+// in as a subterm, so `lambda!(|x| {church_nat(2)} x)` applies a term built
+// elsewhere; the expression must be a LambdaTerm and is moved.
+#[macro_export]
+macro_rules! lambda {
+    (@atom ($($inner:tt)+)) => {
+        $crate::lambda!($($inner)+)
+    };
+    (@atom { $term:expr }) => {
+        $term
+    };
+    (@atom $id:ident) => {
+        $crate::LambdaTerm::Variable($crate::small::SmallName::new(stringify!($id)))
+    };
+    (@apply $function:expr;) => {
+        $function
+    };
+    (@apply $function:expr; | $($rest:tt)+) => {
+        $crate::LambdaTerm::Application {
+            function: ::std::boxed::Box::new($function),
+            argument: ::std::boxed::Box::new($crate::lambda!(| $($rest)+)),
+        }
+    };
+    (@apply $function:expr; $argument:tt $($rest:tt)*) => {
+        $crate::lambda!(@apply $crate::LambdaTerm::Application {
+            function: ::std::boxed::Box::new($function),
+            argument: ::std::boxed::Box::new($crate::lambda!(@atom $argument)),
+        }; $($rest)*)
+    };
+    (| $binder:ident | $($body:tt)+) => {
+        $crate::LambdaTerm::Abstraction {
+            bound_variable: $crate::small::SmallName::new(stringify!($binder)),
+            return_term: ::std::boxed::Box::new($crate::lambda!($($body)+)),
+        }
+    };
+    (| $binder:ident, $($binders:ident),+ | $($body:tt)+) => {
+        $crate::LambdaTerm::Abstraction {
+            bound_variable: $crate::small::SmallName::new(stringify!($binder)),
+            return_term: ::std::boxed::Box::new($crate::lambda!(| $($binders),+ | $($body)+)),
+        }
+    };
+    ($head:tt $($rest:tt)*) => {
+        $crate::lambda!(@apply $crate::lambda!(@atom $head); $($rest)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encodings::church_nat;
+    use crate::{app, lam, parse, var, LambdaTerm};
+
+    #[test]
+    fn builds_nested_binders() {
+        assert_eq!(lambda!(|x| x), lam("x", var("x")));
+        assert_eq!(
+            lambda!(|f, x| f (f x)),
+            lam("f", lam("x", app(var("f"), app(var("f"), var("x")))))
+        );
+        assert_eq!(lambda!(|f| |x| f x), lambda!(|f, x| f x));
+        assert_eq!(
+            lambda!(|x, y, z| x z (y z)),
+            parse("λx. λy. λz. x z (y z)").unwrap()
+        );
+    }
+
+    #[test]
+    fn application_associates_left_and_binders_reach_right() {
+        assert_eq!(lambda!(a b c), app(app(var("a"), var("b")), var("c")));
+        assert_eq!(lambda!(a (b c)), app(var("a"), app(var("b"), var("c"))));
+        assert_eq!(lambda!(f |x| x y), parse("f (λx. x y)").unwrap());
+        assert_eq!(lambda!((|x| x) y), parse("(λx. x) y").unwrap());
+        assert_eq!(lambda!((a)), var("a"));
+    }
+
+    #[test]
+    fn splices_expressions() {
+        let two = church_nat(2);
+        assert_eq!(
+            lambda!(|x| {two.clone()} x),
+            lam("x", app(church_nat(2), var("x")))
+        );
+        assert_eq!(
+            lambda!({ two } f x),
+            parse("(λf. λx. f (f x)) f x").unwrap()
+        );
+        let term: LambdaTerm = lambda!(g { var("y") });
+        assert_eq!(term, app(var("g"), var("y")));
+    }
+}
@@ -1,17 +1,283 @@
 use io::prelude::*;
-use std::io;
+use std::io::{self, IsTerminal};
 
 use rs_lambda::*;
 
+const USAGE: &str = "\
+usage: rs-lambda [options] [file ...]
+       rs-lambda repl [--env file] [--dump-env] [--history file | --no-history]
+       rs-lambda tour
+       rs-lambda diff <term> <term>
+
+reads a term from each file, from each -e, or else from stdin
+  -e, --expr <term>         use <term> as an input
+  -o, --output <format>     print each term as one of
+                              summary (the default), named, db-indices,
+                              db-levels, json, dot, diagram, diagram-svg
+      --json, --dot, --diagram, --diagram=svg
+                            shorthands for the matching --output
+      --normalize           beta reduce each term before printing it
+      --strategy <strategy> normal (the default) or applicative
+      --limit <n>           give up normalizing after n steps (1000)
+      --trace[=underline|color]
+                            print every step of normalizing each term,
+                            marking the redex contracted next
+      --lines               treat each line of input as a separate term,
+                            printing one result or error per line
+                            (--output named unless told otherwise)
+      --step                reduce each term interactively, choosing
+                            which redex to contract at each step
+      --machine-trace[=json]
+                            run each term on the Krivine machine, printing
+                            every transition
+  -h, --help                show this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Summary,
+    Named,
+    DBIndices,
+    DBLevels,
+    Json,
+    Dot,
+    Diagram,
+    DiagramSvg,
+}
+
+enum Input {
+    File(String),
+    Expression(String),
+}
+
+struct Options {
+    inputs: Vec<Input>,
+    output: Output,
+    normalize: bool,
+    strategy: rewrite::Strategy,
+    limit: usize,
+    machine_trace: Option<bool>,
+    step: bool,
+    trace: Option<trace::Highlight>,
+    lines: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        inputs: Vec::new(),
+        output: Output::Summary,
+        normalize: false,
+        strategy: rewrite::Strategy::TopDown,
+        limit: 1000,
+        machine_trace: None,
+        step: false,
+        trace: None,
+        lines: false,
+    };
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| format!("{} expects a value", flag))
+        };
+        match arg.as_str() {
+            "-e" | "--expr" => options.inputs.push(Input::Expression(value(arg)?.clone())),
+            "-o" | "--output" => {
+                output = Some(match value(arg)?.as_str() {
+                    "summary" => Output::Summary,
+                    "named" => Output::Named,
+                    "db-indices" => Output::DBIndices,
+                    "db-levels" => Output::DBLevels,
+                    "json" => Output::Json,
+                    "dot" => Output::Dot,
+                    "diagram" => Output::Diagram,
+                    "diagram-svg" => Output::DiagramSvg,
+                    other => return Err(format!("unknown output format {}", other)),
+                })
+            }
+            "--json" => output = Some(Output::Json),
+            "--dot" => output = Some(Output::Dot),
+            "--diagram" => output = Some(Output::Diagram),
+            "--diagram=svg" => output = Some(Output::DiagramSvg),
+            "--normalize" => options.normalize = true,
+            "--strategy" => {
+                options.strategy = match value(arg)?.as_str() {
+                    "normal" => rewrite::Strategy::TopDown,
+                    "applicative" => rewrite::Strategy::BottomUp,
+                    other => return Err(format!("unknown strategy {}", other)),
+                }
+            }
+            "--limit" => {
+                let limit = value(arg)?;
+                options.limit = limit
+                    .parse()
+                    .map_err(|_| format!("invalid step limit {}", limit))?;
+            }
+            "--step" => options.step = true,
+            "--lines" => options.lines = true,
+            "--trace" => options.trace = Some(trace::Highlight::None),
+            "--trace=underline" => options.trace = Some(trace::Highlight::Underline),
+            "--trace=color" => options.trace = Some(trace::Highlight::Color),
+            "--machine-trace" => options.machine_trace = Some(false),
+            "--machine-trace=json" => options.machine_trace = Some(true),
+            "-" => options.inputs.push(Input::File(String::from("-"))),
+            other if other.starts_with('-') => return Err(format!("unknown option {}", other)),
+            path => options.inputs.push(Input::File(String::from(path))),
+        }
+    }
+    // The summary spans several lines, so --lines prints terms by default.
+    options.output = match output {
+        Some(output) => output,
+        None if options.lines => Output::Named,
+        None => Output::Summary,
+    };
+    Ok(options)
+}
+
 fn main() -> io::Result<()> {
-    match {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("repl") {
+        return run_repl(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("tour") {
+        return tour::Tour::new().run(io::stdin().lock(), &mut io::stdout());
+    }
+    if args.first().map(String::as_str) == Some("diff") {
+        return run_diff(&args[1..]);
+    }
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut sources = Vec::new();
+    if options.inputs.is_empty() {
         let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer)?;
-        let tokens = Lexer::new(&buffer);
-        let mut parser = Parser::new(tokens);
-        parser.parse()
-    } {
-        Ok(ast) => {
+        // Stepping reads its commands from stdin too, so only the first
+        // line holds the term.
+        if options.step {
+            io::stdin().read_line(&mut buffer)?;
+        } else {
+            io::stdin().read_to_string(&mut buffer)?;
+        }
+        sources.push(buffer);
+    }
+    for input in &options.inputs {
+        sources.push(match input {
+            Input::Expression(code) => code.clone(),
+            Input::File(path) if path == "-" => {
+                let mut buffer = String::new();
+                io::stdin().read_to_string(&mut buffer)?;
+                buffer
+            }
+            Input::File(path) => std::fs::read_to_string(path)?,
+        });
+    }
+    let mut failed = false;
+    for source in &sources {
+        if options.lines {
+            // Blank lines are echoed, so output lines up with input.
+            for line in source.lines() {
+                if line.trim().is_empty() {
+                    println!();
+                } else if !run(line, &options) {
+                    failed = true;
+                }
+            }
+        } else if !run(source, &options) {
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Reports a failure: as a JSON diagnostic on stderr in JSON output, in line
+// with the results in --lines mode, and as a message on stderr otherwise.
+fn report(options: &Options, message: String, diagnostic: diagnostic::Diagnostic) {
+    if options.output == Output::Json {
+        eprintln!("{}", diagnostic.to_json());
+        // A placeholder keeps stdout one line per input line.
+        if options.lines {
+            println!("null");
+        }
+    } else if options.lines {
+        println!("{}", message);
+    } else {
+        eprintln!("{}", message);
+    }
+}
+
+// Parses, normalizes and prints one input, giving false if any of that
+// failed.
+fn run(source: &str, options: &Options) -> bool {
+    let ast = match source.parse::<LambdaTerm>() {
+        Ok(ast) => ast,
+        Err(err) => {
+            // Reparsed for the span, keeping to err should the two differ.
+            let diagnostic = match diagnostic::parse(source) {
+                Err(diagnostic) if diagnostic.code == err.code() => diagnostic,
+                _ => diagnostic::Diagnostic::from(&err),
+            };
+            report(options, format!("Error = {:?}", err), diagnostic);
+            return false;
+        }
+    };
+    if let Some(highlight) = options.trace {
+        let rewriter = rewrite::Rewriter::new(options.strategy, options.limit).rule(rewrite::Beta);
+        let trace_options = trace::TraceOptions {
+            highlight,
+            ..trace::TraceOptions::default()
+        };
+        return match trace::write_trace(&mut io::stdout(), &rewriter, &ast, &trace_options) {
+            Ok(Ok(_)) => true,
+            Ok(Err(err)) => {
+                let diagnostic = diagnostic::Diagnostic::from(&err);
+                report(options, diagnostic.message.clone(), diagnostic);
+                false
+            }
+            Err(err) => {
+                let diagnostic = diagnostic::Diagnostic {
+                    code: "io",
+                    message: format!("couldn't write the trace: {}", err),
+                    span: None,
+                };
+                report(options, diagnostic.message.clone(), diagnostic);
+                false
+            }
+        };
+    }
+    let ast = if options.normalize {
+        let rewriter = rewrite::Rewriter::new(options.strategy, options.limit).rule(rewrite::Beta);
+        match rewriter.normalize(&ast) {
+            Ok(normal) => normal,
+            Err(err) => {
+                let diagnostic = diagnostic::Diagnostic::from(&err);
+                report(options, diagnostic.message.clone(), diagnostic);
+                return false;
+            }
+        }
+    } else {
+        ast
+    };
+    if options.step {
+        let mut stepper = step::Stepper::new(ast);
+        return stepper.run(io::stdin().lock(), &mut io::stdout()).is_ok();
+    }
+    if let Some(json) = options.machine_trace {
+        machine_trace(&ast.into(), json);
+        return true;
+    }
+    match options.output {
+        Output::Summary => {
             println!("Free Variables: {:#?}", ast.free_variables());
             println!("Bound Variables: {:#?}", ast.bound_variables());
             println!("{:#?}", ast);
@@ -19,7 +285,164 @@ fn main() -> io::Result<()> {
             let db: DBIndices = ast.into();
             println!("De Brujin Indices: {}", db)
         }
-        Err(err) => println!("Error = {:?}", err),
+        Output::Named => println!("{}", ast),
+        Output::DBIndices => println!("{}", DBIndices::from(ast)),
+        Output::DBLevels => println!("{}", DBLevels::from(ast)),
+        Output::Json => println!("{}", summary_json(&ast)),
+        Output::Dot => print!("{}", ast.to_dot()),
+        Output::Diagram => print!("{}", ast.diagram().to_ascii()),
+        Output::DiagramSvg => print!("{}", ast.diagram().to_svg()),
+    }
+    true
+}
+
+// Prints where two terms differ, one position per line, and exits with
+// status 1 if they do, as diff does.
+fn run_diff(args: &[String]) -> io::Result<()> {
+    let (left, right) = match args {
+        [left, right] => (left, right),
+        _ => {
+            eprintln!("usage: rs-lambda diff <term> <term>");
+            std::process::exit(2);
+        }
+    };
+    let (left, right) = match (left.parse::<LambdaTerm>(), right.parse::<LambdaTerm>()) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(err), _) | (_, Err(err)) => {
+            eprintln!("Error = {:?}", err);
+            std::process::exit(2);
+        }
     };
+    let differences = diff::diff(&left, &right);
+    for difference in &differences {
+        println!(
+            "{}: {} ≠ {}",
+            difference.path, difference.left, difference.right
+        );
+    }
+    if !differences.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_repl(args: &[String]) -> io::Result<()> {
+    let mut session = repl::Session::new();
+    let mut dump_env = false;
+    // Interactive sessions keep their history in the home directory unless
+    // told otherwise; piped input is left out of it.
+    let mut history = std::env::var_os("HOME")
+        .filter(|_| io::stdin().is_terminal())
+        .map(|home| std::path::PathBuf::from(home).join(".rs-lambda-history"));
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--env" => {
+                let path = args.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "--env expects a file")
+                })?;
+                session.environment = environment::Environment::from_json(
+                    &std::fs::read_to_string(path)?,
+                )
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+            }
+            "--dump-env" => dump_env = true,
+            "--history" => {
+                let path = args.next().ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "--history expects a file")
+                })?;
+                history = Some(std::path::PathBuf::from(path));
+            }
+            "--no-history" => history = None,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown argument {}", other),
+                ))
+            }
+        }
+    }
+    if let Some(path) = &history {
+        session.load_history(path)?;
+    }
+    #[cfg(feature = "line-editing")]
+    if io::stdin().is_terminal() {
+        session.run_editing(&mut io::stdout())?;
+    } else {
+        session.run(io::stdin().lock(), &mut io::stdout())?;
+    }
+    #[cfg(not(feature = "line-editing"))]
+    session.run(io::stdin().lock(), &mut io::stdout())?;
+    if let Some(path) = &history {
+        session.save_history(path)?;
+    }
+    if dump_env {
+        println!("{}", session.environment.to_json());
+    }
     Ok(())
 }
+
+// The same facts the default output prints, as one JSON object. Variable
+// sets are sorted so that the output is stable between runs.
+fn summary_json(ast: &LambdaTerm) -> json::Json {
+    use json::Json;
+    let names = |set: std::collections::HashSet<small::SmallName>| {
+        let mut names: Vec<String> = set.into_iter().map(String::from).collect();
+        names.sort();
+        Json::Array(names.into_iter().map(Json::String).collect())
+    };
+    let db = DBIndices::from(ast.clone());
+    Json::Object(vec![
+        (String::from("term"), ast.to_json()),
+        (
+            String::from("reconstruction"),
+            Json::String(ast.to_string()),
+        ),
+        (String::from("free_variables"), names(ast.free_variables())),
+        (
+            String::from("bound_variables"),
+            names(ast.bound_variables()),
+        ),
+        (String::from("de_bruijn"), db.to_json()),
+        (String::from("de_bruijn_text"), Json::String(db.to_string())),
+    ])
+}
+
+const MACHINE_TRACE_LIMIT: usize = 1000;
+
+fn machine_trace(program: &DBIndices, json: bool) {
+    let mut machine = krivine::Machine::new(program);
+    let print = |machine: &krivine::Machine, transition: Option<krivine::Transition>| {
+        if json {
+            let mut state = machine.to_json();
+            if let (json::Json::Object(members), Some(transition)) = (&mut state, transition) {
+                members.insert(
+                    0,
+                    (
+                        String::from("transition"),
+                        json::Json::String(transition.to_string()),
+                    ),
+                );
+            }
+            println!("{}", state);
+        } else {
+            match transition {
+                Some(transition) => println!("{:>6} {}", transition, machine),
+                None => println!("{:>6} {}", "", machine),
+            }
+        }
+    };
+    print(&machine, None);
+    for _ in 0..MACHINE_TRACE_LIMIT {
+        match machine.step() {
+            Some(transition) => print(&machine, Some(transition)),
+            None => {
+                if !json {
+                    println!("\nResult: {}", machine.readback());
+                }
+                return;
+            }
+        }
+    }
+    eprintln!("stopped after {} transitions", MACHINE_TRACE_LIMIT);
+}
@@ -0,0 +1,77 @@
+use crate::{DBTerm, LambdaTerm};
+
+// Sizes count every node; depth counts the nodes on the longest path from
+// the root down to a variable, so a lone variable has depth 1.
+impl LambdaTerm {
+    pub fn size(&self) -> usize {
+        self.node_count()
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            LambdaTerm::Variable(_) => 1,
+            LambdaTerm::Abstraction { return_term, .. } => 1 + return_term.depth(),
+            LambdaTerm::Application { function, argument } => {
+                1 + function.depth().max(argument.depth())
+            }
+        }
+    }
+
+    pub fn count_binders(&self) -> usize {
+        match self {
+            LambdaTerm::Variable(_) => 0,
+            LambdaTerm::Abstraction { return_term, .. } => 1 + return_term.count_binders(),
+            LambdaTerm::Application { function, argument } => {
+                function.count_binders() + argument.count_binders()
+            }
+        }
+    }
+
+    pub fn count_redexes(&self) -> usize {
+        match self {
+            LambdaTerm::Variable(_) => 0,
+            LambdaTerm::Abstraction { return_term, .. } => return_term.count_redexes(),
+            LambdaTerm::Application { function, argument } => {
+                let redex = matches!(**function, LambdaTerm::Abstraction { .. });
+                redex as usize + function.count_redexes() + argument.count_redexes()
+            }
+        }
+    }
+}
+
+impl DBTerm {
+    pub fn size(&self) -> usize {
+        self.node_count()
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            DBTerm::Variable(_) | DBTerm::FreeVariable(_) => 1,
+            DBTerm::Abstraction(return_term) => 1 + return_term.depth(),
+            DBTerm::Application { function, argument } => {
+                1 + function.depth().max(argument.depth())
+            }
+        }
+    }
+
+    pub fn count_binders(&self) -> usize {
+        match self {
+            DBTerm::Variable(_) | DBTerm::FreeVariable(_) => 0,
+            DBTerm::Abstraction(return_term) => 1 + return_term.count_binders(),
+            DBTerm::Application { function, argument } => {
+                function.count_binders() + argument.count_binders()
+            }
+        }
+    }
+
+    pub fn count_redexes(&self) -> usize {
+        match self {
+            DBTerm::Variable(_) | DBTerm::FreeVariable(_) => 0,
+            DBTerm::Abstraction(return_term) => return_term.count_redexes(),
+            DBTerm::Application { function, argument } => {
+                let redex = matches!(**function, DBTerm::Abstraction(_));
+                redex as usize + function.count_redexes() + argument.count_redexes()
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::names::NameSupply;
+use crate::{DBIndices, DBTerm, LambdaTerm, NameScheme};
+use std::fmt;
+
+// Bound variables are de Bruijn indices and free variables keep their names,
+// as in DBTerm, but a term is only ever taken apart by opening an abstraction
+// with a name and put back together by closing over one. Indices then never
+// need shifting, since nothing with a dangling index is substituted in, and
+// names never need renaming, since no binder carries one. Algorithms that
+// would otherwise have to avoid capture are best written against this form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LocallyNameless(pub DBTerm);
+
+impl DBTerm {
+    // Replaces the indices that refer to the abstraction just outside this
+    // term, which should be its body, with `replacement`. The replacement is
+    // expected to be locally closed.
+    pub fn open_with(&self, replacement: &DBTerm) -> DBTerm {
+        fn open_mut(term: &DBTerm, replacement: &DBTerm, abstraction_depth: usize) -> DBTerm {
+            match term {
+                DBTerm::Variable(index) if *index == abstraction_depth + 1 => replacement.clone(),
+                DBTerm::Variable(_) | DBTerm::FreeVariable(_) => term.clone(),
+                DBTerm::Abstraction(return_term) => DBTerm::Abstraction(Box::new(open_mut(
+                    return_term,
+                    replacement,
+                    abstraction_depth + 1,
+                ))),
+                DBTerm::Application { function, argument } => DBTerm::Application {
+                    function: Box::new(open_mut(function, replacement, abstraction_depth)),
+                    argument: Box::new(open_mut(argument, replacement, abstraction_depth)),
+                },
+            }
+        }
+        open_mut(self, replacement, 0)
+    }
+
+    pub fn open(&self, id: &str) -> DBTerm {
+        self.open_with(&DBTerm::FreeVariable(String::from(id)))
+    }
+
+    // The inverse of open: turns free uses of `id` into indices referring to
+    // an abstraction placed just outside the result.
+    pub fn close(&self, id: &str) -> DBTerm {
+        fn close_mut(term: &DBTerm, id: &str, abstraction_depth: usize) -> DBTerm {
+            match term {
+                DBTerm::FreeVariable(free) if free == id => DBTerm::Variable(abstraction_depth + 1),
+                DBTerm::Variable(_) | DBTerm::FreeVariable(_) => term.clone(),
+                DBTerm::Abstraction(return_term) => {
+                    DBTerm::Abstraction(Box::new(close_mut(return_term, id, abstraction_depth + 1)))
+                }
+                DBTerm::Application { function, argument } => DBTerm::Application {
+                    function: Box::new(close_mut(function, id, abstraction_depth)),
+                    argument: Box::new(close_mut(argument, id, abstraction_depth)),
+                },
+            }
+        }
+        close_mut(self, id, 0)
+    }
+
+    // Whether every index refers to an abstraction within the term.
+    pub fn is_locally_closed(&self) -> bool {
+        fn closed_under(term: &DBTerm, abstraction_depth: usize) -> bool {
+            match term {
+                DBTerm::Variable(index) => *index <= abstraction_depth,
+                DBTerm::FreeVariable(_) => true,
+                DBTerm::Abstraction(return_term) => {
+                    closed_under(return_term, abstraction_depth + 1)
+                }
+                DBTerm::Application { function, argument } => {
+                    closed_under(function, abstraction_depth)
+                        && closed_under(argument, abstraction_depth)
+                }
+            }
+        }
+        closed_under(self, 0)
+    }
+}
+
+impl LocallyNameless {
+    // λid. body
+    pub fn abstraction(id: &str, body: &LocallyNameless) -> LocallyNameless {
+        let LocallyNameless(body) = body;
+        LocallyNameless(DBTerm::Abstraction(Box::new(body.close(id))))
+    }
+
+    // Opens an abstraction with a name from `names`, giving that name and the
+    // body. Anything else gives None.
+    pub fn unbind(&self, names: &mut NameSupply, base: &str) -> Option<(String, LocallyNameless)> {
+        match self {
+            LocallyNameless(DBTerm::Abstraction(return_term)) => {
+                let id = String::from(names.fresh(base));
+                let body = return_term.open(&id);
+                Some((id, LocallyNameless(body)))
+            }
+            _ => None,
+        }
+    }
+
+    // Contracts a β-redex at the root: (λ M) N becomes M opened with N.
+    pub fn beta(&self) -> Option<LocallyNameless> {
+        match self {
+            LocallyNameless(DBTerm::Application { function, argument }) => match &**function {
+                DBTerm::Abstraction(return_term) => {
+                    Some(LocallyNameless(return_term.open_with(argument)))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_named(&self, scheme: NameScheme) -> LambdaTerm {
+        let LocallyNameless(term) = self;
+        DBIndices(term.clone()).to_named(scheme)
+    }
+}
+
+// A term converted from names is locally closed, and its indices are the
+// ones DBIndices gives it.
+impl From<LambdaTerm> for LocallyNameless {
+    fn from(lambda: LambdaTerm) -> LocallyNameless {
+        let DBIndices(term) = lambda.into();
+        LocallyNameless(term)
+    }
+}
+
+impl From<DBIndices> for LocallyNameless {
+    fn from(indices: DBIndices) -> LocallyNameless {
+        let DBIndices(term) = indices;
+        LocallyNameless(term)
+    }
+}
+
+impl From<LocallyNameless> for DBIndices {
+    fn from(term: LocallyNameless) -> DBIndices {
+        let LocallyNameless(term) = term;
+        DBIndices(term)
+    }
+}
+
+impl From<LocallyNameless> for LambdaTerm {
+    fn from(term: LocallyNameless) -> LambdaTerm {
+        term.to_named(NameScheme::Letters)
+    }
+}
+
+impl fmt::Display for LocallyNameless {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let LocallyNameless(term) = self;
+        term.fmt(f)
+    }
+}
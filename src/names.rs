@@ -0,0 +1,125 @@
+use crate::small::SmallName;
+use crate::LambdaTerm;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoration {
+    // x', x'', x''', …
+    Primes,
+    // x₁, x₂, x₃, …
+    Subscripts,
+}
+
+// Hands out identifiers that avoid a set of names already in use, and
+// every name it has handed out before.
+#[derive(Debug, Clone)]
+pub struct NameSupply {
+    used: HashSet<SmallName>,
+    decoration: Decoration,
+}
+
+impl NameSupply {
+    pub fn new<N: Into<SmallName>>(used: impl IntoIterator<Item = N>) -> NameSupply {
+        NameSupply::with_decoration(used, Decoration::Primes)
+    }
+
+    pub fn with_decoration<N: Into<SmallName>>(
+        used: impl IntoIterator<Item = N>,
+        decoration: Decoration,
+    ) -> NameSupply {
+        NameSupply {
+            used: used.into_iter().map(Into::into).collect(),
+            decoration,
+        }
+    }
+
+    pub fn avoid(&mut self, name: impl Into<SmallName>) {
+        self.used.insert(name.into());
+    }
+
+    pub fn is_used(&self, name: &str) -> bool {
+        self.used.contains(name)
+    }
+
+    // `base` itself if that's free, otherwise the first decorated form of it
+    // that is.
+    pub fn fresh(&mut self, base: &str) -> SmallName {
+        let mut candidate = String::from(base);
+        let mut n = 0u64;
+        while self.used.contains(candidate.as_str()) {
+            n += 1;
+            candidate = match self.decoration {
+                Decoration::Primes => {
+                    candidate.push('\'');
+                    candidate
+                }
+                Decoration::Subscripts => format!("{}{}", base, subscript(n)),
+            };
+        }
+        let candidate = SmallName::from(candidate);
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+fn subscript(n: u64) -> String {
+    n.to_string()
+        .chars()
+        .map(|digit| match digit {
+            '0' => '₀',
+            '1' => '₁',
+            '2' => '₂',
+            '3' => '₃',
+            '4' => '₄',
+            '5' => '₅',
+            '6' => '₆',
+            '7' => '₇',
+            '8' => '₈',
+            _ => '₉',
+        })
+        .collect()
+}
+
+impl LambdaTerm {
+    // α-renames so that no two abstractions bind the same name and no bound
+    // name is also free (the Barendregt convention). Binders keep their
+    // names where they can.
+    pub fn make_binders_unique(&self) -> LambdaTerm {
+        fn rename(
+            term: &LambdaTerm,
+            names: &mut NameSupply,
+            scope: &mut Vec<(SmallName, SmallName)>,
+        ) -> LambdaTerm {
+            match term {
+                LambdaTerm::Variable(id) => {
+                    match scope.iter().rev().find(|(original, _)| original == id) {
+                        Some((_, renamed)) => LambdaTerm::Variable(renamed.clone()),
+                        None => term.clone(),
+                    }
+                }
+                LambdaTerm::Application { function, argument } => LambdaTerm::Application {
+                    function: Box::new(rename(function, names, scope)),
+                    argument: Box::new(rename(argument, names, scope)),
+                },
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    let fresh = names.fresh(bound_variable);
+                    scope.push((bound_variable.clone(), fresh.clone()));
+                    let return_term = rename(return_term, names, scope);
+                    scope.pop();
+                    LambdaTerm::Abstraction {
+                        bound_variable: fresh,
+                        return_term: Box::new(return_term),
+                    }
+                }
+            }
+        }
+        rename(
+            self,
+            &mut NameSupply::new(self.free_variables()),
+            &mut Vec::new(),
+        )
+    }
+}
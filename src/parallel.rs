@@ -0,0 +1,180 @@
+use crate::rewrite::RewriteError;
+use crate::LambdaTerm;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Subterms smaller than this are normalized on the current thread, since
+// handing them to rayon would cost more than the work it saves.
+const FORK_THRESHOLD: usize = 256;
+
+// The pool's threads get the stack a main thread usually has, as
+// normalizing recurses as deep as the term nests.
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// Shared by every thread working on the one term, so the step limit holds
+// for the whole normalization.
+struct Budget {
+    steps: AtomicUsize,
+    limit: usize,
+    // Whether there is a pool to hand work to.
+    fork: bool,
+}
+
+impl Budget {
+    fn step(&self) -> Result<(), RewriteError> {
+        if self.steps.fetch_add(1, Ordering::Relaxed) < self.limit {
+            Ok(())
+        } else {
+            Err(RewriteError::StepLimit(self.limit))
+        }
+    }
+}
+
+// Whether `term` has at least `n` nodes, looking at no more than that many.
+fn at_least(term: &LambdaTerm, n: usize) -> bool {
+    let mut count = 0;
+    let mut stack = vec![term];
+    while let Some(term) = stack.pop() {
+        count += 1;
+        if count >= n {
+            return true;
+        }
+        match term {
+            LambdaTerm::Variable(_) => (),
+            LambdaTerm::Abstraction { return_term, .. } => stack.push(return_term),
+            LambdaTerm::Application { function, argument } => {
+                stack.extend([&**argument, &**function])
+            }
+        }
+    }
+    false
+}
+
+fn normalize_mut(term: &LambdaTerm, budget: &Budget) -> Result<LambdaTerm, RewriteError> {
+    match term {
+        LambdaTerm::Variable(_) => Ok(term.clone()),
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => Ok(LambdaTerm::Abstraction {
+            bound_variable: bound_variable.clone(),
+            return_term: Box::new(normalize_mut(return_term, budget)?),
+        }),
+        LambdaTerm::Application { function, argument } => {
+            let (function, argument) = normalize_both(function, argument, budget)?;
+            match function {
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    budget.step()?;
+                    normalize_mut(&return_term.substitute(&bound_variable, &argument), budget)
+                }
+                function => Ok(LambdaTerm::Application {
+                    function: Box::new(function),
+                    argument: Box::new(argument),
+                }),
+            }
+        }
+    }
+}
+
+// The function and argument of an application don't share anything, so
+// when both are big they are offered to rayon to normalize side by side.
+fn normalize_both(
+    function: &LambdaTerm,
+    argument: &LambdaTerm,
+    budget: &Budget,
+) -> Result<(LambdaTerm, LambdaTerm), RewriteError> {
+    if budget.fork && at_least(function, FORK_THRESHOLD) && at_least(argument, FORK_THRESHOLD) {
+        let (function, argument) = rayon::join(
+            || normalize_mut(function, budget),
+            || normalize_mut(argument, budget),
+        );
+        return Ok((function?, argument?));
+    }
+    Ok((
+        normalize_mut(function, budget)?,
+        normalize_mut(argument, budget)?,
+    ))
+}
+
+// Normalizes by applicative order, as a Rewriter with Beta and
+// Strategy::BottomUp does, but normalizing the function and argument of big
+// applications in parallel on a rayon pool of `threads` threads. `limit`
+// caps the β-contractions made across all threads.
+pub fn normalize_with_threads(
+    term: &LambdaTerm,
+    limit: usize,
+    threads: usize,
+) -> Result<LambdaTerm, RewriteError> {
+    let mut budget = Budget {
+        steps: AtomicUsize::new(0),
+        limit,
+        fork: threads > 1,
+    };
+    if !budget.fork {
+        return normalize_mut(term, &budget);
+    }
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .stack_size(STACK_SIZE)
+        .build();
+    match pool {
+        Ok(pool) => pool.install(|| normalize_mut(term, &budget)),
+        // No threads to be had, so carry on here.
+        Err(_) => {
+            budget.fork = false;
+            normalize_mut(term, &budget)
+        }
+    }
+}
+
+// normalize_with_threads, with as many threads as the machine can run at
+// once.
+pub fn normalize(term: &LambdaTerm, limit: usize) -> Result<LambdaTerm, RewriteError> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    normalize_with_threads(term, limit, threads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encodings::church_nat;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+    use crate::{app, apps, lam, parse, var};
+
+    #[test]
+    fn agrees_with_bottom_up_rewriting() {
+        let rewriter = Rewriter::new(Strategy::BottomUp, 200).rule(Beta);
+        let mut rng = SplitMix64(371);
+        let mut compared = 0;
+        for i in 0..2000 {
+            let term = gen_closed_term(1 + i % 25, &mut rng);
+            let term = app(app(term, var("p")), lam("q", var("q")));
+            if let Ok(expected) = rewriter.normalize(&term) {
+                let normal = normalize_with_threads(&term, 200, 4).unwrap();
+                assert!(normal.alpha_equivalent(&expected), "{}", term);
+                compared += 1;
+            }
+        }
+        assert!(compared > 1000);
+    }
+
+    #[test]
+    fn forks_on_big_applications() {
+        let succ = parse("λn. λf. λx. f (n f x)").unwrap();
+        let big = |n| app(succ.clone(), church_nat(n));
+        let term = apps(var("pair"), [big(150), big(200)]);
+        let normal = apps(var("pair"), [church_nat(151), church_nat(201)]);
+        for threads in [1, 2, 4] {
+            let result = normalize_with_threads(&term, 100, threads).unwrap();
+            assert!(result.alpha_equivalent(&normal));
+        }
+        assert!(matches!(
+            normalize_with_threads(&term, 5, 4),
+            Err(RewriteError::StepLimit(5))
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::visit::Order;
+use crate::LambdaTerm;
+use std::fmt;
+
+// One step down from a node to one of its immediate subterms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    Body,
+    Function,
+    Argument,
+}
+
+// The steps from the root of a term down to one of its subterms. The empty
+// path addresses the root itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path(pub Vec<Step>);
+
+impl Path {
+    pub fn new() -> Path {
+        Path::default()
+    }
+
+    pub fn child(&self, step: Step) -> Path {
+        let mut steps = self.0.clone();
+        steps.push(step);
+        Path(steps)
+    }
+
+    pub fn parent(&self) -> Option<Path> {
+        let (_, steps) = self.0.split_last()?;
+        Some(Path(steps.to_vec()))
+    }
+}
+
+// Written as the steps separated by `/`, such as `body/function`, or `.` for
+// the root.
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str(".");
+        }
+        for (i, step) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            f.write_str(match step {
+                Step::Body => "body",
+                Step::Function => "function",
+                Step::Argument => "argument",
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<Step>> for Path {
+    fn from(steps: Vec<Step>) -> Path {
+        Path(steps)
+    }
+}
+
+impl LambdaTerm {
+    pub fn get(&self, path: &Path) -> Option<&LambdaTerm> {
+        path.0
+            .iter()
+            .try_fold(self, |term, step| match (term, step) {
+                (LambdaTerm::Abstraction { return_term, .. }, Step::Body) => Some(&**return_term),
+                (LambdaTerm::Application { function, .. }, Step::Function) => Some(&**function),
+                (LambdaTerm::Application { argument, .. }, Step::Argument) => Some(&**argument),
+                _ => None,
+            })
+    }
+
+    pub fn get_mut(&mut self, path: &Path) -> Option<&mut LambdaTerm> {
+        path.0
+            .iter()
+            .try_fold(self, |term, step| match (term, step) {
+                (LambdaTerm::Abstraction { return_term, .. }, Step::Body) => {
+                    Some(&mut **return_term)
+                }
+                (LambdaTerm::Application { function, .. }, Step::Function) => Some(&mut **function),
+                (LambdaTerm::Application { argument, .. }, Step::Argument) => Some(&mut **argument),
+                _ => None,
+            })
+    }
+
+    // Puts `term` at `path`, handing back the subterm it displaces, or gives
+    // `term` back untouched if the path leads nowhere.
+    pub fn replace(&mut self, path: &Path, term: LambdaTerm) -> Result<LambdaTerm, LambdaTerm> {
+        match self.get_mut(path) {
+            Some(subterm) => Ok(std::mem::replace(subterm, term)),
+            None => Err(term),
+        }
+    }
+
+    // The paths to every beta redex, outermost first and then left to right,
+    // so the first is the one normal order would contract.
+    pub fn redexes(&self) -> Vec<Path> {
+        self.iter_subterms(Order::Pre)
+            .filter(|(_, subterm)| {
+                matches!(subterm, LambdaTerm::Application { function, .. }
+                    if matches!(**function, LambdaTerm::Abstraction { .. }))
+            })
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    // Contracts the beta redex at `path`, or gives None if there isn't one
+    // there.
+    pub fn contract(&self, path: &Path) -> Option<LambdaTerm> {
+        let reduced = match self.get(path)? {
+            LambdaTerm::Application { function, argument } => match &**function {
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => return_term.substitute(bound_variable, argument),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let mut term = self.clone();
+        term.replace(path, reduced).ok()?;
+        Some(term)
+    }
+}
@@ -0,0 +1,483 @@
+use crate::names::NameSupply;
+use crate::path::Path;
+use crate::small::SmallName;
+use crate::visit::Order;
+use crate::{app, apps, lam, parse, LambdaTerm, ParserError};
+use std::collections::{BTreeMap, HashSet};
+
+// A term in which some free variables are metavariables, standing for any
+// subterm. Everything else has to match up to α-equivalence, and a
+// metavariable used twice has to match α-equivalent subterms both times.
+// Under a binder a metavariable may use the bound variable, and its binding
+// is written with the pattern's name for it, so matching `(λx. M) N`
+// against `(λy. y y) z` binds M to `x x`.
+//
+// A metavariable applied to distinct bound variables, as in `λx. F x`, is a
+// higher-order pattern in Miller's sense: F matches whatever the body is,
+// abstracted over those variables, and may use no other bound ones. So
+// `λx. F x` against `λy. g y y` binds F to `λy. g y y`, and against
+// `λy. g y` binds F to `g`, since bindings are η-reduced where possible.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    term: LambdaTerm,
+    metavariables: HashSet<SmallName>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bindings(BTreeMap<SmallName, LambdaTerm>);
+
+impl Bindings {
+    pub fn get(&self, metavariable: &str) -> Option<&LambdaTerm> {
+        self.0.get(metavariable)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LambdaTerm)> {
+        self.0.iter().map(|(name, term)| (name.as_str(), term))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Puts each bound subterm in place of its metavariable's free
+    // occurrences in `template`, all at once, avoiding capture. Where the
+    // template applies a metavariable bound to an abstraction, the
+    // application is β-reduced, so `F a` with F bound to `λx. g x x` gives
+    // `g a a` rather than a redex.
+    pub fn instantiate(&self, template: &LambdaTerm) -> LambdaTerm {
+        let mut inserted = HashSet::new();
+        for term in self.0.values() {
+            inserted.extend(term.free_variables());
+        }
+        self.fill(template, &inserted, &mut Vec::new())
+    }
+
+    fn fill(
+        &self,
+        template: &LambdaTerm,
+        inserted: &HashSet<SmallName>,
+        binders: &mut Vec<SmallName>,
+    ) -> LambdaTerm {
+        let (head, arguments) = spine(template);
+        if let LambdaTerm::Variable(id) = head {
+            if let Some(term) = self.0.get(id).filter(|_| !binders.contains(id)) {
+                return arguments
+                    .into_iter()
+                    .fold(term.clone(), |function, argument| {
+                        let argument = self.fill(argument, inserted, binders);
+                        match function {
+                            LambdaTerm::Abstraction {
+                                bound_variable,
+                                return_term,
+                            } => return_term.substitute(&bound_variable, &argument),
+                            function => app(function, argument),
+                        }
+                    });
+            }
+        }
+        match template {
+            LambdaTerm::Variable(_) => template.clone(),
+            LambdaTerm::Application { function, argument } => app(
+                self.fill(function, inserted, binders),
+                self.fill(argument, inserted, binders),
+            ),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => {
+                // Rename binders that would capture a free variable of
+                // something being put inside them.
+                let (bound_variable, return_term) = if inserted.contains(bound_variable) {
+                    let mut names = NameSupply::new(return_term.free_variables());
+                    for id in inserted {
+                        names.avoid(id.clone());
+                    }
+                    let fresh = names.fresh(bound_variable);
+                    let renamed = return_term
+                        .substitute(bound_variable, &LambdaTerm::Variable(fresh.clone()));
+                    (fresh, renamed)
+                } else {
+                    (bound_variable.clone(), (**return_term).clone())
+                };
+                binders.push(bound_variable.clone());
+                let return_term = self.fill(&return_term, inserted, binders);
+                binders.pop();
+                lam(bound_variable, return_term)
+            }
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new<S: Into<SmallName>>(
+        term: LambdaTerm,
+        metavariables: impl IntoIterator<Item = S>,
+    ) -> Pattern {
+        Pattern {
+            term,
+            metavariables: metavariables.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn parse<S: Into<SmallName>>(
+        code: &str,
+        metavariables: impl IntoIterator<Item = S>,
+    ) -> Result<Pattern, ParserError> {
+        Ok(Pattern::new(parse(code)?, metavariables))
+    }
+
+    pub fn term(&self) -> &LambdaTerm {
+        &self.term
+    }
+
+    pub fn metavariables(&self) -> &HashSet<SmallName> {
+        &self.metavariables
+    }
+
+    pub fn match_against(&self, term: &LambdaTerm) -> Option<Bindings> {
+        let mut bindings = Bindings::default();
+        if self.match_mut(&self.term, term, &mut Vec::new(), &mut bindings) {
+            Some(bindings)
+        } else {
+            None
+        }
+    }
+
+    // Recognises `F x₁ … xₙ` for a metavariable F and distinct variables
+    // bound in the pattern, giving F and the binder positions of the xᵢ.
+    fn miller_pattern<'a>(
+        &self,
+        pattern: &'a LambdaTerm,
+        binders: &[(&str, &str)],
+    ) -> Option<(&'a str, Vec<usize>)> {
+        let (head, arguments) = spine(pattern);
+        let metavariable = match head {
+            LambdaTerm::Variable(id)
+                if !arguments.is_empty()
+                    && self.metavariables.contains(id)
+                    && !binders.iter().any(|(binder, _)| binder == id) =>
+            {
+                id
+            }
+            _ => return None,
+        };
+        let mut positions = Vec::new();
+        for argument in arguments {
+            let position = match argument {
+                LambdaTerm::Variable(id) => binders.iter().rposition(|(binder, _)| binder == id)?,
+                _ => return None,
+            };
+            if positions.contains(&position) {
+                return None;
+            }
+            positions.push(position);
+        }
+        Some((metavariable, positions))
+    }
+
+    // Every subterm the pattern matches, in pre-order.
+    pub fn find_all(&self, term: &LambdaTerm) -> Vec<(Path, Bindings)> {
+        term.iter_subterms(Order::Pre)
+            .filter_map(|(path, subterm)| Some((path, self.match_against(subterm)?)))
+            .collect()
+    }
+
+    fn match_mut<'a>(
+        &self,
+        pattern: &'a LambdaTerm,
+        term: &'a LambdaTerm,
+        binders: &mut Vec<(&'a str, &'a str)>,
+        bindings: &mut Bindings,
+    ) -> bool {
+        if let Some((metavariable, arguments)) = self.miller_pattern(pattern, binders) {
+            let term = match abstract_over(term, &arguments, binders) {
+                Some(term) => term,
+                None => return false,
+            };
+            return match bindings.0.get(metavariable) {
+                Some(bound) => bound.alpha_equivalent(&term),
+                None => {
+                    bindings.0.insert(SmallName::from(metavariable), term);
+                    true
+                }
+            };
+        }
+        match (pattern, term) {
+            (LambdaTerm::Variable(id), _) => {
+                let pattern_binder = binders.iter().rposition(|(binder, _)| binder == id);
+                if pattern_binder.is_none() && self.metavariables.contains(id) {
+                    let term = match in_pattern_scope(term, binders) {
+                        Some(term) => term,
+                        None => return false,
+                    };
+                    return match bindings.0.get(id) {
+                        Some(bound) => bound.alpha_equivalent(&term),
+                        None => {
+                            bindings.0.insert(id.clone(), term);
+                            true
+                        }
+                    };
+                }
+                match term {
+                    LambdaTerm::Variable(term_id) => {
+                        let term_binder = binders.iter().rposition(|(_, binder)| binder == term_id);
+                        match (pattern_binder, term_binder) {
+                            (None, None) => id == term_id,
+                            (pattern_binder, term_binder) => pattern_binder == term_binder,
+                        }
+                    }
+                    _ => false,
+                }
+            }
+            (
+                LambdaTerm::Abstraction {
+                    bound_variable: pattern_variable,
+                    return_term: pattern_body,
+                },
+                LambdaTerm::Abstraction {
+                    bound_variable: term_variable,
+                    return_term: term_body,
+                },
+            ) => {
+                binders.push((pattern_variable, term_variable));
+                let matched = self.match_mut(pattern_body, term_body, binders, bindings);
+                binders.pop();
+                matched
+            }
+            (
+                LambdaTerm::Application {
+                    function: pattern_function,
+                    argument: pattern_argument,
+                },
+                LambdaTerm::Application { function, argument },
+            ) => {
+                self.match_mut(pattern_function, function, binders, bindings)
+                    && self.match_mut(pattern_argument, argument, binders, bindings)
+            }
+            _ => false,
+        }
+    }
+}
+
+// Rewrites a matched subterm so the variables bound by the term's binders
+// go by the names the pattern's binders give them. That fails when one of
+// the subterm's own free variables would be captured by a pattern binder, or
+// a binder it needs is shadowed on the pattern side.
+fn in_pattern_scope(term: &LambdaTerm, binders: &[(&str, &str)]) -> Option<LambdaTerm> {
+    let free = term.free_variables();
+    let mut renames = Vec::new();
+    for id in &free {
+        match binders.iter().rposition(|(_, binder)| binder == id) {
+            Some(position) => {
+                let pattern_name = binders[position].0;
+                if binders[position + 1..]
+                    .iter()
+                    .any(|(binder, _)| *binder == pattern_name)
+                {
+                    return None;
+                }
+                if pattern_name != id {
+                    renames.push((id.as_str(), pattern_name));
+                }
+            }
+            None if binders.iter().any(|(binder, _)| binder == id) => return None,
+            None => (),
+        }
+    }
+    // Renaming goes through fresh names first so that swapped binders,
+    // where x matched y and y matched x, don't run into each other.
+    let mut names = NameSupply::new(free.iter().cloned().chain(term.bound_variables()));
+    for (binder, _) in binders {
+        names.avoid(*binder);
+    }
+    let mut term = term.clone();
+    let mut staged = Vec::new();
+    for (from, to) in renames {
+        let fresh = names.fresh(from);
+        term = term.substitute(from, &LambdaTerm::Variable(fresh.clone()));
+        staged.push((fresh, to));
+    }
+    for (fresh, to) in staged {
+        term = term.substitute(&fresh, &LambdaTerm::Variable(SmallName::from(to)));
+    }
+    Some(term)
+}
+
+// Splits `f a b c` into `f` and `[a, b, c]`.
+fn spine(term: &LambdaTerm) -> (&LambdaTerm, Vec<&LambdaTerm>) {
+    let mut arguments = Vec::new();
+    let mut head = term;
+    while let LambdaTerm::Application { function, argument } = head {
+        arguments.push(&**argument);
+        head = function;
+    }
+    arguments.reverse();
+    (head, arguments)
+}
+
+// Turns the subterm matched by `F x₁ … xₙ` into F's binding `λx₁. … λxₙ. t`,
+// named as in the term, failing if `t` uses a bound variable that isn't
+// one of the xᵢ.
+fn abstract_over(
+    term: &LambdaTerm,
+    positions: &[usize],
+    binders: &[(&str, &str)],
+) -> Option<LambdaTerm> {
+    for id in term.free_variables() {
+        if let Some(position) = binders.iter().rposition(|(_, binder)| *binder == id) {
+            if !positions.contains(&position) {
+                return None;
+            }
+        }
+    }
+    let names: Vec<&str> = positions
+        .iter()
+        .map(|&position| binders[position].1)
+        .collect();
+    // λx₁. … λxₙ. t xₙ … reduces to t when the xᵢ appear only as those
+    // trailing arguments.
+    let (head, arguments) = spine(term);
+    let keep = names.len().min(arguments.len());
+    let mut reducible = 0;
+    while reducible < keep {
+        let name = names[names.len() - 1 - reducible];
+        match arguments[arguments.len() - 1 - reducible] {
+            LambdaTerm::Variable(id) if id == name => reducible += 1,
+            _ => break,
+        }
+    }
+    let (term, names) = if reducible > 0 {
+        let rest = apps(
+            head.clone(),
+            arguments[..arguments.len() - reducible]
+                .iter()
+                .map(|&argument| argument.clone()),
+        );
+        let dropped = &names[names.len() - reducible..];
+        let kept = &names[..names.len() - reducible];
+        if dropped
+            .iter()
+            .any(|name| rest.free_variables().contains(*name))
+            || has_duplicates(names.as_slice())
+        {
+            (term.clone(), names.as_slice())
+        } else {
+            (rest, kept)
+        }
+    } else {
+        (term.clone(), names.as_slice())
+    };
+    Some(names.iter().rev().fold(term, |body, name| lam(*name, body)))
+}
+
+fn has_duplicates(names: &[&str]) -> bool {
+    names
+        .iter()
+        .enumerate()
+        .any(|(i, name)| names[..i].contains(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::var;
+
+    fn pattern(code: &str, metavariables: &[&str]) -> Pattern {
+        Pattern::parse(code, metavariables.iter().copied()).unwrap()
+    }
+
+    fn term(code: &str) -> LambdaTerm {
+        parse(code).unwrap()
+    }
+
+    #[test]
+    fn repeated_metavariables_must_agree() {
+        let twice = pattern("f M M", &["M"]);
+        let bindings = twice.match_against(&term("f (λx. x) (λy. y)")).unwrap();
+        assert_eq!(bindings.len(), 1);
+        assert!(bindings.get("M").unwrap().alpha_equivalent(&term("λx. x")));
+        assert!(twice
+            .match_against(&term("f (λx. x) (λx. λy. x)"))
+            .is_none());
+        assert!(twice.match_against(&term("f a b")).is_none());
+
+        let redex = pattern("(λx. M) N", &["M", "N"]);
+        let bindings = redex.match_against(&term("(λy. y y) z")).unwrap();
+        assert_eq!(bindings.get("M"), Some(&term("x x")));
+        assert_eq!(bindings.get("N"), Some(&var("z")));
+    }
+
+    #[test]
+    fn fails_on_a_mismatch() {
+        let redex = pattern("(λx. M) N", &["M", "N"]);
+        assert!(redex.match_against(&term("f z")).is_none());
+        assert!(redex.match_against(&term("λx. x")).is_none());
+        // Variables that aren't metavariables have to be the same name.
+        assert!(pattern("f M", &["M"]).match_against(&term("g a")).is_none());
+        // M would need the outer x, which the pattern's inner x shadows.
+        assert!(pattern("λx. λx. M", &["M"])
+            .match_against(&term("λx. λy. x"))
+            .is_none());
+    }
+
+    #[test]
+    fn instantiate_avoids_capture() {
+        let bindings = pattern("f M", &["M"]).match_against(&term("f y")).unwrap();
+        let filled = bindings.instantiate(&term("λy. M y"));
+        assert!(filled.alpha_equivalent(&term("λz. y z")), "{}", filled);
+        assert!(filled.free_variables().contains("y"));
+        // Metavariables bound inside the template aren't filled in.
+        assert_eq!(bindings.instantiate(&term("λM. M")), term("λM. M"));
+    }
+
+    #[test]
+    fn higher_order_patterns_abstract_over_their_arguments() {
+        let eta = pattern("λx. F x", &["F"]);
+        let bindings = eta.match_against(&term("λy. g y")).unwrap();
+        assert_eq!(bindings.get("F"), Some(&var("g")));
+        let bindings = eta.match_against(&term("λy. g y y")).unwrap();
+        assert!(bindings
+            .get("F")
+            .unwrap()
+            .alpha_equivalent(&term("λy. g y y")));
+
+        // F x y may use both binders, in either order.
+        let two = pattern("λx. λy. F y x", &["F"]);
+        let bindings = two.match_against(&term("λa. λb. h a b")).unwrap();
+        let filled = bindings.instantiate(&term("F p q"));
+        assert_eq!(filled, term("h q p"));
+    }
+
+    #[test]
+    fn higher_order_patterns_keep_bound_variables_in_their_arguments() {
+        // F only receives y, so it can't be handed a body that uses x.
+        let only_y = pattern("λx. λy. F y", &["F"]);
+        assert!(only_y.match_against(&term("λa. λb. g a b")).is_none());
+        assert!(only_y.match_against(&term("λa. λb. g b")).is_some());
+        // `F x x` repeats x, so it isn't a Miller pattern itself, but its
+        // function part `F x` is, and the last x matches on its own.
+        let repeated = pattern("λx. F x x", &["F"]);
+        let bindings = repeated.match_against(&term("λy. g y y")).unwrap();
+        assert_eq!(bindings.get("F"), Some(&var("g")));
+        assert!(repeated.match_against(&term("λy. g y z")).is_none());
+    }
+
+    #[test]
+    fn eta_can_be_written_as_a_rule() {
+        use crate::rewrite::{Rewriter, Rule, Strategy};
+        let eta = Rule::parse("eta", "λx. F x", "F", ["F"]).unwrap();
+        let rewriter = Rewriter::new(Strategy::TopDown, 100).rule(eta);
+        assert_eq!(rewriter.normalize(&term("λx. f x")).unwrap(), var("f"));
+        assert_eq!(
+            rewriter.normalize(&term("λy. λx. f y x")).unwrap(),
+            var("f")
+        );
+        // λx. f x x isn't an η-redex: F would have to use x.
+        let not_a_redex = term("λx. f x x");
+        assert_eq!(rewriter.normalize(&not_a_redex).unwrap(), not_a_redex);
+    }
+}
@@ -0,0 +1,331 @@
+use crate::environment::{Environment, Expansion};
+use crate::krivine::Machine;
+use crate::rewrite::{Beta, RewriteError, Rewriter, Strategy};
+use crate::small::SmallName;
+use crate::store::{StoreError, TermStore};
+use crate::{parse, DBIndices, DBTerm, LambdaTerm, ParserError};
+use std::fmt;
+
+pub enum Stage<'e> {
+    Parse,
+    // Expands free variables that name a definition, including ones that
+    // appear inside other definitions.
+    Resolve(&'e Environment),
+    Pass {
+        name: &'static str,
+        run: fn(&LambdaTerm) -> LambdaTerm,
+    },
+    // Reduces in normal order to β-normal form, δ-expanding definitions as
+    // `expansion` says.
+    Normalize {
+        environment: &'e Environment,
+        expansion: Expansion,
+        limit: usize,
+    },
+    Convert,
+    // Runs the Krivine machine to weak head normal form, then reduces what's
+    // left under binders and in arguments to normal form. Steps of both count
+    // towards the limit.
+    Evaluate {
+        limit: usize,
+    },
+    DecodeNumeral,
+}
+
+impl<'e> Stage<'e> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Parse => "parse",
+            Stage::Resolve(_) => "resolve",
+            Stage::Pass { name, .. } => name,
+            Stage::Normalize { .. } => "normalize",
+            Stage::Convert => "convert",
+            Stage::Evaluate { .. } => "evaluate",
+            Stage::DecodeNumeral => "decode",
+        }
+    }
+
+    fn run(&self, input: &Artifact) -> Result<Artifact, PipelineError> {
+        match (self, input) {
+            (Stage::Parse, Artifact::Source(code)) => Ok(Artifact::Term(parse(code)?)),
+            (Stage::Resolve(environment), Artifact::Term(term)) => {
+                resolve(term, environment).map(Artifact::Term)
+            }
+            (Stage::Pass { run, .. }, Artifact::Term(term)) => Ok(Artifact::Term(run(term))),
+            (
+                Stage::Normalize {
+                    environment,
+                    expansion,
+                    limit,
+                },
+                Artifact::Term(term),
+            ) => {
+                let rewriter = Rewriter::new(Strategy::TopDown, *limit).rule(Beta);
+                match environment.normalize(&rewriter, term, *expansion) {
+                    Ok(term) => Ok(Artifact::Term(term)),
+                    Err(RewriteError::StepLimit(limit)) => Err(PipelineError::StepLimit(limit)),
+                }
+            }
+            (Stage::Convert, Artifact::Term(term)) => Ok(Artifact::Indices(term.clone().into())),
+            (Stage::Evaluate { limit }, Artifact::Indices(program)) => {
+                let mut machine = Machine::new(program);
+                for taken in 0..*limit {
+                    if machine.step().is_none() {
+                        // The machine stops at weak head normal form, with
+                        // redexes left under binders and in arguments.
+                        let mut store = TermStore::new();
+                        let head = store.insert_indices(&machine.readback());
+                        return match store.normalize(head, limit - taken) {
+                            Ok(normal) => Ok(Artifact::Indices(store.to_indices(normal))),
+                            Err(StoreError::StepLimit(_)) => Err(PipelineError::StepLimit(*limit)),
+                        };
+                    }
+                }
+                Err(PipelineError::StepLimit(*limit))
+            }
+            (Stage::DecodeNumeral, Artifact::Indices(DBIndices(term))) => {
+                decode_numeral(term).map(Artifact::Numeral)
+            }
+            (stage, _) => Err(PipelineError::Mismatch(stage.name())),
+        }
+    }
+}
+
+fn resolve(term: &LambdaTerm, environment: &Environment) -> Result<LambdaTerm, PipelineError> {
+    let mut term = term.clone();
+    // Each round expands one more level of definitions in terms of others.
+    for _ in 0..=environment.len() {
+        let defined: Vec<SmallName> = term
+            .free_variables()
+            .into_iter()
+            .filter(|name| environment.get(name).is_some())
+            .collect();
+        if defined.is_empty() {
+            return Ok(term);
+        }
+        for name in defined {
+            if let Some(definition) = environment.get(&name) {
+                term = term.substitute(&name, definition);
+            }
+        }
+    }
+    // Anything left after as many rounds as there are definitions is
+    // defined in terms of itself.
+    match term
+        .free_variables()
+        .into_iter()
+        .filter(|name| environment.get(name).is_some())
+        .min()
+    {
+        Some(name) => Err(PipelineError::Unresolved(name.into())),
+        None => Ok(term),
+    }
+}
+
+fn decode_numeral(term: &DBTerm) -> Result<u64, PipelineError> {
+    let mut body = match term {
+        DBTerm::Abstraction(inner) => match &**inner {
+            DBTerm::Abstraction(body) => &**body,
+            _ => return Err(PipelineError::NotANumeral),
+        },
+        _ => return Err(PipelineError::NotANumeral),
+    };
+    let mut n = 0;
+    loop {
+        match body {
+            DBTerm::Variable(1) => break Ok(n),
+            DBTerm::Application { function, argument }
+                if matches!(**function, DBTerm::Variable(2)) =>
+            {
+                n += 1;
+                body = argument;
+            }
+            _ => break Err(PipelineError::NotANumeral),
+        }
+    }
+}
+
+pub enum Artifact {
+    Source(String),
+    Term(LambdaTerm),
+    Indices(DBIndices),
+    Numeral(u64),
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Artifact::Source(code) => write!(f, "{}", code),
+            Artifact::Term(term) => write!(f, "{}", term),
+            Artifact::Indices(term) => write!(f, "{}", term),
+            Artifact::Numeral(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PipelineError {
+    Parser(ParserError),
+    // A stage was handed an artifact it doesn't accept, such as evaluating
+    // before converting.
+    Mismatch(&'static str),
+    Unresolved(String),
+    StepLimit(usize),
+    NotANumeral,
+}
+
+impl From<ParserError> for PipelineError {
+    fn from(err: ParserError) -> PipelineError {
+        PipelineError::Parser(err)
+    }
+}
+
+// The artifact produced by each stage that ran, named by stage, and the
+// error that stopped the run early if there was one.
+pub struct Run {
+    pub artifacts: Vec<(&'static str, Artifact)>,
+    pub error: Option<PipelineError>,
+}
+
+impl Run {
+    pub fn result(&self) -> Result<&Artifact, &PipelineError> {
+        match &self.error {
+            Some(err) => Err(err),
+            None => Ok(&self.artifacts.last().expect("a run has its source").1),
+        }
+    }
+}
+
+// Stages run in order, each on the artifact left by the one before. They are
+// public so that callers can toggle or reorder them after building.
+#[derive(Default)]
+pub struct Pipeline<'e> {
+    pub stages: Vec<Stage<'e>>,
+}
+
+impl<'e> Pipeline<'e> {
+    pub fn new() -> Pipeline<'e> {
+        Pipeline::default()
+    }
+
+    pub fn parse(mut self) -> Pipeline<'e> {
+        self.stages.push(Stage::Parse);
+        self
+    }
+
+    pub fn resolve(mut self, environment: &'e Environment) -> Pipeline<'e> {
+        self.stages.push(Stage::Resolve(environment));
+        self
+    }
+
+    pub fn pass(mut self, name: &'static str, run: fn(&LambdaTerm) -> LambdaTerm) -> Pipeline<'e> {
+        self.stages.push(Stage::Pass { name, run });
+        self
+    }
+
+    pub fn normalize(
+        mut self,
+        environment: &'e Environment,
+        expansion: Expansion,
+        limit: usize,
+    ) -> Pipeline<'e> {
+        self.stages.push(Stage::Normalize {
+            environment,
+            expansion,
+            limit,
+        });
+        self
+    }
+
+    pub fn convert(mut self) -> Pipeline<'e> {
+        self.stages.push(Stage::Convert);
+        self
+    }
+
+    pub fn evaluate(mut self, limit: usize) -> Pipeline<'e> {
+        self.stages.push(Stage::Evaluate { limit });
+        self
+    }
+
+    pub fn decode_numeral(mut self) -> Pipeline<'e> {
+        self.stages.push(Stage::DecodeNumeral);
+        self
+    }
+
+    pub fn run(&self, source: &str) -> Run {
+        let mut run = Run {
+            artifacts: vec![("source", Artifact::Source(String::from(source)))],
+            error: None,
+        };
+        for stage in &self.stages {
+            let input = &run.artifacts.last().expect("a run has its source").1;
+            match stage.run(input) {
+                Ok(artifact) => run.artifacts.push((stage.name(), artifact)),
+                Err(err) => {
+                    run.error = Some(err);
+                    break;
+                }
+            }
+        }
+        run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn numeral(source: &str) -> Result<u64, PipelineError> {
+        let run = Pipeline::new()
+            .parse()
+            .convert()
+            .evaluate(1000)
+            .decode_numeral()
+            .run(source);
+        match run.result() {
+            Ok(Artifact::Numeral(n)) => Ok(*n),
+            Ok(artifact) => panic!("ended with {}", artifact),
+            Err(_) => Err(run.error.unwrap()),
+        }
+    }
+
+    #[test]
+    fn evaluates_numerals_to_normal_form() {
+        assert_eq!(numeral("λf. λx. x").unwrap(), 0);
+        assert_eq!(numeral("(λn. λf. λx. f (n f x)) (λf. λx. x)").unwrap(), 1);
+        assert_eq!(numeral("λf. λx. f ((λy. y) x)").unwrap(), 1);
+        assert_eq!(
+            numeral("(λm. λn. λf. m (n f)) (λf. λx. f (f x)) (λf. λx. f (f (f x)))").unwrap(),
+            6
+        );
+        assert!(matches!(numeral("λx. x"), Err(PipelineError::NotANumeral)));
+        assert!(matches!(
+            numeral("(λx. x x) (λx. x x)"),
+            Err(PipelineError::StepLimit(1000))
+        ));
+    }
+
+    #[test]
+    fn resolves_definitions_in_terms_of_others() {
+        let mut environment = Environment::new();
+        environment.define("zero", parse("λf. λx. x").unwrap());
+        environment.define("succ", parse("λn. λf. λx. f (n f x)").unwrap());
+        environment.define("two", parse("succ (succ zero)").unwrap());
+        environment.define("loop", parse("succ loop").unwrap());
+        let pipeline = Pipeline::new()
+            .parse()
+            .resolve(&environment)
+            .convert()
+            .evaluate(1000)
+            .decode_numeral();
+        assert!(matches!(
+            pipeline.run("succ two").result(),
+            Ok(Artifact::Numeral(3))
+        ));
+        assert!(matches!(
+            pipeline.run("loop").result(),
+            Err(PipelineError::Unresolved(name)) if name == "loop"
+        ));
+    }
+}
@@ -0,0 +1,703 @@
+use crate::encodings::decode_church_nat;
+use crate::{Backticks, DBIndices, DBLevels, DBTerm, LambdaTerm, Quoting};
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parens {
+    Minimal,
+    Explicit,
+    // Minimal, plus parentheses around abstraction bodies that are
+    // applications and around the function in nested applications, so that
+    // `λx. x y z` prints as `λx. ((x y) z)`.
+    Clarifying,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numerals {
+    Plain,
+    Sugar,
+}
+
+// How lines are broken once a width is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    // Fill each line with as many words as fit, indenting the rest by two.
+    Fill,
+    // Break applications and abstraction bodies as whole units, indenting
+    // each by the structure it sits in, so the shape of the term shows.
+    Nested,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    pub width: Option<usize>,
+    pub wrap: Wrap,
+    pub ascii: bool,
+    pub parens: Parens,
+    pub numerals: Numerals,
+    // `λx. M` rather than `λx.M`.
+    pub space_after_dot: bool,
+    // `λx y. M` rather than `λx. λy. M`.
+    pub collapse_binders: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> PrintOptions {
+        PrintOptions {
+            width: None,
+            wrap: Wrap::Fill,
+            ascii: false,
+            parens: Parens::Minimal,
+            numerals: Numerals::Plain,
+            space_after_dot: true,
+            collapse_binders: false,
+        }
+    }
+}
+
+// Lays text out into lines no wider than the configured width. Only the
+// current word is buffered, so output streams through in constant memory.
+struct Layout<'w> {
+    out: &'w mut dyn fmt::Write,
+    width: Option<usize>,
+    word: String,
+    line_length: usize,
+    started: bool,
+}
+
+impl<'w> Layout<'w> {
+    fn new(out: &'w mut dyn fmt::Write, width: Option<usize>) -> Layout<'w> {
+        Layout {
+            out,
+            width,
+            word: String::new(),
+            line_length: 0,
+            started: false,
+        }
+    }
+
+    fn space(&mut self) -> fmt::Result {
+        match self.width {
+            None => self.out.write_char(' '),
+            Some(width) => {
+                let word_length = self.word.chars().count();
+                if !self.started {
+                    self.started = true;
+                    self.line_length = word_length;
+                } else if self.line_length + 1 + word_length > width {
+                    self.out.write_str("\n  ")?;
+                    self.line_length = 2 + word_length;
+                } else {
+                    self.out.write_char(' ')?;
+                    self.line_length += 1 + word_length;
+                }
+                self.out.write_str(&self.word)?;
+                self.word.clear();
+                Ok(())
+            }
+        }
+    }
+
+    // The separator before the first word isn't written, so flushing the last
+    // word goes through the same path as every other one.
+    fn finish(&mut self) -> fmt::Result {
+        match self.width {
+            None => Ok(()),
+            Some(_) => self.space(),
+        }
+    }
+}
+
+impl<'w> fmt::Write for Layout<'w> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        match self.width {
+            None => self.out.write_str(text),
+            Some(_) => {
+                self.word.push_str(text);
+                Ok(())
+            }
+        }
+    }
+}
+
+// The binders printed together at the head of an abstraction, and the body
+// after them. Without collapse_binders there is always one.
+fn binders<'a>(term: &'a LambdaTerm, options: &PrintOptions) -> (Vec<&'a str>, &'a LambdaTerm) {
+    let mut binders = Vec::new();
+    let mut body = term;
+    while let LambdaTerm::Abstraction {
+        bound_variable,
+        return_term,
+    } = body
+    {
+        binders.push(bound_variable.as_str());
+        body = return_term;
+        let sugared = options.numerals == Numerals::Sugar && decode_church_nat(body).is_some();
+        if !options.collapse_binders || options.parens == Parens::Explicit || sugared {
+            break;
+        }
+    }
+    (binders, body)
+}
+
+enum Work<'a> {
+    Term(&'a LambdaTerm),
+    Identifier(&'a str),
+    Text(&'static str),
+    Space,
+}
+
+fn render(term: &LambdaTerm, options: &PrintOptions, out: &mut dyn fmt::Write) -> fmt::Result {
+    use fmt::Write;
+
+    let numeral = |term: &LambdaTerm| match options.numerals {
+        Numerals::Plain => None,
+        Numerals::Sugar => decode_church_nat(term),
+    };
+    if let (Some(width), Wrap::Nested) = (options.width, options.wrap) {
+        return pretty(&doc(term, options), width, out);
+    }
+    let explicit = options.parens == Parens::Explicit;
+    let clarifying = options.parens == Parens::Clarifying;
+    let lambda = if options.ascii { "\\" } else { "λ" };
+    let mut layout = Layout::new(out, options.width);
+    let mut stack = vec![Work::Term(term)];
+    // Work is pushed in reverse, so that it pops off the stack in print order.
+    while let Some(work) = stack.pop() {
+        let term = match work {
+            Work::Term(term) => term,
+            Work::Identifier(id) => {
+                Backticks.write_identifier(&mut layout, id)?;
+                continue;
+            }
+            Work::Text(text) => {
+                layout.write_str(text)?;
+                continue;
+            }
+            Work::Space => {
+                layout.space()?;
+                continue;
+            }
+        };
+        if let Some(n) = numeral(term) {
+            write!(layout, "{}", n)?;
+            continue;
+        }
+        match term {
+            LambdaTerm::Variable(id) => Backticks.write_identifier(&mut layout, id)?,
+            LambdaTerm::Application { function, argument } if explicit => stack.extend([
+                Work::Text(")"),
+                Work::Term(argument),
+                Work::Space,
+                Work::Term(function),
+                Work::Text("("),
+            ]),
+            LambdaTerm::Application { function, argument } => {
+                let atomic_argument =
+                    matches!(**argument, LambdaTerm::Variable(_)) || numeral(argument).is_some();
+                if atomic_argument {
+                    stack.push(Work::Term(argument));
+                } else {
+                    stack.extend([Work::Text(")"), Work::Term(argument), Work::Text("(")]);
+                }
+                stack.push(Work::Space);
+                let wrapped_function = match **function {
+                    LambdaTerm::Abstraction { .. } => numeral(function).is_none(),
+                    LambdaTerm::Application { .. } => clarifying,
+                    LambdaTerm::Variable(_) => false,
+                };
+                if wrapped_function {
+                    stack.extend([Work::Text(")"), Work::Term(function), Work::Text("(")]);
+                } else {
+                    stack.push(Work::Term(function));
+                }
+            }
+            LambdaTerm::Abstraction { .. } => {
+                let (binders, body) = binders(term, options);
+                let wrapped_body = clarifying && matches!(body, LambdaTerm::Application { .. });
+                if explicit {
+                    stack.push(Work::Text(")"));
+                }
+                if wrapped_body {
+                    stack.push(Work::Text(")"));
+                }
+                stack.push(Work::Term(body));
+                if wrapped_body {
+                    stack.push(Work::Text("("));
+                }
+                if options.space_after_dot {
+                    stack.push(Work::Space);
+                }
+                stack.push(Work::Text("."));
+                for (i, bound_variable) in binders.iter().enumerate().rev() {
+                    stack.push(Work::Identifier(bound_variable));
+                    if i > 0 {
+                        stack.push(Work::Text(" "));
+                    }
+                }
+                stack.push(Work::Text(lambda));
+                if explicit {
+                    stack.push(Work::Text("("));
+                }
+            }
+        }
+    }
+    layout.finish()
+}
+
+// A document in the style of Wadler's "A prettier printer". Each group is
+// printed on one line if the rest of it fits, and otherwise has its own
+// lines broken, each followed by the enclosing nesting's indentation.
+enum Doc {
+    Text(String),
+    // A line break, or the given text when its group is printed flat.
+    Line(&'static str),
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+// Documents nest as deep as the term, so dropping one takes them apart
+// with a stack instead of the default recursive drop.
+impl Drop for Doc {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        let take = |doc: &mut Doc, pending: &mut Vec<Doc>| match doc {
+            Doc::Nest(_, inner) | Doc::Group(inner) => {
+                pending.push(std::mem::replace(&mut **inner, Doc::Concat(Vec::new())))
+            }
+            Doc::Concat(docs) => pending.append(docs),
+            Doc::Text(_) | Doc::Line(_) => (),
+        };
+        take(self, &mut pending);
+        while let Some(mut doc) = pending.pop() {
+            take(&mut doc, &mut pending);
+        }
+    }
+}
+
+fn text(text: impl Into<String>) -> Doc {
+    Doc::Text(text.into())
+}
+
+fn parenthesized(doc: Doc) -> Doc {
+    Doc::Concat(vec![text("("), doc, text(")")])
+}
+
+// Builds the document for `term`, with the same parentheses and numerals
+// render would print. Like render it keeps its own stack rather than
+// recursing, building each node's document once those of its subterms are
+// on `docs`.
+fn doc(term: &LambdaTerm, options: &PrintOptions) -> Doc {
+    enum Task<'a> {
+        Visit(&'a LambdaTerm),
+        // f a, fully parenthesized.
+        Explicit,
+        // A head and its arguments, each noting whether it's parenthesized.
+        Spine(bool, Vec<bool>),
+        // The binders up to the dot, and whether the body is parenthesized.
+        Abstraction(String, bool),
+    }
+    let numeral = |term: &LambdaTerm| match options.numerals {
+        Numerals::Plain => None,
+        Numerals::Sugar => decode_church_nat(term),
+    };
+    let identifier = |id: &str| {
+        let mut quoted = String::new();
+        let _ = Backticks.write_identifier(&mut quoted, id);
+        quoted
+    };
+    let wrap = |doc: Doc, wrapped: bool| if wrapped { parenthesized(doc) } else { doc };
+    let explicit = options.parens == Parens::Explicit;
+    let clarifying = options.parens == Parens::Clarifying;
+    let lambda = if options.ascii { "\\" } else { "λ" };
+    let separator = if options.space_after_dot { " " } else { "" };
+    let mut tasks = vec![Task::Visit(term)];
+    let mut docs = Vec::new();
+    while let Some(task) = tasks.pop() {
+        let term = match task {
+            Task::Visit(term) => term,
+            Task::Explicit => {
+                let argument = docs.pop().expect("the argument is built");
+                let function = docs.pop().expect("the function is built");
+                docs.push(parenthesized(Doc::Group(Box::new(Doc::Concat(vec![
+                    function,
+                    Doc::Nest(2, Box::new(Doc::Concat(vec![Doc::Line(" "), argument]))),
+                ])))));
+                continue;
+            }
+            Task::Spine(wrapped_head, wrapped_arguments) => {
+                let built = docs.split_off(docs.len() - 1 - wrapped_arguments.len());
+                let mut built = built.into_iter();
+                let head = wrap(built.next().expect("the head is built"), wrapped_head);
+                let mut rest = Vec::new();
+                for (argument, wrapped) in built.zip(wrapped_arguments) {
+                    rest.push(Doc::Line(" "));
+                    rest.push(wrap(argument, wrapped));
+                }
+                docs.push(Doc::Group(Box::new(Doc::Concat(vec![
+                    head,
+                    Doc::Nest(2, Box::new(Doc::Concat(rest))),
+                ]))));
+                continue;
+            }
+            Task::Abstraction(head, wrapped_body) => {
+                let body = wrap(docs.pop().expect("the body is built"), wrapped_body);
+                let abstraction = Doc::Group(Box::new(Doc::Concat(vec![
+                    text(head),
+                    Doc::Nest(2, Box::new(Doc::Concat(vec![Doc::Line(separator), body]))),
+                ])));
+                docs.push(wrap(abstraction, explicit));
+                continue;
+            }
+        };
+        if let Some(n) = numeral(term) {
+            docs.push(text(n.to_string()));
+            continue;
+        }
+        // Subterms are visited in print order, so their documents end up on
+        // `docs` in that order too.
+        match term {
+            LambdaTerm::Variable(id) => docs.push(text(identifier(id))),
+            LambdaTerm::Application { function, argument } if explicit => {
+                tasks.extend([Task::Explicit, Task::Visit(argument), Task::Visit(function)])
+            }
+            LambdaTerm::Application { .. } => {
+                // Gathers f a b c into one group, so that when it breaks each
+                // argument goes on its own line under f.
+                let mut arguments = Vec::new();
+                let mut head = term;
+                while let LambdaTerm::Application { function, argument } = head {
+                    arguments.push(&**argument);
+                    head = function;
+                    if clarifying {
+                        break;
+                    }
+                }
+                let wrapped_head = match head {
+                    LambdaTerm::Abstraction { .. } => numeral(head).is_none(),
+                    LambdaTerm::Application { .. } => true,
+                    LambdaTerm::Variable(_) => false,
+                };
+                let wrapped_arguments = arguments
+                    .iter()
+                    .rev()
+                    .map(|argument| {
+                        !matches!(argument, LambdaTerm::Variable(_)) && numeral(argument).is_none()
+                    })
+                    .collect();
+                tasks.push(Task::Spine(wrapped_head, wrapped_arguments));
+                tasks.extend(arguments.into_iter().map(Task::Visit));
+                tasks.push(Task::Visit(head));
+            }
+            LambdaTerm::Abstraction { .. } => {
+                // Consecutive binders stay together on the first line,
+                // whether or not they're printed collapsed.
+                let chained = PrintOptions {
+                    collapse_binders: true,
+                    ..options.clone()
+                };
+                let (binders, body) = binders(term, &chained);
+                let mut head = String::new();
+                for (i, bound_variable) in binders.iter().enumerate() {
+                    if i == 0 || !options.collapse_binders {
+                        if i > 0 {
+                            head.push_str(separator);
+                        }
+                        head.push_str(lambda);
+                    } else {
+                        head.push(' ');
+                    }
+                    head.push_str(&identifier(bound_variable));
+                    if !options.collapse_binders {
+                        head.push('.');
+                    }
+                }
+                if options.collapse_binders {
+                    head.push('.');
+                }
+                let wrapped_body = clarifying && matches!(body, LambdaTerm::Application { .. });
+                tasks.push(Task::Abstraction(head, wrapped_body));
+                tasks.push(Task::Visit(body));
+            }
+        }
+    }
+    docs.pop().expect("the term is built")
+}
+
+// Whether everything up to the next line break in broken mode fits into
+// `remaining` columns, starting with the group `doc` laid out flat and
+// continuing with the work still on `stack`.
+fn fits(mut remaining: usize, doc: &Doc, stack: &[(usize, bool, &Doc)]) -> bool {
+    let mut pending = vec![(true, doc)];
+    let mut stack = stack.iter().rev();
+    loop {
+        let (flat, doc) = match pending.pop() {
+            Some(work) => work,
+            None => match stack.next() {
+                Some(&(_, flat, doc)) => (flat, doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => match remaining.checked_sub(text.chars().count()) {
+                Some(left) => remaining = left,
+                None => return false,
+            },
+            Doc::Line(text) if flat => match remaining.checked_sub(text.chars().count()) {
+                Some(left) => remaining = left,
+                None => return false,
+            },
+            Doc::Line(_) => return true,
+            Doc::Nest(_, doc) | Doc::Group(doc) => pending.push((flat, doc)),
+            Doc::Concat(docs) => pending.extend(docs.iter().rev().map(|doc| (flat, doc))),
+        }
+    }
+}
+
+fn pretty(doc: &Doc, width: usize, out: &mut dyn fmt::Write) -> fmt::Result {
+    let mut column = 0;
+    // Each entry is the indentation, whether the enclosing group is flat,
+    // and the document still to print.
+    let mut stack = vec![(0, false, doc)];
+    while let Some((indent, flat, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                out.write_str(text)?;
+                column += text.chars().count();
+            }
+            Doc::Line(text) if flat => {
+                out.write_str(text)?;
+                column += text.chars().count();
+            }
+            Doc::Line(_) => {
+                // Written out rather than padded, as format widths stop at
+                // u16::MAX.
+                out.write_str("\n")?;
+                for _ in 0..indent {
+                    out.write_str(" ")?;
+                }
+                column = indent;
+            }
+            Doc::Nest(extra, doc) => stack.push((indent + extra, flat, doc)),
+            Doc::Group(doc) => {
+                let flat = flat || fits(width.saturating_sub(column), doc, &stack);
+                stack.push((indent, flat, doc));
+            }
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, flat, doc))),
+        }
+    }
+    Ok(())
+}
+
+// Forwards formatted text to an io::Write, holding on to the io::Error that
+// fmt::Error has no room for.
+struct IoAdapter<'w, W: io::Write> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<'w, W: io::Write> fmt::Write for IoAdapter<'w, W> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.inner.write_all(text.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+pub struct OptionsDisplay<'a> {
+    term: &'a LambdaTerm,
+    options: &'a PrintOptions,
+}
+
+impl<'a> fmt::Display for OptionsDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        render(self.term, self.options, f)
+    }
+}
+
+impl LambdaTerm {
+    pub fn display_with<'a>(&'a self, options: &'a PrintOptions) -> OptionsDisplay<'a> {
+        OptionsDisplay {
+            term: self,
+            options,
+        }
+    }
+
+    pub fn write_term<W: io::Write>(&self, w: &mut W, options: &PrintOptions) -> io::Result<()> {
+        let mut buffered = io::BufWriter::new(w);
+        let mut adapter = IoAdapter {
+            inner: &mut buffered,
+            error: None,
+        };
+        if render(self, options, &mut adapter).is_err() {
+            return Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error")));
+        }
+        buffered.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexStyle {
+    // 1
+    Bare,
+    // #1
+    Hash,
+    // [1]
+    Bracketed,
+}
+
+#[derive(Debug, Clone)]
+pub struct DBPrintOptions {
+    pub index_style: IndexStyle,
+    // Counts from 0 rather than 1, as many texts on de Bruijn indices do.
+    pub zero_based: bool,
+    // Writes `λ. 1` rather than `λ 1`.
+    pub dot: bool,
+}
+
+impl Default for DBPrintOptions {
+    fn default() -> DBPrintOptions {
+        DBPrintOptions {
+            index_style: IndexStyle::Bare,
+            zero_based: false,
+            dot: false,
+        }
+    }
+}
+
+fn render_db(term: &DBTerm, options: &DBPrintOptions, f: &mut dyn fmt::Write) -> fmt::Result {
+    enum Work<'a> {
+        Term(&'a DBTerm),
+        Text(&'static str),
+    }
+    let lambda = if options.dot { "λ. " } else { "λ " };
+    let mut stack = vec![Work::Term(term)];
+    while let Some(work) = stack.pop() {
+        let term = match work {
+            Work::Term(term) => term,
+            Work::Text(text) => {
+                f.write_str(text)?;
+                continue;
+            }
+        };
+        match term {
+            DBTerm::Variable(index) => {
+                // Indices start at 1, so a 0 built by hand has no zero-based
+                // spelling and comes out as `?`.
+                let index = if options.zero_based {
+                    index
+                        .checked_sub(1)
+                        .map_or(String::from("?"), |i| i.to_string())
+                } else {
+                    index.to_string()
+                };
+                match options.index_style {
+                    IndexStyle::Bare => write!(f, "{}", index)?,
+                    IndexStyle::Hash => write!(f, "#{}", index)?,
+                    IndexStyle::Bracketed => write!(f, "[{}]", index)?,
+                }
+            }
+            DBTerm::FreeVariable(id) => Backticks.write_identifier(f, id)?,
+            DBTerm::Application { function, argument } => {
+                match **argument {
+                    DBTerm::Variable(_) | DBTerm::FreeVariable(_) => {
+                        stack.push(Work::Term(argument))
+                    }
+                    _ => stack.extend([Work::Text(")"), Work::Term(argument), Work::Text("(")]),
+                }
+                match **function {
+                    DBTerm::Abstraction(_) => {
+                        stack.extend([Work::Text(") "), Work::Term(function), Work::Text("(")])
+                    }
+                    _ => stack.extend([Work::Text(" "), Work::Term(function)]),
+                }
+            }
+            DBTerm::Abstraction(return_term) => {
+                f.write_str(lambda)?;
+                stack.push(Work::Term(return_term));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub struct DBOptionsDisplay<'a> {
+    term: &'a DBTerm,
+    options: &'a DBPrintOptions,
+}
+
+impl<'a> fmt::Display for DBOptionsDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        render_db(self.term, self.options, f)
+    }
+}
+
+impl DBTerm {
+    pub fn display_with<'a>(&'a self, options: &'a DBPrintOptions) -> DBOptionsDisplay<'a> {
+        DBOptionsDisplay {
+            term: self,
+            options,
+        }
+    }
+}
+
+impl DBIndices {
+    pub fn display_with<'a>(&'a self, options: &'a DBPrintOptions) -> DBOptionsDisplay<'a> {
+        self.0.display_with(options)
+    }
+}
+
+impl DBLevels {
+    pub fn display_with<'a>(&'a self, options: &'a DBPrintOptions) -> DBOptionsDisplay<'a> {
+        self.0.display_with(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app, lam, var};
+
+    #[test]
+    fn zero_based_indices_mark_index_zero() {
+        let options = DBPrintOptions {
+            zero_based: true,
+            index_style: IndexStyle::Hash,
+            ..DBPrintOptions::default()
+        };
+        let term = DBTerm::Abstraction(Box::new(DBTerm::Application {
+            function: Box::new(DBTerm::Variable(1)),
+            argument: Box::new(DBTerm::Variable(0)),
+        }));
+        assert_eq!(term.display_with(&options).to_string(), "λ #0 #?");
+    }
+
+    #[test]
+    fn nested_wrapping_prints_deep_terms() {
+        let depth = 100_000;
+        let mut term = var("x");
+        for i in 0..depth {
+            term = if i % 2 == 0 {
+                lam("x", term)
+            } else {
+                app(var("f"), term)
+            };
+        }
+        // Wide enough for one line; broken, the indentation alone would
+        // grow quadratically with the depth.
+        let options = PrintOptions {
+            width: Some(1_000_000),
+            wrap: Wrap::Nested,
+            ..PrintOptions::default()
+        };
+        let printed = term.display_with(&options).to_string();
+        assert_eq!(printed.matches('λ').count(), depth / 2);
+        // Dropping the term itself would recurse.
+        std::mem::forget(term);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::path::{Path, Step};
+use crate::{DBIndices, DBLevels, DBTerm, LambdaTerm};
+use std::collections::HashMap;
+
+// Nodes are numbered in pre-order, so the root is always `NodeId(0)` and the
+// numbering of a freshly parsed term is fixed by its shape alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize);
+
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    origins: HashMap<NodeId, NodeId>,
+}
+
+impl Provenance {
+    pub fn new() -> Provenance {
+        Provenance::default()
+    }
+
+    pub fn identity(node_count: usize) -> Provenance {
+        Provenance {
+            origins: (0..node_count).map(|id| (NodeId(id), NodeId(id))).collect(),
+        }
+    }
+
+    pub fn record(&mut self, output: NodeId, input: NodeId) {
+        self.origins.insert(output, input);
+    }
+
+    pub fn origin(&self, output: NodeId) -> Option<NodeId> {
+        self.origins.get(&output).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.origins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.origins.is_empty()
+    }
+
+    // `self` maps the intermediate term back to the input and `later` maps the
+    // output back to the intermediate term.
+    pub fn then(&self, later: &Provenance) -> Provenance {
+        Provenance {
+            origins: later
+                .origins
+                .iter()
+                .filter_map(|(output, intermediate)| {
+                    self.origin(*intermediate).map(|input| (*output, input))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Provenance {
+    // For `after`, which is `before` with the subterm at `path` replaced.
+    // Nodes outside that subterm come from where they were, and nodes inside
+    // it from wherever `local` says, `local` numbering the replacement and
+    // the subterm it replaced each from their own root.
+    pub fn replacing(
+        before: &LambdaTerm,
+        after: &LambdaTerm,
+        path: &Path,
+        local: &Provenance,
+    ) -> Provenance {
+        let NodeId(start) = before.node_id(path).expect("the path is in the term");
+        let replaced = before.node(NodeId(start)).map_or(0, LambdaTerm::node_count);
+        let replacement = after.node(NodeId(start)).map_or(0, LambdaTerm::node_count);
+        let mut provenance = Provenance::new();
+        for id in 0..after.node_count() {
+            let origin = if id < start {
+                Some(id)
+            } else if id < start + replacement {
+                local
+                    .origin(NodeId(id - start))
+                    .map(|NodeId(origin)| start + origin)
+            } else {
+                Some(id - replacement + replaced)
+            };
+            if let Some(origin) = origin {
+                provenance.record(NodeId(id), NodeId(origin));
+            }
+        }
+        provenance
+    }
+}
+
+pub struct Tracked<T> {
+    pub term: T,
+    pub provenance: Provenance,
+}
+
+impl LambdaTerm {
+    pub fn node_count(&self) -> usize {
+        match self {
+            LambdaTerm::Variable(_) => 1,
+            LambdaTerm::Abstraction { return_term, .. } => 1 + return_term.node_count(),
+            LambdaTerm::Application { function, argument } => {
+                1 + function.node_count() + argument.node_count()
+            }
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&LambdaTerm> {
+        let NodeId(mut remaining) = id;
+        let mut term = self;
+        loop {
+            if remaining == 0 {
+                break Some(term);
+            }
+            remaining -= 1;
+            term = match term {
+                LambdaTerm::Variable(_) => break None,
+                LambdaTerm::Abstraction { return_term, .. } => return_term,
+                LambdaTerm::Application { function, argument } => {
+                    let function_nodes = function.node_count();
+                    if remaining < function_nodes {
+                        function
+                    } else {
+                        remaining -= function_nodes;
+                        argument
+                    }
+                }
+            };
+        }
+    }
+
+    // The inverse of `node`, for the subterm a path leads to.
+    pub fn node_id(&self, path: &Path) -> Option<NodeId> {
+        let mut id = 0;
+        let mut term = self;
+        for step in &path.0 {
+            term = match (term, step) {
+                (LambdaTerm::Abstraction { return_term, .. }, Step::Body) => {
+                    id += 1;
+                    return_term
+                }
+                (LambdaTerm::Application { function, .. }, Step::Function) => {
+                    id += 1;
+                    function
+                }
+                (LambdaTerm::Application { function, argument }, Step::Argument) => {
+                    id += 1 + function.node_count();
+                    argument
+                }
+                _ => return None,
+            };
+        }
+        Some(NodeId(id))
+    }
+
+    pub fn tracked(self) -> Tracked<LambdaTerm> {
+        let provenance = Provenance::identity(self.node_count());
+        Tracked {
+            term: self,
+            provenance,
+        }
+    }
+}
+
+impl DBTerm {
+    pub fn node_count(&self) -> usize {
+        match self {
+            DBTerm::Variable(_) | DBTerm::FreeVariable(_) => 1,
+            DBTerm::Abstraction(return_term) => 1 + return_term.node_count(),
+            DBTerm::Application { function, argument } => {
+                1 + function.node_count() + argument.node_count()
+            }
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&DBTerm> {
+        let NodeId(mut remaining) = id;
+        let mut term = self;
+        loop {
+            if remaining == 0 {
+                break Some(term);
+            }
+            remaining -= 1;
+            term = match term {
+                DBTerm::Variable(_) | DBTerm::FreeVariable(_) => break None,
+                DBTerm::Abstraction(return_term) => return_term,
+                DBTerm::Application { function, argument } => {
+                    let function_nodes = function.node_count();
+                    if remaining < function_nodes {
+                        function
+                    } else {
+                        remaining -= function_nodes;
+                        argument
+                    }
+                }
+            };
+        }
+    }
+}
+
+// The de Bruijn conversions preserve the shape of the tree node for node, so
+// the provenance of their input carries over unchanged.
+
+impl From<Tracked<LambdaTerm>> for Tracked<DBLevels> {
+    fn from(tracked: Tracked<LambdaTerm>) -> Tracked<DBLevels> {
+        Tracked {
+            term: tracked.term.into(),
+            provenance: tracked.provenance,
+        }
+    }
+}
+
+impl From<Tracked<LambdaTerm>> for Tracked<DBIndices> {
+    fn from(tracked: Tracked<LambdaTerm>) -> Tracked<DBIndices> {
+        Tracked {
+            term: tracked.term.into(),
+            provenance: tracked.provenance,
+        }
+    }
+}
+
+impl From<Tracked<DBLevels>> for Tracked<DBIndices> {
+    fn from(tracked: Tracked<DBLevels>) -> Tracked<DBIndices> {
+        Tracked {
+            term: tracked.term.into(),
+            provenance: tracked.provenance,
+        }
+    }
+}
+
+impl From<Tracked<DBIndices>> for Tracked<DBLevels> {
+    fn from(tracked: Tracked<DBIndices>) -> Tracked<DBLevels> {
+        Tracked {
+            term: tracked.term.into(),
+            provenance: tracked.provenance,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::graph;
+use crate::names::NameSupply;
+use crate::rewrite::RewriteError;
+use crate::small::SmallName;
+use crate::{app, lam, parse, var, LambdaTerm};
+
+pub const DEFAULT_LIMIT: usize = 1_000_000;
+
+// Mogensen's self-interpreter E, for which E ⌜M⌝ has the same normal form as
+// M. It takes each quoted term apart with the three selectors, given Y:
+//
+//   E = Y (λe. λm. m (λx. x) (λm. λn. e m (e n)) (λm. λv. e (m v)))
+pub const SELF_INTERPRETER: &str = "(λf. (λx. f (x x)) (λx. f (x x))) \
+    (λe. λm. m (λx. x) (λm. λn. e m (e n)) (λm. λv. e (m v)))";
+
+pub fn self_interpreter() -> LambdaTerm {
+    parse(SELF_INTERPRETER).unwrap()
+}
+
+// The Mogensen–Scott encoding represents a term inside the calculus, with
+// binders represented by binders (higher-order abstract syntax):
+//
+//   ⌜x⌝     = λa. λb. λc. a x
+//   ⌜M N⌝   = λa. λb. λc. b ⌜M⌝ ⌜N⌝
+//   ⌜λx. M⌝ = λa. λb. λc. c (λx. ⌜M⌝)
+//
+// Every quoted term is in normal form, and ⌜M⌝ has the same free variables
+// as M. The names a, b and c are primed where M already uses them.
+pub fn quote(term: &LambdaTerm) -> LambdaTerm {
+    fn quote_mut(term: &LambdaTerm, selectors: &[SmallName; 3]) -> LambdaTerm {
+        let [a, b, c] = selectors;
+        let body = match term {
+            LambdaTerm::Variable(id) => app(var(a), var(id)),
+            LambdaTerm::Application { function, argument } => app(
+                app(var(b), quote_mut(function, selectors)),
+                quote_mut(argument, selectors),
+            ),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => app(
+                var(c),
+                lam(bound_variable, quote_mut(return_term, selectors)),
+            ),
+        };
+        lam(a, lam(b, lam(c, body)))
+    }
+    let mut names = NameSupply::new(
+        term.free_variables()
+            .into_iter()
+            .chain(term.bound_variables()),
+    );
+    let selectors = [names.fresh("a"), names.fresh("b"), names.fresh("c")];
+    quote_mut(term, &selectors)
+}
+
+// Reads back a term encoded by quote, whatever the encoding's binders are
+// called, or None if `term` isn't in that shape.
+pub fn unquote(term: &LambdaTerm) -> Option<LambdaTerm> {
+    let (a, b, c, body) = match term {
+        LambdaTerm::Abstraction {
+            bound_variable: a,
+            return_term,
+        } => match &**return_term {
+            LambdaTerm::Abstraction {
+                bound_variable: b,
+                return_term,
+            } => match &**return_term {
+                LambdaTerm::Abstraction {
+                    bound_variable: c,
+                    return_term,
+                } if a != b && b != c && a != c => (a, b, c, &**return_term),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let is = |term: &LambdaTerm, selector: &str| matches!(term, LambdaTerm::Variable(id) if id == selector);
+    let unquoted = match body {
+        LambdaTerm::Application { function, argument } if is(function, a) => match &**argument {
+            LambdaTerm::Variable(id) => LambdaTerm::Variable(id.clone()),
+            _ => return None,
+        },
+        LambdaTerm::Application { function, argument } => match (&**function, &**argument) {
+            (
+                LambdaTerm::Application {
+                    function: selector,
+                    argument: quoted_function,
+                },
+                quoted_argument,
+            ) if is(selector, b) => app(unquote(quoted_function)?, unquote(quoted_argument)?),
+            (
+                selector,
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                },
+            ) if is(selector, c) => lam(bound_variable, unquote(return_term)?),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    // The selectors are bound by this layer of the encoding, so they can't
+    // also stand for the encoded term's own free variables.
+    let free = unquoted.free_variables();
+    if free.contains(a) || free.contains(b) || free.contains(c) {
+        None
+    } else {
+        Some(unquoted)
+    }
+}
+
+// Normalizes `term` by quoting it and running the self-interpreter on the
+// quotation, which takes far more reductions than normalizing it directly.
+pub fn interpret_via_self(term: &LambdaTerm) -> Result<LambdaTerm, RewriteError> {
+    interpret_via_self_with_limit(term, DEFAULT_LIMIT)
+}
+
+pub fn interpret_via_self_with_limit(
+    term: &LambdaTerm,
+    limit: usize,
+) -> Result<LambdaTerm, RewriteError> {
+    graph::evaluate(&app(self_interpreter(), quote(term)), limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+
+    #[test]
+    fn unquotes_quotations() {
+        for code in ["x", "λx. x", "f (λa. a b c)", "λa. λb. λc. c b a"] {
+            let term = parse(code).unwrap();
+            assert_eq!(unquote(&quote(&term)), Some(term));
+        }
+        assert_eq!(unquote(&parse("λx. x").unwrap()), None);
+    }
+
+    #[test]
+    fn self_interpretation_agrees_with_normal_order_rewriting() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 300).rule(Beta);
+        let mut rng = SplitMix64(383);
+        let mut compared = 0;
+        for i in 0..200 {
+            let term = gen_closed_term(1 + i % 12, &mut rng);
+            let term = app(app(term, var("p")), lam("q", var("q")));
+            if let Ok(expected) = rewriter.normalize(&term) {
+                let normal = interpret_via_self(&term).unwrap();
+                assert!(normal.alpha_equivalent(&expected), "{}", term);
+                compared += 1;
+            }
+        }
+        assert!(compared > 150);
+        let omega = parse("(λx. x x) (λx. x x)").unwrap();
+        assert!(matches!(
+            interpret_via_self_with_limit(&omega, 1000),
+            Err(RewriteError::StepLimit(1000))
+        ));
+    }
+}
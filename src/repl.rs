@@ -0,0 +1,359 @@
+#[cfg(feature = "line-editing")]
+use crate::editor::Editor;
+use crate::environment::{Environment, Expansion};
+use crate::lint;
+use crate::print::{Numerals, Parens, PrintOptions, Wrap};
+use crate::rewrite::{Beta, Rewriter, Strategy};
+use crate::step::Stepper;
+use crate::{is_plain_identifier, parse, IncrementalParser, ParseStatus};
+use std::io::{self, BufRead, Write};
+
+pub enum Outcome {
+    Output(String),
+    Quit,
+}
+
+const HELP: &str = "\
+enter a term to have it parsed and printed back (unclosed parentheses
+continue it onto the next line), or one of
+  :set                      show the current print settings
+  :set width <n>|off        break output lines longer than n columns
+  :set wrap fill|nested     fill lines with words, or break by structure
+  :set ascii on|off         print \\ instead of λ
+  :set parens explicit|minimal|clarifying
+  :set numerals sugar|plain print Church numerals as digits
+  :set dot spaced|tight     print λx. M or λx.M
+  :set binders collapsed|separate
+  :set fold on|off          print subterms matching a definition by name
+  :let <name> = <term>      add a definition to the environment
+  :env                      list the definitions in the environment
+  :eval <term>              normalize a term, expanding definitions lazily
+  :lint <term>              point out groupings that depend on precedence
+  :step <term>              reduce a term one chosen redex at a time
+  :cancel                   discard a partially entered term
+  :history                  list the lines entered so far
+  !!, !<n>                  run the last line, or line n of :history, again
+  :help                     show this message
+  :quit                     leave the session";
+
+#[derive(Default)]
+pub struct Session {
+    pub print_options: PrintOptions,
+    pub environment: Environment,
+    pub fold_definitions: bool,
+    input: IncrementalParser,
+    // While set, lines go to the stepper instead of being parsed as terms.
+    stepper: Option<Stepper>,
+    // Every non-empty line entered, oldest first, including those loaded
+    // from a history file.
+    pub history: Vec<String>,
+}
+
+// How many lines save_history keeps.
+const HISTORY_LIMIT: usize = 1000;
+
+// How many steps :eval takes before giving up.
+const EVAL_LIMIT: usize = 10_000;
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    pub fn execute(&mut self, line: &str) -> Outcome {
+        let line = match self.recall(line) {
+            Ok(line) => line,
+            Err(message) => return Outcome::Output(message),
+        };
+        if !line.trim().is_empty() {
+            self.history.push(line.clone());
+        }
+        let line = line.as_str();
+        if let Some(stepper) = &mut self.stepper {
+            return match stepper.execute(line) {
+                Outcome::Quit => {
+                    self.stepper = None;
+                    Outcome::Output(String::new())
+                }
+                output => output,
+            };
+        }
+        let trimmed = line.trim();
+        if let Some(command) = trimmed.strip_prefix(':') {
+            self.command(command)
+        } else if trimmed.is_empty() && !self.input.is_pending() {
+            Outcome::Output(String::new())
+        } else {
+            match self.input.push(&format!("{}\n", line)) {
+                ParseStatus::Complete(term) => {
+                    let term = if self.fold_definitions {
+                        self.environment.fold(&term)
+                    } else {
+                        term
+                    };
+                    Outcome::Output(term.display_with(&self.print_options).to_string())
+                }
+                ParseStatus::Incomplete => Outcome::Output(String::new()),
+                ParseStatus::Failed(err) => Outcome::Output(format!("error: {:?}", err)),
+            }
+        }
+    }
+
+    // Expands `!!` and `!<n>` into the line they refer to, leaving any other
+    // line alone.
+    fn recall(&self, line: &str) -> Result<String, String> {
+        let reference = match line.trim().strip_prefix('!') {
+            Some(reference) if self.stepper.is_none() => reference,
+            _ => return Ok(String::from(line)),
+        };
+        let found = match reference {
+            "!" => self.history.last(),
+            n => n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| self.history.get(i)),
+        };
+        found
+            .cloned()
+            .ok_or_else(|| format!("no line !{} in the history", reference))
+    }
+
+    pub fn load_history(&mut self, path: &std::path::Path) -> io::Result<()> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                self.history.extend(text.lines().map(String::from));
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Writes the most recent lines of the history, HISTORY_LIMIT at most.
+    pub fn save_history(&self, path: &std::path::Path) -> io::Result<()> {
+        let start = self.history.len().saturating_sub(HISTORY_LIMIT);
+        let mut text = self.history[start..].join("\n");
+        text.push('\n');
+        std::fs::write(path, text)
+    }
+
+    fn command(&mut self, command: &str) -> Outcome {
+        let mut words = command.split_whitespace();
+        match words.next() {
+            Some("q") | Some("quit") => Outcome::Quit,
+            Some("cancel") => {
+                self.input.reset();
+                Outcome::Output(String::new())
+            }
+            Some("set") => Outcome::Output(self.set(&words.collect::<Vec<_>>())),
+            Some("let") => Outcome::Output(self.define(command["let".len()..].trim())),
+            Some("env") => Outcome::Output(
+                self.environment
+                    .iter()
+                    .map(|(name, term)| {
+                        format!("{} = {}", name, term.display_with(&self.print_options))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Some("eval") => Outcome::Output(self.evaluate(command["eval".len()..].trim())),
+            Some("lint") => Outcome::Output(
+                lint::lint(command["lint".len()..].trim())
+                    .into_iter()
+                    .map(|lint| format!("token {}: {}", lint.token_index, lint.message))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Some("step") => match parse(command["step".len()..].trim()) {
+                Ok(term) => {
+                    let mut stepper = Stepper::new(term);
+                    stepper.print_options = self.print_options.clone();
+                    let shown = stepper.show();
+                    self.stepper = Some(stepper);
+                    Outcome::Output(shown)
+                }
+                Err(err) => Outcome::Output(format!("error: {:?}", err)),
+            },
+            Some("history") => Outcome::Output(
+                self.history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| format!("{:>4}  {}", i + 1, line))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Some("help") | None => Outcome::Output(String::from(HELP)),
+            Some(other) => Outcome::Output(format!("unknown command :{} (try :help)", other)),
+        }
+    }
+
+    fn evaluate(&self, source: &str) -> String {
+        let term = match parse(source) {
+            Ok(term) => term,
+            Err(err) => return format!("error: {:?}", err),
+        };
+        let rewriter = Rewriter::new(Strategy::TopDown, EVAL_LIMIT).rule(Beta);
+        match self
+            .environment
+            .normalize(&rewriter, &term, Expansion::Lazy)
+        {
+            Ok(term) => {
+                let term = if self.fold_definitions {
+                    self.environment.fold(&term)
+                } else {
+                    term
+                };
+                term.display_with(&self.print_options).to_string()
+            }
+            Err(err) => format!("error: {:?}", err),
+        }
+    }
+
+    fn define(&mut self, definition: &str) -> String {
+        let (name, source) = match definition.split_once('=') {
+            Some((name, source)) => (name.trim(), source),
+            None => return String::from("usage: :let <name> = <term>"),
+        };
+        if !is_plain_identifier(name) {
+            return format!("invalid name {}", name);
+        }
+        match parse(source) {
+            Ok(term) => {
+                self.environment.define(name, term);
+                String::new()
+            }
+            Err(err) => format!("error: {:?}", err),
+        }
+    }
+
+    fn set(&mut self, args: &[&str]) -> String {
+        let options = &mut self.print_options;
+        match args {
+            [] => {
+                return format!(
+                "width {}\nwrap {}\nascii {}\nparens {}\nnumerals {}\ndot {}\nbinders {}\nfold {}",
+                options
+                    .width
+                    .map_or_else(|| String::from("off"), |width| width.to_string()),
+                match options.wrap {
+                    Wrap::Fill => "fill",
+                    Wrap::Nested => "nested",
+                },
+                if options.ascii { "on" } else { "off" },
+                match options.parens {
+                    Parens::Minimal => "minimal",
+                    Parens::Explicit => "explicit",
+                    Parens::Clarifying => "clarifying",
+                },
+                match options.numerals {
+                    Numerals::Plain => "plain",
+                    Numerals::Sugar => "sugar",
+                },
+                if options.space_after_dot {
+                    "spaced"
+                } else {
+                    "tight"
+                },
+                if options.collapse_binders {
+                    "collapsed"
+                } else {
+                    "separate"
+                },
+                if self.fold_definitions { "on" } else { "off" },
+            )
+            }
+            ["width", "off"] => options.width = None,
+            ["width", width] => match width.parse() {
+                Ok(width) if width > 0 => options.width = Some(width),
+                _ => return format!("invalid width {}", width),
+            },
+            ["wrap", "fill"] => options.wrap = Wrap::Fill,
+            ["wrap", "nested"] => options.wrap = Wrap::Nested,
+            ["ascii", "on"] => options.ascii = true,
+            ["ascii", "off"] => options.ascii = false,
+            ["parens", "explicit"] => options.parens = Parens::Explicit,
+            ["parens", "minimal"] => options.parens = Parens::Minimal,
+            ["parens", "clarifying"] => options.parens = Parens::Clarifying,
+            ["numerals", "sugar"] => options.numerals = Numerals::Sugar,
+            ["numerals", "plain"] => options.numerals = Numerals::Plain,
+            ["dot", "spaced"] => options.space_after_dot = true,
+            ["dot", "tight"] => options.space_after_dot = false,
+            ["binders", "collapsed"] => options.collapse_binders = true,
+            ["binders", "separate"] => options.collapse_binders = false,
+            ["fold", "on"] => self.fold_definitions = true,
+            ["fold", "off"] => self.fold_definitions = false,
+            _ => return format!("unknown setting {} (try :help)", args.join(" ")),
+        }
+        String::new()
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.stepper.is_some() {
+            "step> "
+        } else if self.input.is_pending() {
+            ".. "
+        } else if self.print_options.ascii {
+            "\\> "
+        } else {
+            "λ> "
+        }
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            write!(output, "{}", self.prompt())?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => {
+                    writeln!(output)?;
+                    break;
+                }
+            };
+            match self.execute(&line) {
+                Outcome::Output(text) if text.is_empty() => (),
+                Outcome::Output(text) => writeln!(output, "{}", text)?,
+                Outcome::Quit => break,
+            }
+        }
+        Ok(())
+    }
+
+    // Like `run`, but reads each line from the terminal through an editor
+    // with cursor movement and the session's history on the arrow keys.
+    #[cfg(feature = "line-editing")]
+    pub fn run_editing<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        let mut editor = Editor::new();
+        while let Some(line) =
+            editor.read_line_from_terminal(self.prompt(), &self.history, output)?
+        {
+            match self.execute(&line) {
+                Outcome::Output(text) if text.is_empty() => (),
+                Outcome::Output(text) => writeln!(output, "{}", text)?,
+                Outcome::Quit => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(session: &mut Session, line: &str) -> String {
+        match session.execute(line) {
+            Outcome::Output(output) => output,
+            Outcome::Quit => panic!("{} quit the session", line),
+        }
+    }
+
+    #[test]
+    fn eval_expands_definitions_in_normal_order() {
+        let mut session = Session::new();
+        output(&mut session, ":let K = λx. λy. x");
+        assert_eq!(output(&mut session, ":eval K a ((λx. x x) (λx. x x))"), "a");
+    }
+}
@@ -0,0 +1,487 @@
+use crate::path::{Path, Step};
+use crate::pattern::{Bindings, Pattern};
+use crate::provenance::{NodeId, Provenance, Tracked};
+use crate::small::SmallName;
+use crate::visit::Order;
+use crate::{LambdaTerm, ParserError};
+use std::collections::HashSet;
+
+pub trait Rewrite {
+    fn name(&self) -> &str;
+
+    // Rewrites `term` itself, not its subterms, if the rule applies there.
+    fn rewrite(&self, term: &LambdaTerm) -> Option<LambdaTerm>;
+
+    // Maps nodes of `contractum`, what `rewrite` gave back for `redex`, to
+    // the nodes of `redex` they came from, each numbered from its own root.
+    // Unless a rule knows better, only the root is accounted for.
+    fn provenance(&self, redex: &LambdaTerm, contractum: &LambdaTerm) -> Provenance {
+        let _ = (redex, contractum);
+        let mut provenance = Provenance::new();
+        provenance.record(NodeId(0), NodeId(0));
+        provenance
+    }
+}
+
+// A user rule, `pattern ⇒ template`, where the template's free occurrences
+// of the pattern's metavariables are filled in from the match.
+pub struct Rule {
+    name: String,
+    pattern: Pattern,
+    template: LambdaTerm,
+    condition: Option<fn(&Bindings) -> bool>,
+}
+
+impl Rule {
+    pub fn new(name: impl Into<String>, pattern: Pattern, template: LambdaTerm) -> Rule {
+        Rule {
+            name: name.into(),
+            pattern,
+            template,
+            condition: None,
+        }
+    }
+
+    pub fn parse<S: Into<SmallName>>(
+        name: impl Into<String>,
+        pattern: &str,
+        template: &str,
+        metavariables: impl IntoIterator<Item = S>,
+    ) -> Result<Rule, ParserError> {
+        Ok(Rule::new(
+            name,
+            Pattern::parse(pattern, metavariables)?,
+            crate::parse(template)?,
+        ))
+    }
+
+    // Only rewrite matches for which `condition` holds.
+    pub fn when(mut self, condition: fn(&Bindings) -> bool) -> Rule {
+        self.condition = Some(condition);
+        self
+    }
+
+    fn introduced(&self) -> HashSet<SmallName> {
+        self.template
+            .free_variables()
+            .difference(self.pattern.metavariables())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Rewrite for Rule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rewrite(&self, term: &LambdaTerm) -> Option<LambdaTerm> {
+        let bindings = self.pattern.match_against(term)?;
+        if !self.condition.is_none_or(|condition| condition(&bindings)) {
+            return None;
+        }
+        let result = bindings.instantiate(&self.template);
+        // A binding can mention a variable bound inside the pattern; placing
+        // it somewhere outside that binder would leave the variable free.
+        let mut allowed = term.free_variables();
+        allowed.extend(self.introduced());
+        // Higher-order patterns like `λx. F x ⇒ F` also match terms they
+        // leave as they are; treating those as rewrites would never finish.
+        if result.free_variables().is_subset(&allowed) && !result.alpha_equivalent(term) {
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+// (λx. M) N ⇒ M[x := N]
+pub struct Beta;
+
+impl Rewrite for Beta {
+    fn name(&self) -> &str {
+        "β"
+    }
+
+    fn rewrite(&self, term: &LambdaTerm) -> Option<LambdaTerm> {
+        match term {
+            LambdaTerm::Application { function, argument } => match &**function {
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => Some(return_term.substitute(bound_variable, argument)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // Substitution keeps the body's shape, with a copy of the argument in
+    // place of each free occurrence of the bound variable.
+    fn provenance(&self, redex: &LambdaTerm, _: &LambdaTerm) -> Provenance {
+        fn walk(
+            body: &LambdaTerm,
+            id: usize,
+            variable: &str,
+            argument: (usize, usize),
+            next: &mut usize,
+            provenance: &mut Provenance,
+        ) {
+            let copied = match body {
+                LambdaTerm::Variable(name) if name == variable => argument,
+                LambdaTerm::Abstraction { bound_variable, .. } if bound_variable == variable => {
+                    (id, body.node_count())
+                }
+                _ => (id, 1),
+            };
+            for offset in 0..copied.1 {
+                provenance.record(NodeId(*next + offset), NodeId(copied.0 + offset));
+            }
+            *next += copied.1;
+            match body {
+                LambdaTerm::Variable(_) => (),
+                LambdaTerm::Abstraction { bound_variable, .. } if bound_variable == variable => (),
+                LambdaTerm::Abstraction { return_term, .. } => {
+                    walk(return_term, id + 1, variable, argument, next, provenance)
+                }
+                LambdaTerm::Application {
+                    function,
+                    argument: operand,
+                } => {
+                    walk(function, id + 1, variable, argument, next, provenance);
+                    let id = id + 1 + function.node_count();
+                    walk(operand, id, variable, argument, next, provenance);
+                }
+            }
+        }
+        let mut provenance = Provenance::new();
+        if let LambdaTerm::Application { function, argument } = redex {
+            if let LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } = &**function
+            {
+                // The redex is numbered application, abstraction, body,
+                // argument.
+                let argument = (2 + return_term.node_count(), argument.node_count());
+                walk(
+                    return_term,
+                    2,
+                    bound_variable,
+                    argument,
+                    &mut 0,
+                    &mut provenance,
+                );
+            }
+        }
+        provenance
+    }
+}
+
+// λx. M x ⇒ M, when x isn't free in M
+pub struct Eta;
+
+impl Rewrite for Eta {
+    fn name(&self) -> &str {
+        "η"
+    }
+
+    fn rewrite(&self, term: &LambdaTerm) -> Option<LambdaTerm> {
+        match term {
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => match &**return_term {
+                LambdaTerm::Application { function, argument }
+                    if matches!(&**argument, LambdaTerm::Variable(id) if id == bound_variable)
+                        && !function.free_variables().contains(bound_variable) =>
+                {
+                    Some((**function).clone())
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    // The contractum is M itself, which in λx. M x starts at the third node.
+    fn provenance(&self, _: &LambdaTerm, contractum: &LambdaTerm) -> Provenance {
+        let mut provenance = Provenance::new();
+        for id in 0..contractum.node_count() {
+            provenance.record(NodeId(id), NodeId(id + 2));
+        }
+        provenance
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    // Try the outermost, leftmost subterm first.
+    TopDown,
+    // Try the innermost, leftmost subterm first.
+    BottomUp,
+}
+
+#[derive(Debug)]
+pub enum RewriteError {
+    StepLimit(usize),
+}
+
+// The rule that fired, where, and the whole term after it fired.
+pub struct Rewritten<'r> {
+    pub rule: &'r str,
+    pub path: Path,
+    pub term: LambdaTerm,
+}
+
+pub struct Rewriter {
+    pub rules: Vec<Box<dyn Rewrite>>,
+    pub strategy: Strategy,
+    pub limit: usize,
+}
+
+impl Rewriter {
+    pub fn new(strategy: Strategy, limit: usize) -> Rewriter {
+        Rewriter {
+            rules: Vec::new(),
+            strategy,
+            limit,
+        }
+    }
+
+    pub fn rule(mut self, rule: impl Rewrite + 'static) -> Rewriter {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn step(&self, term: &LambdaTerm) -> Option<Rewritten<'_>> {
+        self.step_with(term, |_, _| None)
+    }
+
+    // Like step, but tries `extra` at each subterm before the rules, in the
+    // same traversal. It is given the subterm's path and gives back the
+    // name of what it did and the replacement.
+    pub(crate) fn step_with<'r>(
+        &'r self,
+        term: &LambdaTerm,
+        extra: impl Fn(&Path, &LambdaTerm) -> Option<(&'r str, LambdaTerm)>,
+    ) -> Option<Rewritten<'r>> {
+        self.find(term, extra).map(|(rewritten, _)| rewritten)
+    }
+
+    // Like step, but also maps the nodes of the result to the nodes of the
+    // term `term` was tracked from. Nodes of the contractum that a rule
+    // builds from nothing have no origin.
+    pub fn step_tracked(&self, term: &Tracked<LambdaTerm>) -> Option<Tracked<Rewritten<'_>>> {
+        let (rewritten, rule) = self.find(&term.term, |_, _| None)?;
+        let redex = term
+            .term
+            .get(&rewritten.path)
+            .expect("the redex is in the term");
+        let contractum = rewritten
+            .term
+            .get(&rewritten.path)
+            .expect("so is the contractum");
+        let local = match rule {
+            Some(rule) => rule.provenance(redex, contractum),
+            None => Provenance::new(),
+        };
+        let step = Provenance::replacing(&term.term, &rewritten.term, &rewritten.path, &local);
+        Some(Tracked {
+            provenance: term.provenance.then(&step),
+            term: rewritten,
+        })
+    }
+
+    // The rewrite step_with would take, and the rule that took it, unless it
+    // was `extra`.
+    fn find<'r>(
+        &'r self,
+        term: &LambdaTerm,
+        extra: impl Fn(&Path, &LambdaTerm) -> Option<(&'r str, LambdaTerm)>,
+    ) -> Option<(Rewritten<'r>, Option<&'r dyn Rewrite>)> {
+        let order = match self.strategy {
+            Strategy::TopDown => Order::Pre,
+            Strategy::BottomUp => Order::Post,
+        };
+        for (path, subterm) in term.iter_subterms(order) {
+            let extra = extra(&path, subterm);
+            let rules = self
+                .rules
+                .iter()
+                .map(|rule| (rule.name(), Some(&**rule), rule.rewrite(subterm)));
+            for (name, rule, replacement) in extra
+                .map(|(name, term)| (name, None, Some(term)))
+                .into_iter()
+                .chain(rules)
+            {
+                let replacement = match replacement {
+                    Some(replacement) => replacement,
+                    None => continue,
+                };
+                // A rule may bring in free variables the subterm didn't have;
+                // skip the spot if a binder above would capture them.
+                let fresh = replacement.free_variables();
+                let original = subterm.free_variables();
+                if binders_along(term, &path)
+                    .iter()
+                    .any(|binder| fresh.contains(*binder) && !original.contains(*binder))
+                {
+                    continue;
+                }
+                let mut rewritten = term.clone();
+                let _ = rewritten.replace(&path, replacement);
+                let rewritten = Rewritten {
+                    rule: name,
+                    path,
+                    term: rewritten,
+                };
+                return Some((rewritten, rule));
+            }
+        }
+        None
+    }
+
+    // Rewrites until no rule applies anywhere.
+    pub fn normalize(&self, term: &LambdaTerm) -> Result<LambdaTerm, RewriteError> {
+        let mut term = term.clone();
+        for _ in 0..self.limit {
+            match self.step(&term) {
+                Some(rewritten) => term = rewritten.term,
+                None => return Ok(term),
+            }
+        }
+        match self.step(&term) {
+            Some(_) => Err(RewriteError::StepLimit(self.limit)),
+            None => Ok(term),
+        }
+    }
+
+    // Like normalize, keeping track of where each node of the normal form
+    // came from.
+    pub fn normalize_tracked(
+        &self,
+        term: Tracked<LambdaTerm>,
+    ) -> Result<Tracked<LambdaTerm>, RewriteError> {
+        let mut term = term;
+        for _ in 0..self.limit {
+            match self.step_tracked(&term) {
+                Some(rewritten) => {
+                    term = Tracked {
+                        term: rewritten.term.term,
+                        provenance: rewritten.provenance,
+                    }
+                }
+                None => return Ok(term),
+            }
+        }
+        match self.step(&term.term) {
+            Some(_) => Err(RewriteError::StepLimit(self.limit)),
+            None => Ok(term),
+        }
+    }
+}
+
+pub(crate) fn binders_along<'a>(term: &'a LambdaTerm, path: &Path) -> Vec<&'a str> {
+    let mut binders = Vec::new();
+    let mut term = term;
+    for step in &path.0 {
+        term = match (term, step) {
+            (
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                },
+                Step::Body,
+            ) => {
+                binders.push(bound_variable.as_str());
+                return_term
+            }
+            (LambdaTerm::Application { function, .. }, Step::Function) => function,
+            (LambdaTerm::Application { argument, .. }, Step::Argument) => argument,
+            _ => break,
+        };
+    }
+    binders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::{app, parse, var};
+
+    fn origins(tracked: &Tracked<LambdaTerm>) -> Vec<Option<usize>> {
+        (0..tracked.term.node_count())
+            .map(|id| tracked.provenance.origin(NodeId(id)).map(|NodeId(id)| id))
+            .collect()
+    }
+
+    #[test]
+    fn beta_steps_trace_copies_of_the_argument() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 100).rule(Beta);
+        let term = parse("g ((λx. f x x) a)").unwrap().tracked();
+        let step = rewriter.step_tracked(&term).unwrap();
+        assert_eq!(step.term.term.to_string(), "g (f a a)");
+        let step = Tracked {
+            term: step.term.term,
+            provenance: step.provenance,
+        };
+        // g ((λx. f x x) a) is numbered app, g, app, λx, app, app, f, x, x, a.
+        assert_eq!(origins(&step), [0, 1, 4, 5, 6, 9, 9].map(Some).to_vec());
+    }
+
+    #[test]
+    fn eta_and_other_rules_trace_what_they_can() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 100).rule(Eta);
+        let step = rewriter.step_tracked(&parse("λx. f y x").unwrap().tracked());
+        let step = step.unwrap();
+        assert_eq!(step.term.term.to_string(), "f y");
+        assert_eq!(step.provenance.origin(NodeId(0)), Some(NodeId(2)));
+        assert_eq!(step.provenance.origin(NodeId(2)), Some(NodeId(4)));
+
+        let swap = Rule::parse("swap", "f a b", "f b a", ["a", "b"]).unwrap();
+        let rewriter = Rewriter::new(Strategy::TopDown, 100).rule(swap);
+        let step = rewriter.step_tracked(&parse("λz. f a b").unwrap().tracked());
+        let step = step.unwrap();
+        assert_eq!(step.provenance.origin(NodeId(0)), Some(NodeId(0)));
+        assert_eq!(step.provenance.origin(NodeId(1)), Some(NodeId(1)));
+        assert_eq!(step.provenance.origin(NodeId(2)), None);
+    }
+
+    #[test]
+    fn normal_forms_come_from_nodes_of_the_same_kind() {
+        let rewriter = Rewriter::new(Strategy::TopDown, 300).rule(Beta);
+        let mut rng = SplitMix64(290);
+        let mut compared = 0;
+        for i in 0..300 {
+            let term = gen_closed_term(1 + i % 12, &mut rng);
+            let term = app(app(term, var("p")), var("q"));
+            let Ok(normal) = rewriter.normalize_tracked(term.clone().tracked()) else {
+                continue;
+            };
+            assert!(normal
+                .term
+                .alpha_equivalent(&rewriter.normalize(&term).unwrap()));
+            let free = term.free_variables();
+            for id in 0..normal.term.node_count() {
+                let output = normal.term.node(NodeId(id)).unwrap();
+                let origin = normal
+                    .provenance
+                    .origin(NodeId(id))
+                    .expect("β traces every node");
+                let input = term.node(origin).unwrap();
+                match (output, input) {
+                    (LambdaTerm::Variable(name), LambdaTerm::Variable(origin)) => {
+                        assert!(!free.contains(name) || name == origin, "{}", term)
+                    }
+                    (LambdaTerm::Abstraction { .. }, LambdaTerm::Abstraction { .. })
+                    | (LambdaTerm::Application { .. }, LambdaTerm::Application { .. }) => (),
+                    _ => panic!("{} came from {} in {}", output, input, term),
+                }
+            }
+            compared += 1;
+        }
+        assert!(compared > 200);
+    }
+}
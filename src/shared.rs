@@ -0,0 +1,299 @@
+use crate::names::NameSupply;
+use crate::small::SmallName;
+use crate::LambdaTerm;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum SharedNode {
+    Abstraction {
+        bound_variable: SmallName,
+        return_term: SharedTerm,
+    },
+    Application {
+        function: SharedTerm,
+        argument: SharedTerm,
+    },
+    Variable(SmallName),
+}
+
+// A term whose subterms are reference counted, so that rewriting it can hand
+// back the parts it leaves alone instead of copying them. Cloning one is
+// just a count increment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SharedTerm(Rc<SharedNode>);
+
+impl Deref for SharedTerm {
+    type Target = SharedNode;
+
+    fn deref(&self) -> &SharedNode {
+        &self.0
+    }
+}
+
+impl SharedTerm {
+    pub fn variable(id: impl Into<SmallName>) -> SharedTerm {
+        SharedTerm(Rc::new(SharedNode::Variable(id.into())))
+    }
+
+    pub fn abstraction(
+        bound_variable: impl Into<SmallName>,
+        return_term: SharedTerm,
+    ) -> SharedTerm {
+        SharedTerm(Rc::new(SharedNode::Abstraction {
+            bound_variable: bound_variable.into(),
+            return_term,
+        }))
+    }
+
+    pub fn application(function: SharedTerm, argument: SharedTerm) -> SharedTerm {
+        SharedTerm(Rc::new(SharedNode::Application { function, argument }))
+    }
+
+    // Whether the two are the same node in memory, not just equal.
+    pub fn ptr_eq(&self, other: &SharedTerm) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub fn occurs_free(&self, variable: &str) -> bool {
+        match &**self {
+            SharedNode::Variable(id) => id == variable,
+            SharedNode::Abstraction { bound_variable, .. } if bound_variable == variable => false,
+            SharedNode::Abstraction { return_term, .. } => return_term.occurs_free(variable),
+            SharedNode::Application { function, argument } => {
+                function.occurs_free(variable) || argument.occurs_free(variable)
+            }
+        }
+    }
+
+    pub fn free_variables(&self) -> HashSet<SmallName> {
+        fn free_variables_mut<'a>(
+            term: &'a SharedTerm,
+            bound: &mut Vec<&'a str>,
+            set: &mut HashSet<SmallName>,
+        ) {
+            match &**term {
+                SharedNode::Variable(id) => {
+                    if !bound.contains(&id.as_str()) {
+                        set.insert(id.clone());
+                    }
+                }
+                SharedNode::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    bound.push(bound_variable);
+                    free_variables_mut(return_term, bound, set);
+                    bound.pop();
+                }
+                SharedNode::Application { function, argument } => {
+                    free_variables_mut(function, bound, set);
+                    free_variables_mut(argument, bound, set);
+                }
+            }
+        }
+        let mut set = HashSet::new();
+        free_variables_mut(self, &mut Vec::new(), &mut set);
+        set
+    }
+
+    // Capture-avoiding substitution as LambdaTerm::substitute does it, except
+    // that every subterm in which `variable` doesn't occur free comes back
+    // as the very same node, and each occurrence that is replaced shares
+    // `replacement` rather than copying it. If `variable` doesn't occur free
+    // at all, the result is `self`.
+    pub fn substitute(&self, variable: &str, replacement: &SharedTerm) -> SharedTerm {
+        // None when `term` comes through unchanged.
+        fn substitute_mut(
+            term: &SharedTerm,
+            variable: &str,
+            replacement: &SharedTerm,
+            replacement_free: &HashSet<SmallName>,
+        ) -> Option<SharedTerm> {
+            match &**term {
+                SharedNode::Variable(id) if id == variable => Some(replacement.clone()),
+                SharedNode::Variable(_) => None,
+                SharedNode::Application { function, argument } => {
+                    let new_function =
+                        substitute_mut(function, variable, replacement, replacement_free);
+                    let new_argument =
+                        substitute_mut(argument, variable, replacement, replacement_free);
+                    if new_function.is_none() && new_argument.is_none() {
+                        return None;
+                    }
+                    Some(SharedTerm::application(
+                        new_function.unwrap_or_else(|| function.clone()),
+                        new_argument.unwrap_or_else(|| argument.clone()),
+                    ))
+                }
+                SharedNode::Abstraction { bound_variable, .. } if bound_variable == variable => {
+                    None
+                }
+                SharedNode::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    if replacement_free.contains(bound_variable)
+                        && return_term.occurs_free(variable)
+                    {
+                        let mut names = NameSupply::new(return_term.free_variables());
+                        for id in replacement_free {
+                            names.avoid(id.clone());
+                        }
+                        let fresh = names.fresh(bound_variable);
+                        let renamed = return_term
+                            .substitute(bound_variable, &SharedTerm::variable(fresh.clone()));
+                        let body =
+                            substitute_mut(&renamed, variable, replacement, replacement_free)
+                                .unwrap_or(renamed);
+                        Some(SharedTerm::abstraction(fresh, body))
+                    } else {
+                        substitute_mut(return_term, variable, replacement, replacement_free)
+                            .map(|body| SharedTerm::abstraction(bound_variable.clone(), body))
+                    }
+                }
+            }
+        }
+        // Without an occurrence there's nothing to do, and no need to find
+        // the replacement's free variables either.
+        if !self.occurs_free(variable) {
+            return self.clone();
+        }
+        substitute_mut(self, variable, replacement, &replacement.free_variables())
+            .unwrap_or_else(|| self.clone())
+    }
+
+    // Contracts the term if it is itself a β-redex.
+    pub fn beta(&self) -> Option<SharedTerm> {
+        match &**self {
+            SharedNode::Application { function, argument } => match &**function {
+                SharedNode::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => Some(return_term.substitute(bound_variable, argument)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_lambda(&self) -> LambdaTerm {
+        match &**self {
+            SharedNode::Variable(id) => LambdaTerm::Variable(id.clone()),
+            SharedNode::Abstraction {
+                bound_variable,
+                return_term,
+            } => LambdaTerm::Abstraction {
+                bound_variable: bound_variable.clone(),
+                return_term: Box::new(return_term.to_lambda()),
+            },
+            SharedNode::Application { function, argument } => LambdaTerm::Application {
+                function: Box::new(function.to_lambda()),
+                argument: Box::new(argument.to_lambda()),
+            },
+        }
+    }
+}
+
+impl LambdaTerm {
+    pub fn to_shared(&self) -> SharedTerm {
+        match self {
+            LambdaTerm::Variable(id) => SharedTerm::variable(id.clone()),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => SharedTerm::abstraction(bound_variable.clone(), return_term.to_shared()),
+            LambdaTerm::Application { function, argument } => {
+                SharedTerm::application(function.to_shared(), argument.to_shared())
+            }
+        }
+    }
+}
+
+impl From<&LambdaTerm> for SharedTerm {
+    fn from(term: &LambdaTerm) -> SharedTerm {
+        term.to_shared()
+    }
+}
+
+impl From<&SharedTerm> for LambdaTerm {
+    fn from(term: &SharedTerm) -> LambdaTerm {
+        term.to_lambda()
+    }
+}
+
+impl fmt::Display for SharedTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_lambda().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+
+    // A random term with the outer binders stripped off, so that it has
+    // free variables to substitute for and to be captured.
+    fn open_term(size: usize, rng: &mut SplitMix64) -> LambdaTerm {
+        let mut term = gen_closed_term(size, rng);
+        for _ in 0..2 {
+            if let LambdaTerm::Abstraction { return_term, .. } = term {
+                term = *return_term;
+            }
+        }
+        term
+    }
+
+    #[test]
+    fn substitutes_as_lambda_terms_do() {
+        let mut rng = SplitMix64(370);
+        for i in 0..3000 {
+            let term = open_term(3 + i % 30, &mut rng);
+            let replacement = open_term(3 + i % 7, &mut rng);
+            for variable in ["x0", "x1"] {
+                let expected = term.substitute(variable, &replacement);
+                let shared = term
+                    .to_shared()
+                    .substitute(variable, &replacement.to_shared());
+                assert!(
+                    shared.to_lambda().alpha_equivalent(&expected),
+                    "{}[{} := {}]",
+                    term,
+                    variable,
+                    replacement
+                );
+                assert_eq!(shared.free_variables(), expected.free_variables());
+            }
+        }
+    }
+
+    #[test]
+    fn shares_what_substitution_leaves_alone() {
+        let term = crate::parse("λx. (λy. y) (x z) (λz. z)")
+            .unwrap()
+            .to_shared();
+        let replacement = SharedTerm::variable("w");
+        assert!(term.substitute("v", &replacement).ptr_eq(&term));
+        let substituted = term.substitute("z", &replacement);
+        assert_eq!(substituted.to_string(), "λx. (λy. y) (x w) (λz. z)");
+        let parts = |term: &SharedTerm| match &**term {
+            SharedNode::Abstraction { return_term, .. } => match &**return_term {
+                SharedNode::Application { function, argument } => match &**function {
+                    SharedNode::Application { function, .. } => {
+                        (function.clone(), argument.clone())
+                    }
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        let (identity, constant) = parts(&term);
+        let (new_identity, new_constant) = parts(&substituted);
+        assert!(identity.ptr_eq(&new_identity));
+        assert!(constant.ptr_eq(&new_constant));
+    }
+}
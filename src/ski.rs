@@ -0,0 +1,485 @@
+use crate::rewrite::RewriteError;
+use crate::small::SmallName;
+use crate::{combinators, lambda, parse, Backticks, LambdaTerm, ParserError, Quoting, Token};
+use std::fmt;
+use std::str::FromStr;
+
+// Reads a combinatory logic expression such as `S K K x`, built from
+// applications alone, and expands the combinators named in
+// combinators::NAMES into their lambda terms. Every other name is kept as a
+// free variable.
+pub fn parse_ski(code: &str) -> Result<LambdaTerm, ParserError> {
+    fn expand(term: LambdaTerm) -> Result<LambdaTerm, ParserError> {
+        match term {
+            LambdaTerm::Variable(id) => {
+                Ok(combinators::by_name(&id).unwrap_or(LambdaTerm::Variable(id)))
+            }
+            LambdaTerm::Application { function, argument } => Ok(LambdaTerm::Application {
+                function: Box::new(expand(*function)?),
+                argument: Box::new(expand(*argument)?),
+            }),
+            LambdaTerm::Abstraction { .. } => Err(ParserError::Unexpected(Token::Lambda)),
+        }
+    }
+    expand(parse(code)?)
+}
+
+// Reads a combinatory logic term such as `S K (K x)`, the way Display writes
+// it: S, K, I, B, C, S', B' and C' are the combinators, every other name is
+// a variable, and there are no abstractions. A variable named after a
+// combinator reads back as the combinator, quoted or not.
+pub fn parse_cl(code: &str) -> Result<CLTerm, ParserError> {
+    fn convert(term: LambdaTerm) -> Result<CLTerm, ParserError> {
+        match term {
+            LambdaTerm::Variable(id) => Ok(CLTerm::combinator(&id).unwrap_or(CLTerm::Variable(id))),
+            LambdaTerm::Application { function, argument } => {
+                Ok(CLTerm::app(convert(*function)?, convert(*argument)?))
+            }
+            LambdaTerm::Abstraction { .. } => Err(ParserError::Unexpected(Token::Lambda)),
+        }
+    }
+    convert(parse(code)?)
+}
+
+impl FromStr for CLTerm {
+    type Err = ParserError;
+
+    fn from_str(code: &str) -> Result<CLTerm, ParserError> {
+        parse_cl(code)
+    }
+}
+
+// A term of combinatory logic, with free variables. to_ski only produces S,
+// K and I; the rest come from Turner's optimizations in to_turner.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CLTerm {
+    S,
+    K,
+    I,
+    B,
+    C,
+    // S' k f g x = k (f x) (g x)
+    SPrime,
+    // B' k f g x = k (f (g x)), the combinator Turner calls B*
+    BPrime,
+    // C' k f g x = k (f x) g
+    CPrime,
+    Variable(SmallName),
+    Application {
+        function: Box<CLTerm>,
+        argument: Box<CLTerm>,
+    },
+}
+
+impl CLTerm {
+    pub fn app(function: CLTerm, argument: CLTerm) -> CLTerm {
+        CLTerm::Application {
+            function: Box::new(function),
+            argument: Box::new(argument),
+        }
+    }
+
+    // Counts every combinator, variable and application.
+    pub fn size(&self) -> usize {
+        match self {
+            CLTerm::Application { function, argument } => 1 + function.size() + argument.size(),
+            _ => 1,
+        }
+    }
+
+    pub fn occurs(&self, id: &str) -> bool {
+        match self {
+            CLTerm::Variable(variable) => variable == id,
+            CLTerm::Application { function, argument } => {
+                function.occurs(id) || argument.occurs(id)
+            }
+            _ => false,
+        }
+    }
+
+    // The combinator a name stands for in parse_cl and Display.
+    pub fn combinator(name: &str) -> Option<CLTerm> {
+        match name {
+            "S" => Some(CLTerm::S),
+            "K" => Some(CLTerm::K),
+            "I" => Some(CLTerm::I),
+            "B" => Some(CLTerm::B),
+            "C" => Some(CLTerm::C),
+            "S'" => Some(CLTerm::SPrime),
+            "B'" => Some(CLTerm::BPrime),
+            "C'" => Some(CLTerm::CPrime),
+            _ => None,
+        }
+    }
+
+    // How many arguments the combinator needs before it reduces.
+    fn arity(&self) -> Option<usize> {
+        match self {
+            CLTerm::I => Some(1),
+            CLTerm::K => Some(2),
+            CLTerm::S | CLTerm::B | CLTerm::C => Some(3),
+            CLTerm::SPrime | CLTerm::BPrime | CLTerm::CPrime => Some(4),
+            CLTerm::Variable(_) | CLTerm::Application { .. } => None,
+        }
+    }
+
+    // One step of weak reduction, contracting the leftmost outermost redex.
+    // Unlike β-reduction this never looks inside a partially applied
+    // combinator, so S K is already in weak normal form.
+    pub fn step(&self) -> Option<CLTerm> {
+        let mut arguments = Vec::new();
+        let mut head = self;
+        while let CLTerm::Application { function, argument } = head {
+            arguments.push(&**argument);
+            head = function;
+        }
+        arguments.reverse();
+        let app = CLTerm::app;
+        match head.arity() {
+            Some(n) if n <= arguments.len() => {
+                let a: Vec<CLTerm> = arguments[..n].iter().map(|&a| a.clone()).collect();
+                let contracted = match (head, &a[..]) {
+                    (CLTerm::I, [x]) => x.clone(),
+                    (CLTerm::K, [x, _]) => x.clone(),
+                    (CLTerm::S, [x, y, z]) => {
+                        app(app(x.clone(), z.clone()), app(y.clone(), z.clone()))
+                    }
+                    (CLTerm::B, [x, y, z]) => app(x.clone(), app(y.clone(), z.clone())),
+                    (CLTerm::C, [x, y, z]) => app(app(x.clone(), z.clone()), y.clone()),
+                    (CLTerm::SPrime, [k, f, g, x]) => app(
+                        app(k.clone(), app(f.clone(), x.clone())),
+                        app(g.clone(), x.clone()),
+                    ),
+                    (CLTerm::BPrime, [k, f, g, x]) => {
+                        app(k.clone(), app(f.clone(), app(g.clone(), x.clone())))
+                    }
+                    (CLTerm::CPrime, [k, f, g, x]) => {
+                        app(app(k.clone(), app(f.clone(), x.clone())), g.clone())
+                    }
+                    _ => unreachable!("arity matches the number of arguments taken"),
+                };
+                Some(
+                    arguments[n..]
+                        .iter()
+                        .fold(contracted, |term, &argument| app(term, argument.clone())),
+                )
+            }
+            _ => (0..arguments.len()).find_map(|i| {
+                let reduced = arguments[i].step()?;
+                let rebuilt = arguments
+                    .iter()
+                    .enumerate()
+                    .fold(head.clone(), |term, (j, &a)| {
+                        app(term, if i == j { reduced.clone() } else { a.clone() })
+                    });
+                Some(rebuilt)
+            }),
+        }
+    }
+
+    // Weak reduction to weak normal form, giving up after `limit` steps.
+    pub fn normalize(&self, limit: usize) -> Result<CLTerm, RewriteError> {
+        let mut term = self.clone();
+        for _ in 0..limit {
+            match term.step() {
+                Some(next) => term = next,
+                None => return Ok(term),
+            }
+        }
+        match term.step() {
+            Some(_) => Err(RewriteError::StepLimit(limit)),
+            None => Ok(term),
+        }
+    }
+
+    // Replaces each combinator by its lambda term. The combinators are closed,
+    // so nothing can be captured.
+    pub fn to_lambda(&self) -> LambdaTerm {
+        match self {
+            CLTerm::S => combinators::s(),
+            CLTerm::K => combinators::k(),
+            CLTerm::I => combinators::i(),
+            CLTerm::B => combinators::b(),
+            CLTerm::C => combinators::c(),
+            CLTerm::SPrime => lambda!(|k, f, g, x| k (f x) (g x)),
+            CLTerm::BPrime => lambda!(|k, f, g, x| k (f (g x))),
+            CLTerm::CPrime => lambda!(|k, f, g, x| k (f x) g),
+            CLTerm::Variable(id) => LambdaTerm::Variable(id.clone()),
+            CLTerm::Application { function, argument } => LambdaTerm::Application {
+                function: Box::new(function.to_lambda()),
+                argument: Box::new(argument.to_lambda()),
+            },
+        }
+    }
+}
+
+// [x] M, a combinator term that behaves like λx. M.
+fn bracket(id: &str, term: CLTerm) -> CLTerm {
+    if !term.occurs(id) {
+        return CLTerm::app(CLTerm::K, term);
+    }
+    match term {
+        CLTerm::Variable(_) => CLTerm::I,
+        CLTerm::Application { function, argument } => match *argument {
+            // [x] (M x) = M, when x isn't free in M.
+            CLTerm::Variable(ref variable) if variable == id && !function.occurs(id) => *function,
+            argument => CLTerm::app(
+                CLTerm::app(CLTerm::S, bracket(id, *function)),
+                bracket(id, argument),
+            ),
+        },
+        // Combinators don't mention x, so were handled above.
+        term => CLTerm::app(CLTerm::K, term),
+    }
+}
+
+// Bracket abstraction, innermost abstractions first. The result is
+// extensionally equal to `term`: applied to the same arguments, both reduce
+// to the same normal form, if either has one.
+pub fn to_ski(term: &LambdaTerm) -> CLTerm {
+    match term {
+        LambdaTerm::Variable(id) => CLTerm::Variable(id.clone()),
+        LambdaTerm::Application { function, argument } => {
+            CLTerm::app(to_ski(function), to_ski(argument))
+        }
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => bracket(bound_variable, to_ski(return_term)),
+    }
+}
+
+// S p q, simplified by Turner's rules, where p and q are already
+// abstracted over the same variable:
+//
+//   S (K p) (K q)     = K (p q)
+//   S (K p) I         = p
+//   S (K p) (B q r)   = B' p q r
+//   S (K p) q         = B p q
+//   S (B p q) (K r)   = C' p q r
+//   S p (K q)         = C p q
+//   S (B p q) r       = S' p q r
+fn turner_s(p: CLTerm, q: CLTerm) -> CLTerm {
+    fn applied_to(term: &CLTerm, combinator: CLTerm) -> Option<&CLTerm> {
+        match term {
+            CLTerm::Application { function, argument } if **function == combinator => {
+                Some(argument)
+            }
+            _ => None,
+        }
+    }
+    fn b_parts(term: &CLTerm) -> Option<(&CLTerm, &CLTerm)> {
+        match term {
+            CLTerm::Application { function, argument } => {
+                applied_to(function, CLTerm::B).map(|first| (first, &**argument))
+            }
+            _ => None,
+        }
+    }
+    let app = CLTerm::app;
+    if let Some(p) = applied_to(&p, CLTerm::K) {
+        if let Some(q) = applied_to(&q, CLTerm::K) {
+            return app(CLTerm::K, app(p.clone(), q.clone()));
+        }
+        if q == CLTerm::I {
+            return p.clone();
+        }
+        if let Some((q, r)) = b_parts(&q) {
+            return app(app(app(CLTerm::BPrime, p.clone()), q.clone()), r.clone());
+        }
+        return app(app(CLTerm::B, p.clone()), q);
+    }
+    if let Some(r) = applied_to(&q, CLTerm::K) {
+        if let Some((p, q)) = b_parts(&p) {
+            return app(app(app(CLTerm::CPrime, p.clone()), q.clone()), r.clone());
+        }
+        return app(app(CLTerm::C, p), r.clone());
+    }
+    if let Some((first, second)) = b_parts(&p) {
+        return app(app(app(CLTerm::SPrime, first.clone()), second.clone()), q);
+    }
+    app(app(CLTerm::S, p), q)
+}
+
+// [x] M as bracket gives it, with every S built by turner_s instead.
+fn turner_bracket(id: &str, term: CLTerm) -> CLTerm {
+    if !term.occurs(id) {
+        return CLTerm::app(CLTerm::K, term);
+    }
+    match term {
+        CLTerm::Variable(_) => CLTerm::I,
+        CLTerm::Application { function, argument } => match *argument {
+            CLTerm::Variable(ref variable) if variable == id && !function.occurs(id) => *function,
+            argument => turner_s(turner_bracket(id, *function), turner_bracket(id, argument)),
+        },
+        term => CLTerm::app(CLTerm::K, term),
+    }
+}
+
+// Turner's algorithm, which adds B, C, S', B' and C' to bracket abstraction.
+// Naive bracket abstraction can grow a term exponentially in the number of
+// nested binders; with these rules the growth is much closer to linear.
+pub fn to_turner(term: &LambdaTerm) -> CLTerm {
+    match term {
+        LambdaTerm::Variable(id) => CLTerm::Variable(id.clone()),
+        LambdaTerm::Application { function, argument } => {
+            CLTerm::app(to_turner(function), to_turner(argument))
+        }
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => turner_bracket(bound_variable, to_turner(return_term)),
+    }
+}
+
+// The sizes of a term and of its translations by to_ski and to_turner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeReport {
+    pub lambda: usize,
+    pub ski: usize,
+    pub turner: usize,
+}
+
+pub fn size_report(term: &LambdaTerm) -> SizeReport {
+    SizeReport {
+        lambda: term.size(),
+        ski: to_ski(term).size(),
+        turner: to_turner(term).size(),
+    }
+}
+
+impl fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lambda {}, SKI {}, Turner {}",
+            self.lambda, self.ski, self.turner
+        )
+    }
+}
+
+impl From<&CLTerm> for LambdaTerm {
+    fn from(term: &CLTerm) -> LambdaTerm {
+        term.to_lambda()
+    }
+}
+
+impl From<&LambdaTerm> for CLTerm {
+    fn from(term: &LambdaTerm) -> CLTerm {
+        to_ski(term)
+    }
+}
+
+impl fmt::Display for CLTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CLTerm::S => f.write_str("S"),
+            CLTerm::K => f.write_str("K"),
+            CLTerm::I => f.write_str("I"),
+            CLTerm::B => f.write_str("B"),
+            CLTerm::C => f.write_str("C"),
+            CLTerm::SPrime => f.write_str("S'"),
+            CLTerm::BPrime => f.write_str("B'"),
+            CLTerm::CPrime => f.write_str("C'"),
+            CLTerm::Variable(id) => Backticks.write_identifier(f, id),
+            CLTerm::Application { function, argument } => match **argument {
+                CLTerm::Application { .. } => write!(f, "{} ({})", function, argument),
+                _ => write!(f, "{} {}", function, argument),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{gen_closed_term, SplitMix64};
+    use crate::rewrite::{Beta, Eta, Rewriter, Strategy};
+    use crate::{apps, var};
+
+    #[test]
+    fn parses_what_display_prints() {
+        for code in [
+            "S K K",
+            "S' (B' C') (K x) I",
+            "B (C y) (S (K I))",
+            "`a b` S",
+        ] {
+            let term = parse_cl(code).unwrap();
+            assert_eq!(parse_cl(&term.to_string()).unwrap(), term);
+        }
+        assert_eq!(
+            "S' k f g".parse::<CLTerm>().unwrap(),
+            apps_cl(CLTerm::SPrime, ["k", "f", "g"])
+        );
+        assert!(parse_cl("λx. x").is_err());
+    }
+
+    fn apps_cl<const N: usize>(head: CLTerm, arguments: [&str; N]) -> CLTerm {
+        arguments.into_iter().fold(head, |function, id| {
+            CLTerm::app(function, CLTerm::Variable(SmallName::from(id)))
+        })
+    }
+
+    #[test]
+    fn reduces_each_combinator_by_its_rule() {
+        let cases = [
+            ("S x y z", "x z (y z)"),
+            ("K x y", "x"),
+            ("I x", "x"),
+            ("B x y z", "x (y z)"),
+            ("C x y z", "x z y"),
+            ("S' k f g x", "k (f x) (g x)"),
+            ("B' k f g x", "k (f (g x))"),
+            ("C' k f g x", "k (f x) g"),
+            ("S K K x", "x"),
+            ("S K", "S K"),
+        ];
+        for (code, expected) in cases {
+            let normal = parse_cl(code).unwrap().normalize(100).unwrap();
+            assert_eq!(normal, parse_cl(expected).unwrap(), "{}", code);
+        }
+        let omega = parse_cl("S I I (S I I)").unwrap();
+        assert!(matches!(
+            omega.normalize(50),
+            Err(RewriteError::StepLimit(50))
+        ));
+    }
+
+    // Applies each translation of a random closed term to free arguments
+    // and checks that weak reduction, read back and normalized, agrees with
+    // the source's βη-normal form. Pairs that run out of steps are skipped.
+    fn reduces_like_its_source(translate: fn(&LambdaTerm) -> CLTerm, seed: u64) -> usize {
+        let rewriter = Rewriter::new(Strategy::TopDown, 500).rule(Beta).rule(Eta);
+        let mut rng = SplitMix64(seed);
+        let mut compared = 0;
+        for i in 0..1000 {
+            let term = gen_closed_term(2 + i % 14, &mut rng);
+            let arguments = || ["a", "b", "c", "d"].map(var);
+            let Ok(expected) = rewriter.normalize(&apps(term.clone(), arguments())) else {
+                continue;
+            };
+            let translated = arguments()
+                .iter()
+                .fold(translate(&term), |function, argument| {
+                    CLTerm::app(function, CLTerm::from(argument))
+                });
+            if let Ok(weak) = translated.normalize(500) {
+                let reduced = rewriter.normalize(&weak.to_lambda()).unwrap();
+                assert!(reduced.alpha_equivalent(&expected), "{}", term);
+                compared += 1;
+            }
+        }
+        compared
+    }
+
+    #[test]
+    fn turner_translations_reduce_like_their_sources() {
+        assert!(reduces_like_its_source(to_turner, 378) > 500);
+    }
+
+    #[test]
+    fn ski_translations_reduce_like_their_sources() {
+        assert!(reduces_like_its_source(to_ski, 340) > 500);
+    }
+}
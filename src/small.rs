@@ -0,0 +1,213 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+// The longest name kept inline; with the length byte and the tag this makes
+// a SmallName the same size as a String.
+const INLINE_CAPACITY: usize = 22;
+
+#[derive(Clone, PartialEq, Eq)]
+enum Repr {
+    // Bytes past `len` are always zero, so equal names have equal
+    // representations.
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+// An identifier that stores names of up to 22 bytes in place, so that the
+// one to three letter names most terms are made of never touch the heap.
+// Cloning one of those is a copy, where cloning a String allocates.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SmallName(Repr);
+
+impl SmallName {
+    pub fn new(name: &str) -> SmallName {
+        if name.len() <= INLINE_CAPACITY {
+            let mut bytes = [0; INLINE_CAPACITY];
+            bytes[..name.len()].copy_from_slice(name.as_bytes());
+            SmallName(Repr::Inline {
+                len: name.len() as u8,
+                bytes,
+            })
+        } else {
+            SmallName(Repr::Heap(Box::from(name)))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Inline { len, bytes } => {
+                std::str::from_utf8(&bytes[..*len as usize]).expect("inline names are UTF-8")
+            }
+            Repr::Heap(name) => name,
+        }
+    }
+
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+impl Deref for SmallName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Hashed and ordered as the name itself, whichever way it's stored.
+impl Hash for SmallName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for SmallName {
+    fn partial_cmp(&self, other: &SmallName) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallName {
+    fn cmp(&self, other: &SmallName) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Default for SmallName {
+    fn default() -> SmallName {
+        SmallName::new("")
+    }
+}
+
+// Lets sets and maps keyed by names be looked up with a &str.
+impl Borrow<str> for SmallName {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallName {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for SmallName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for SmallName {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<SmallName> for str {
+    fn eq(&self, other: &SmallName) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<SmallName> for &str {
+    fn eq(&self, other: &SmallName) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<SmallName> for String {
+    fn eq(&self, other: &SmallName) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl From<&str> for SmallName {
+    fn from(name: &str) -> SmallName {
+        SmallName::new(name)
+    }
+}
+
+impl From<&String> for SmallName {
+    fn from(name: &String) -> SmallName {
+        SmallName::new(name)
+    }
+}
+
+impl From<String> for SmallName {
+    fn from(name: String) -> SmallName {
+        SmallName::new(&name)
+    }
+}
+
+impl From<&SmallName> for SmallName {
+    fn from(name: &SmallName) -> SmallName {
+        name.clone()
+    }
+}
+
+impl From<SmallName> for String {
+    fn from(name: SmallName) -> String {
+        String::from(name.as_str())
+    }
+}
+
+impl From<&SmallName> for String {
+    fn from(name: &SmallName) -> String {
+        String::from(name.as_str())
+    }
+}
+
+// Written as a plain string, as a String name would be.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SmallName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SmallName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<SmallName, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = SmallName;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a variable name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<SmallName, E> {
+                Ok(SmallName::new(name))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+impl fmt::Debug for SmallName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for SmallName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,117 @@
+use crate::print::PrintOptions;
+use crate::repl::Outcome;
+use crate::LambdaTerm;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+  <n>      contract redex n
+  (empty)  contract redex 1, the one normal order picks
+  u, undo  go back to the term before the last contraction
+  q, quit  stop stepping";
+
+// Reduces a term one redex at a time, with the user choosing which redex to
+// contract each time. Every term passed through is kept so that steps can
+// be undone.
+pub struct Stepper {
+    pub print_options: PrintOptions,
+    history: Vec<LambdaTerm>,
+}
+
+impl Stepper {
+    pub fn new(term: LambdaTerm) -> Stepper {
+        Stepper {
+            print_options: PrintOptions::default(),
+            history: vec![term],
+        }
+    }
+
+    pub fn current(&self) -> &LambdaTerm {
+        self.history.last().unwrap()
+    }
+
+    pub fn steps(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    // The current term, and below it each of its redexes with the number
+    // that picks it.
+    pub fn show(&self) -> String {
+        let term = self.current();
+        let mut text = format!(
+            "[{}] {}",
+            self.steps(),
+            term.display_with(&self.print_options)
+        );
+        let redexes = term.redexes();
+        if redexes.is_empty() {
+            text.push_str("\n  normal form");
+        }
+        for (i, path) in redexes.iter().enumerate() {
+            let redex = term.get(path).unwrap();
+            text.push_str(&format!(
+                "\n  {}: {}",
+                i + 1,
+                redex.display_with(&self.print_options)
+            ));
+        }
+        text
+    }
+
+    pub fn contract(&mut self, n: usize) -> Result<(), String> {
+        let redexes = self.current().redexes();
+        let path = match n.checked_sub(1).and_then(|i| redexes.get(i)) {
+            Some(path) => path,
+            None if redexes.is_empty() => return Err(String::from("no redexes left")),
+            None => return Err(format!("pick a redex from 1 to {}", redexes.len())),
+        };
+        let next = self.current().contract(path).unwrap();
+        self.history.push(next);
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<(), String> {
+        if self.history.len() == 1 {
+            return Err(String::from("nothing to undo"));
+        }
+        self.history.pop();
+        Ok(())
+    }
+
+    pub fn execute(&mut self, line: &str) -> Outcome {
+        let result = match line.trim() {
+            "q" | "quit" => return Outcome::Quit,
+            "?" | "h" | "help" => return Outcome::Output(String::from(HELP)),
+            "u" | "undo" => self.undo(),
+            "" => self.contract(1),
+            choice => match choice.parse() {
+                Ok(n) => self.contract(n),
+                Err(_) => Err(format!("unknown command {} (try help)", choice)),
+            },
+        };
+        match result {
+            Ok(()) => Outcome::Output(self.show()),
+            Err(message) => Outcome::Output(message),
+        }
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        writeln!(output, "{}", self.show())?;
+        let mut lines = input.lines();
+        loop {
+            write!(output, "step> ")?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => {
+                    writeln!(output)?;
+                    break;
+                }
+            };
+            match self.execute(&line) {
+                Outcome::Output(text) => writeln!(output, "{}", text)?,
+                Outcome::Quit => break,
+            }
+        }
+        Ok(())
+    }
+}
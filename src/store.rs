@@ -0,0 +1,245 @@
+use crate::symbol::Symbol;
+use crate::{parse, DBIndices, DBTerm, LambdaTerm, NameScheme, ParserError};
+
+// Refers to a node by its position in a TermStore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TermId(pub usize);
+
+// Bound variables are de Bruijn indices, as in DBTerm, so reduction never
+// has to rename anything. Free variables are interned, so a node is small
+// and copying one allocates nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Node {
+    Variable(usize),
+    FreeVariable(Symbol),
+    Abstraction(TermId),
+    Application(TermId, TermId),
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    StepLimit(usize),
+}
+
+// Keeps every node of its terms side by side in one Vec. Nodes are never
+// changed once added, so terms can share subterms freely: reduction only adds
+// the nodes that differ from the term it started with, and hands back the
+// same id for anything left untouched. Nothing is freed until the store is
+// cleared or dropped.
+#[derive(Debug, Clone, Default)]
+pub struct TermStore {
+    nodes: Vec<Node>,
+}
+
+impl TermStore {
+    pub fn new() -> TermStore {
+        TermStore::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> TermStore {
+        TermStore {
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    pub fn get(&self, id: TermId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    pub fn add(&mut self, node: Node) -> TermId {
+        self.nodes.push(node);
+        TermId(self.nodes.len() - 1)
+    }
+
+    pub fn insert(&mut self, term: &LambdaTerm) -> TermId {
+        fn insert_mut<'a>(
+            store: &mut TermStore,
+            term: &'a LambdaTerm,
+            scope: &mut Vec<&'a str>,
+        ) -> TermId {
+            let node = match term {
+                LambdaTerm::Variable(id) => match scope.iter().rposition(|bound| bound == id) {
+                    Some(position) => Node::Variable(scope.len() - position),
+                    None => Node::FreeVariable(Symbol::intern(id)),
+                },
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    scope.push(bound_variable);
+                    let return_term = insert_mut(store, return_term, scope);
+                    scope.pop();
+                    Node::Abstraction(return_term)
+                }
+                LambdaTerm::Application { function, argument } => {
+                    let function = insert_mut(store, function, scope);
+                    let argument = insert_mut(store, argument, scope);
+                    Node::Application(function, argument)
+                }
+            };
+            store.add(node)
+        }
+        insert_mut(self, term, &mut Vec::new())
+    }
+
+    pub fn insert_indices(&mut self, indices: &DBIndices) -> TermId {
+        fn insert_mut(store: &mut TermStore, term: &DBTerm) -> TermId {
+            let node = match term {
+                DBTerm::Variable(index) => Node::Variable(*index),
+                DBTerm::FreeVariable(id) => Node::FreeVariable(Symbol::intern(id)),
+                DBTerm::Abstraction(return_term) => {
+                    Node::Abstraction(insert_mut(store, return_term))
+                }
+                DBTerm::Application { function, argument } => {
+                    let function = insert_mut(store, function);
+                    let argument = insert_mut(store, argument);
+                    Node::Application(function, argument)
+                }
+            };
+            store.add(node)
+        }
+        let DBIndices(term) = indices;
+        insert_mut(self, term)
+    }
+
+    pub fn parse(&mut self, code: &str) -> Result<TermId, ParserError> {
+        Ok(self.insert(&parse(code)?))
+    }
+
+    pub fn to_indices(&self, id: TermId) -> DBIndices {
+        fn extract(store: &TermStore, id: TermId) -> DBTerm {
+            match store.get(id) {
+                Node::Variable(index) => DBTerm::Variable(*index),
+                Node::FreeVariable(id) => DBTerm::FreeVariable(String::from(*id)),
+                Node::Abstraction(return_term) => {
+                    DBTerm::Abstraction(Box::new(extract(store, *return_term)))
+                }
+                Node::Application(function, argument) => DBTerm::Application {
+                    function: Box::new(extract(store, *function)),
+                    argument: Box::new(extract(store, *argument)),
+                },
+            }
+        }
+        DBIndices(extract(self, id))
+    }
+
+    pub fn to_named(&self, id: TermId, scheme: NameScheme) -> LambdaTerm {
+        self.to_indices(id).to_named(scheme)
+    }
+
+    // Raises the indices in `id` that reach past `cutoff` enclosing binders
+    // by `amount`.
+    fn shift(&mut self, id: TermId, amount: usize, cutoff: usize) -> TermId {
+        match *self.get(id) {
+            Node::Variable(index) if index > cutoff => self.add(Node::Variable(index + amount)),
+            Node::Variable(_) | Node::FreeVariable(_) => id,
+            Node::Abstraction(return_term) => {
+                let shifted = self.shift(return_term, amount, cutoff + 1);
+                self.rebuild_abstraction(id, return_term, shifted)
+            }
+            Node::Application(function, argument) => {
+                let new_function = self.shift(function, amount, cutoff);
+                let new_argument = self.shift(argument, amount, cutoff);
+                self.rebuild_application(id, (function, argument), (new_function, new_argument))
+            }
+        }
+    }
+
+    // Puts `argument` in place of the variable bound just outside `body`,
+    // which is `abstraction_depth` binders further in than that abstraction,
+    // and lowers the indices that pointed past it.
+    fn instantiate(&mut self, body: TermId, argument: TermId, abstraction_depth: usize) -> TermId {
+        match *self.get(body) {
+            Node::Variable(index) if index == abstraction_depth + 1 => {
+                self.shift(argument, abstraction_depth, 0)
+            }
+            Node::Variable(index) if index > abstraction_depth + 1 => {
+                self.add(Node::Variable(index - 1))
+            }
+            Node::Variable(_) | Node::FreeVariable(_) => body,
+            Node::Abstraction(return_term) => {
+                let instantiated = self.instantiate(return_term, argument, abstraction_depth + 1);
+                self.rebuild_abstraction(body, return_term, instantiated)
+            }
+            Node::Application(function, argument_term) => {
+                let new_function = self.instantiate(function, argument, abstraction_depth);
+                let new_argument = self.instantiate(argument_term, argument, abstraction_depth);
+                self.rebuild_application(
+                    body,
+                    (function, argument_term),
+                    (new_function, new_argument),
+                )
+            }
+        }
+    }
+
+    fn rebuild_abstraction(&mut self, id: TermId, old: TermId, new: TermId) -> TermId {
+        if old == new {
+            id
+        } else {
+            self.add(Node::Abstraction(new))
+        }
+    }
+
+    fn rebuild_application(
+        &mut self,
+        id: TermId,
+        old: (TermId, TermId),
+        new: (TermId, TermId),
+    ) -> TermId {
+        if old == new {
+            id
+        } else {
+            self.add(Node::Application(new.0, new.1))
+        }
+    }
+
+    // Contracts the leftmost outermost redex, giving None for a normal form.
+    pub fn step(&mut self, id: TermId) -> Option<TermId> {
+        match *self.get(id) {
+            Node::Variable(_) | Node::FreeVariable(_) => None,
+            Node::Abstraction(return_term) => {
+                let reduced = self.step(return_term)?;
+                Some(self.add(Node::Abstraction(reduced)))
+            }
+            Node::Application(function, argument) => {
+                if let Node::Abstraction(body) = *self.get(function) {
+                    return Some(self.instantiate(body, argument, 0));
+                }
+                if let Some(function) = self.step(function) {
+                    return Some(self.add(Node::Application(function, argument)));
+                }
+                let argument = self.step(argument)?;
+                Some(self.add(Node::Application(function, argument)))
+            }
+        }
+    }
+
+    // Reduces in normal order until no redex is left, or gives up after
+    // `limit` steps.
+    pub fn normalize(&mut self, id: TermId, limit: usize) -> Result<TermId, StoreError> {
+        let mut id = id;
+        for _ in 0..limit {
+            match self.step(id) {
+                Some(reduced) => id = reduced,
+                None => return Ok(id),
+            }
+        }
+        match self.step(id) {
+            Some(_) => Err(StoreError::StepLimit(limit)),
+            None => Ok(id),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::small::SmallName;
+use crate::{LambdaTerm, Term};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+// An interned identifier. Symbols for equal strings are equal, so comparing
+// or hashing one costs as much as a u32, and copying one allocates nothing.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+// Interned strings live for the rest of the program, which suits the small
+// and repetitive set of names found in lambda terms.
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+}
+
+// Reading a name back only takes the lock shared, so threads resolving
+// symbols don't wait on each other.
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(RwLock::default)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        let known = interner()
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .symbols
+            .get(name)
+            .copied();
+        if let Some(symbol) = known {
+            return symbol;
+        }
+        let mut interner = interner().write().unwrap_or_else(|err| err.into_inner());
+        // Another thread may have interned it in between.
+        if let Some(&symbol) = interner.symbols.get(name) {
+            return symbol;
+        }
+        let name: &'static str = Box::leak(Box::from(name));
+        let symbol = Symbol(interner.names.len() as u32);
+        interner.names.push(name);
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        let interner = interner().read().unwrap_or_else(|err| err.into_inner());
+        interner.names[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Symbol {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Symbol {
+        Symbol::intern(&name)
+    }
+}
+
+impl From<Symbol> for &'static str {
+    fn from(symbol: Symbol) -> &'static str {
+        symbol.as_str()
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> String {
+        String::from(symbol.as_str())
+    }
+}
+
+impl From<&SmallName> for Symbol {
+    fn from(name: &SmallName) -> Symbol {
+        Symbol::intern(name)
+    }
+}
+
+impl From<Symbol> for SmallName {
+    fn from(symbol: Symbol) -> SmallName {
+        SmallName::new(symbol.as_str())
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({:?})", self.as_str())
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// A term whose variables are interned, for work that compares names often.
+// Interning is opt in: LambdaTerm keeps SmallNames, which read back without
+// the interner's lock, and intern and resolve convert between the two.
+pub type SymbolTerm = Term<Symbol>;
+
+impl LambdaTerm {
+    pub fn intern(&self) -> SymbolTerm {
+        self.map_variables(|id| Symbol::intern(id))
+    }
+
+    // free_variables, with each name interned rather than copied, and
+    // without building an interned copy of the term first.
+    pub fn free_symbols(&self) -> HashSet<Symbol> {
+        let mut set = HashSet::new();
+        self.for_each_free_variable(|id| {
+            set.insert(Symbol::intern(id));
+        });
+        set
+    }
+}
+
+impl SymbolTerm {
+    pub fn resolve(&self) -> LambdaTerm {
+        self.map_variables(|&symbol| SmallName::from(symbol))
+    }
+}
+
+impl From<&LambdaTerm> for SymbolTerm {
+    fn from(term: &LambdaTerm) -> SymbolTerm {
+        term.intern()
+    }
+}
+
+impl From<&SymbolTerm> for LambdaTerm {
+    fn from(term: &SymbolTerm) -> LambdaTerm {
+        term.resolve()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{app, lam, var};
+
+    #[test]
+    fn equal_names_intern_to_equal_symbols() {
+        assert_eq!(Symbol::intern("x"), Symbol::from(String::from("x")));
+        assert_ne!(Symbol::intern("x"), Symbol::intern("y"));
+        assert_eq!(Symbol::intern("λ-name").as_str(), "λ-name");
+        let name = SmallName::new("z");
+        assert_eq!(SmallName::from(Symbol::from(&name)), name);
+    }
+
+    #[test]
+    fn terms_round_trip_through_interning() {
+        let term = app(lam("x", app(var("x"), var("y"))), var("y"));
+        assert_eq!(term.intern().resolve(), term);
+        assert_eq!(term.free_symbols(), HashSet::from([Symbol::intern("y")]));
+    }
+
+    #[test]
+    fn interning_from_many_threads_agrees() {
+        let symbols: Vec<Symbol> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| Symbol::intern("shared-across-threads")))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        assert!(symbols.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+}
@@ -0,0 +1,907 @@
+use crate::names::NameSupply;
+use crate::store::TermStore;
+use crate::{is_identifier_continue, is_identifier_start, LambdaTerm, NameScheme};
+use std::collections::HashSet;
+use std::fmt;
+
+// Types of System F. A type variable that no ∀ binds is treated as a base
+// type, so `λx: o. x` checks with type `o → o`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Variable(String),
+    Function {
+        domain: Box<Type>,
+        codomain: Box<Type>,
+    },
+    Forall {
+        type_variable: String,
+        body: Box<Type>,
+    },
+}
+
+// Terms of System F, written `λx: T. M`, `Λα. M` and `M [T]` for type
+// abstraction and application, with `\`, `/\`, `forall` and `->` accepted in
+// place of `λ`, `Λ`, `∀` and `→`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SystemFTerm {
+    Variable(String),
+    Abstraction {
+        bound_variable: String,
+        variable_type: Type,
+        return_term: Box<SystemFTerm>,
+    },
+    Application {
+        function: Box<SystemFTerm>,
+        argument: Box<SystemFTerm>,
+    },
+    TypeAbstraction {
+        type_variable: String,
+        return_term: Box<SystemFTerm>,
+    },
+    TypeApplication {
+        function: Box<SystemFTerm>,
+        argument: Type,
+    },
+}
+
+impl Type {
+    pub fn function(domain: Type, codomain: Type) -> Type {
+        Type::Function {
+            domain: Box::new(domain),
+            codomain: Box::new(codomain),
+        }
+    }
+
+    pub fn forall(type_variable: impl Into<String>, body: Type) -> Type {
+        Type::Forall {
+            type_variable: type_variable.into(),
+            body: Box::new(body),
+        }
+    }
+
+    pub fn free_type_variables(&self) -> HashSet<String> {
+        match self {
+            Type::Variable(id) => HashSet::from([id.clone()]),
+            Type::Function { domain, codomain } => {
+                let mut set = domain.free_type_variables();
+                set.extend(codomain.free_type_variables());
+                set
+            }
+            Type::Forall {
+                type_variable,
+                body,
+            } => {
+                let mut set = body.free_type_variables();
+                set.remove(type_variable);
+                set
+            }
+        }
+    }
+
+    // Replaces the free occurrences of `variable`, renaming any ∀ that would
+    // capture a free variable of `replacement`.
+    pub fn substitute(&self, variable: &str, replacement: &Type) -> Type {
+        match self {
+            Type::Variable(id) if id == variable => replacement.clone(),
+            Type::Variable(_) => self.clone(),
+            Type::Function { domain, codomain } => Type::function(
+                domain.substitute(variable, replacement),
+                codomain.substitute(variable, replacement),
+            ),
+            Type::Forall { type_variable, .. } if type_variable == variable => self.clone(),
+            Type::Forall {
+                type_variable,
+                body,
+            } => {
+                let replacement_free = replacement.free_type_variables();
+                if replacement_free.contains(type_variable) {
+                    let mut names = NameSupply::new(
+                        body.free_type_variables()
+                            .into_iter()
+                            .chain(replacement_free),
+                    );
+                    names.avoid(variable);
+                    let fresh = String::from(names.fresh(type_variable));
+                    let renamed = body.substitute(type_variable, &Type::Variable(fresh.clone()));
+                    Type::forall(fresh, renamed.substitute(variable, replacement))
+                } else {
+                    Type::forall(type_variable, body.substitute(variable, replacement))
+                }
+            }
+        }
+    }
+
+    pub fn alpha_equivalent(&self, other: &Type) -> bool {
+        fn alpha_equivalent_mut<'a>(
+            left: &'a Type,
+            right: &'a Type,
+            binders: &mut Vec<(&'a str, &'a str)>,
+        ) -> bool {
+            match (left, right) {
+                (Type::Variable(left_id), Type::Variable(right_id)) => {
+                    let left_binder = binders.iter().rposition(|(id, _)| id == left_id);
+                    let right_binder = binders.iter().rposition(|(_, id)| id == right_id);
+                    match (left_binder, right_binder) {
+                        (None, None) => left_id == right_id,
+                        (left_binder, right_binder) => left_binder == right_binder,
+                    }
+                }
+                (
+                    Type::Function {
+                        domain: left_domain,
+                        codomain: left_codomain,
+                    },
+                    Type::Function {
+                        domain: right_domain,
+                        codomain: right_codomain,
+                    },
+                ) => {
+                    alpha_equivalent_mut(left_domain, right_domain, binders)
+                        && alpha_equivalent_mut(left_codomain, right_codomain, binders)
+                }
+                (
+                    Type::Forall {
+                        type_variable: left_variable,
+                        body: left_body,
+                    },
+                    Type::Forall {
+                        type_variable: right_variable,
+                        body: right_body,
+                    },
+                ) => {
+                    binders.push((left_variable, right_variable));
+                    let equivalent = alpha_equivalent_mut(left_body, right_body, binders);
+                    binders.pop();
+                    equivalent
+                }
+                _ => false,
+            }
+        }
+        alpha_equivalent_mut(self, other, &mut Vec::new())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeError {
+    UnboundVariable(String),
+    // Applied something whose type isn't a function type.
+    NotAFunction(Type),
+    // Applied something to a type whose type isn't a ∀.
+    NotPolymorphic(Type),
+    Mismatch { expected: Type, found: Type },
+}
+
+impl SystemFTerm {
+    // The free type variables of the annotations, outside any Λ binding them.
+    pub fn free_type_variables(&self) -> HashSet<String> {
+        match self {
+            SystemFTerm::Variable(_) => HashSet::new(),
+            SystemFTerm::Abstraction {
+                variable_type,
+                return_term,
+                ..
+            } => {
+                let mut set = variable_type.free_type_variables();
+                set.extend(return_term.free_type_variables());
+                set
+            }
+            SystemFTerm::Application { function, argument } => {
+                let mut set = function.free_type_variables();
+                set.extend(argument.free_type_variables());
+                set
+            }
+            SystemFTerm::TypeAbstraction {
+                type_variable,
+                return_term,
+            } => {
+                let mut set = return_term.free_type_variables();
+                set.remove(type_variable);
+                set
+            }
+            SystemFTerm::TypeApplication { function, argument } => {
+                let mut set = function.free_type_variables();
+                set.extend(argument.free_type_variables());
+                set
+            }
+        }
+    }
+
+    // Replaces a free type variable throughout the term's annotations,
+    // renaming any Λ that would capture a free variable of `replacement`.
+    pub fn substitute_type(&self, variable: &str, replacement: &Type) -> SystemFTerm {
+        match self {
+            SystemFTerm::Variable(_) => self.clone(),
+            SystemFTerm::Abstraction {
+                bound_variable,
+                variable_type,
+                return_term,
+            } => SystemFTerm::Abstraction {
+                bound_variable: bound_variable.clone(),
+                variable_type: variable_type.substitute(variable, replacement),
+                return_term: Box::new(return_term.substitute_type(variable, replacement)),
+            },
+            SystemFTerm::Application { function, argument } => SystemFTerm::Application {
+                function: Box::new(function.substitute_type(variable, replacement)),
+                argument: Box::new(argument.substitute_type(variable, replacement)),
+            },
+            SystemFTerm::TypeAbstraction { type_variable, .. } if type_variable == variable => {
+                self.clone()
+            }
+            SystemFTerm::TypeAbstraction {
+                type_variable,
+                return_term,
+            } => {
+                let replacement_free = replacement.free_type_variables();
+                let (type_variable, return_term) = if replacement_free.contains(type_variable) {
+                    let mut names = NameSupply::new(
+                        return_term
+                            .free_type_variables()
+                            .into_iter()
+                            .chain(replacement_free),
+                    );
+                    names.avoid(variable);
+                    let fresh = String::from(names.fresh(type_variable));
+                    let renamed =
+                        return_term.substitute_type(type_variable, &Type::Variable(fresh.clone()));
+                    (fresh, renamed)
+                } else {
+                    (type_variable.clone(), (**return_term).clone())
+                };
+                SystemFTerm::TypeAbstraction {
+                    type_variable,
+                    return_term: Box::new(return_term.substitute_type(variable, replacement)),
+                }
+            }
+            SystemFTerm::TypeApplication { function, argument } => SystemFTerm::TypeApplication {
+                function: Box::new(function.substitute_type(variable, replacement)),
+                argument: argument.substitute(variable, replacement),
+            },
+        }
+    }
+
+    pub fn type_of(&self) -> Result<Type, TypeError> {
+        self.type_in(&mut Vec::new())
+    }
+
+    pub fn check(&self, expected: &Type) -> Result<(), TypeError> {
+        let found = self.type_of()?;
+        if found.alpha_equivalent(expected) {
+            Ok(())
+        } else {
+            Err(TypeError::Mismatch {
+                expected: expected.clone(),
+                found,
+            })
+        }
+    }
+
+    // `context` holds the types of the enclosing λ binders, innermost last.
+    fn type_in(&self, context: &mut Vec<(String, Type)>) -> Result<Type, TypeError> {
+        match self {
+            SystemFTerm::Variable(id) => {
+                match context.iter().rev().find(|(bound, _)| bound == id) {
+                    Some((_, variable_type)) => Ok(variable_type.clone()),
+                    None => Err(TypeError::UnboundVariable(id.clone())),
+                }
+            }
+            SystemFTerm::Abstraction {
+                bound_variable,
+                variable_type,
+                return_term,
+            } => {
+                context.push((bound_variable.clone(), variable_type.clone()));
+                let return_type = return_term.type_in(context);
+                context.pop();
+                Ok(Type::function(variable_type.clone(), return_type?))
+            }
+            SystemFTerm::Application { function, argument } => match function.type_in(context)? {
+                Type::Function { domain, codomain } => {
+                    let argument_type = argument.type_in(context)?;
+                    if argument_type.alpha_equivalent(&domain) {
+                        Ok(*codomain)
+                    } else {
+                        Err(TypeError::Mismatch {
+                            expected: *domain,
+                            found: argument_type,
+                        })
+                    }
+                }
+                other => Err(TypeError::NotAFunction(other)),
+            },
+            SystemFTerm::TypeAbstraction {
+                type_variable,
+                return_term,
+            } => {
+                // A type variable already used by the context's types would
+                // be captured, so the binder is renamed first.
+                let in_context: HashSet<String> = context
+                    .iter()
+                    .flat_map(|(_, variable_type)| variable_type.free_type_variables())
+                    .collect();
+                if in_context.contains(type_variable) {
+                    let mut names = NameSupply::new(
+                        in_context
+                            .into_iter()
+                            .chain(return_term.free_type_variables()),
+                    );
+                    let fresh = String::from(names.fresh(type_variable));
+                    let renamed =
+                        return_term.substitute_type(type_variable, &Type::Variable(fresh.clone()));
+                    Ok(Type::forall(fresh, renamed.type_in(context)?))
+                } else {
+                    Ok(Type::forall(type_variable, return_term.type_in(context)?))
+                }
+            }
+            SystemFTerm::TypeApplication { function, argument } => {
+                match function.type_in(context)? {
+                    Type::Forall {
+                        type_variable,
+                        body,
+                    } => Ok(body.substitute(&type_variable, argument)),
+                    other => Err(TypeError::NotPolymorphic(other)),
+                }
+            }
+        }
+    }
+
+    // Drops the annotations, type abstractions and type applications.
+    pub fn erase(&self) -> LambdaTerm {
+        match self {
+            SystemFTerm::Variable(id) => LambdaTerm::Variable(id.into()),
+            SystemFTerm::Abstraction {
+                bound_variable,
+                return_term,
+                ..
+            } => LambdaTerm::Abstraction {
+                bound_variable: bound_variable.into(),
+                return_term: Box::new(return_term.erase()),
+            },
+            SystemFTerm::Application { function, argument } => LambdaTerm::Application {
+                function: Box::new(function.erase()),
+                argument: Box::new(argument.erase()),
+            },
+            SystemFTerm::TypeAbstraction { return_term, .. } => return_term.erase(),
+            SystemFTerm::TypeApplication { function, .. } => function.erase(),
+        }
+    }
+}
+
+// A term that has passed the type checker, along with its type. The only
+// way to get one is through SystemFTerm::typed, so holding one proves the
+// check succeeded.
+#[derive(Debug, Clone)]
+pub struct WellTyped {
+    term: SystemFTerm,
+    term_type: Type,
+}
+
+impl SystemFTerm {
+    pub fn typed(self) -> Result<WellTyped, TypeError> {
+        let term_type = self.type_of()?;
+        Ok(WellTyped {
+            term: self,
+            term_type,
+        })
+    }
+
+    // Whether the term stays within the simply typed λ-calculus: no type
+    // abstraction or application, and no ∀ in any annotation.
+    pub fn is_simply_typed(&self) -> bool {
+        fn is_simple(t: &Type) -> bool {
+            match t {
+                Type::Variable(_) => true,
+                Type::Function { domain, codomain } => is_simple(domain) && is_simple(codomain),
+                Type::Forall { .. } => false,
+            }
+        }
+        match self {
+            SystemFTerm::Variable(_) => true,
+            SystemFTerm::Abstraction {
+                variable_type,
+                return_term,
+                ..
+            } => is_simple(variable_type) && return_term.is_simply_typed(),
+            SystemFTerm::Application { function, argument } => {
+                function.is_simply_typed() && argument.is_simply_typed()
+            }
+            SystemFTerm::TypeAbstraction { .. } | SystemFTerm::TypeApplication { .. } => false,
+        }
+    }
+}
+
+impl WellTyped {
+    pub fn term(&self) -> &SystemFTerm {
+        &self.term
+    }
+
+    pub fn term_type(&self) -> &Type {
+        &self.term_type
+    }
+
+    pub fn into_term(self) -> SystemFTerm {
+        self.term
+    }
+
+    // The β-normal form of the erased term. System F, and the simply typed
+    // calculus within it, is strongly normalizing, and erasing types keeps
+    // that, so reduction runs with no step limit: every well-typed term
+    // reaches its normal form.
+    pub fn normalize(&self) -> LambdaTerm {
+        let mut store = TermStore::new();
+        let mut id = store.insert(&self.term.erase());
+        while let Some(reduced) = store.step(id) {
+            id = reduced;
+        }
+        store.to_named(id, NameScheme::Letters)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Variable(id) => write!(f, "{}", id),
+            Type::Function { domain, codomain } => {
+                match **domain {
+                    Type::Variable(_) => write!(f, "{}", domain)?,
+                    _ => write!(f, "({})", domain)?,
+                }
+                write!(f, " → {}", codomain)
+            }
+            Type::Forall {
+                type_variable,
+                body,
+            } => write!(f, "∀{}. {}", type_variable, body),
+        }
+    }
+}
+
+impl fmt::Display for SystemFTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemFTerm::Variable(id) => write!(f, "{}", id),
+            SystemFTerm::Abstraction {
+                bound_variable,
+                variable_type,
+                return_term,
+            } => write!(f, "λ{}: {}. {}", bound_variable, variable_type, return_term),
+            SystemFTerm::TypeAbstraction {
+                type_variable,
+                return_term,
+            } => write!(f, "Λ{}. {}", type_variable, return_term),
+            SystemFTerm::Application { function, argument } => {
+                match **function {
+                    SystemFTerm::Abstraction { .. } | SystemFTerm::TypeAbstraction { .. } => {
+                        write!(f, "({}) ", function)?
+                    }
+                    _ => write!(f, "{} ", function)?,
+                }
+                match **argument {
+                    SystemFTerm::Variable(_) => write!(f, "{}", argument),
+                    _ => write!(f, "({})", argument),
+                }
+            }
+            SystemFTerm::TypeApplication { function, argument } => match **function {
+                SystemFTerm::Abstraction { .. } | SystemFTerm::TypeAbstraction { .. } => {
+                    write!(f, "({}) [{}]", function, argument)
+                }
+                _ => write!(f, "{} [{}]", function, argument),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FToken {
+    Lambda,
+    BigLambda,
+    Forall,
+    Dot,
+    Colon,
+    Arrow,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Identifier(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemFParseError {
+    PrematureEnd,
+    StrayCharacter(char),
+    // What the parser was looking for, and the token it found instead.
+    Expected(&'static str, FToken),
+}
+
+fn tokenize(code: &str) -> Result<Vec<FToken>, SystemFParseError> {
+    let symbols = [
+        ("/\\", FToken::BigLambda),
+        ("->", FToken::Arrow),
+        ("forall", FToken::Forall),
+        ("λ", FToken::Lambda),
+        ("\\", FToken::Lambda),
+        ("Λ", FToken::BigLambda),
+        ("∀", FToken::Forall),
+        ("→", FToken::Arrow),
+        (".", FToken::Dot),
+        (":", FToken::Colon),
+        ("(", FToken::LParen),
+        (")", FToken::RParen),
+        ("[", FToken::LBracket),
+        ("]", FToken::RBracket),
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = code;
+    'tokens: while let Some(ch) = rest.chars().next() {
+        if ch.is_whitespace() {
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+        for (symbol, token) in &symbols {
+            let after = match rest.strip_prefix(symbol) {
+                Some(after) => after,
+                None => continue,
+            };
+            // `forall` is only a keyword when it isn't the start of a longer
+            // identifier.
+            let whole = !symbol.chars().all(|ch| ch.is_ascii_alphabetic())
+                || !after.starts_with(is_identifier_continue);
+            if whole {
+                tokens.push(token.clone());
+                rest = &rest[symbol.len()..];
+                continue 'tokens;
+            }
+        }
+        if is_identifier_start(ch) {
+            let end = rest
+                .char_indices()
+                .find(|(_, ch)| !is_identifier_continue(*ch) || *ch == 'Λ')
+                .map_or(rest.len(), |(i, _)| i);
+            tokens.push(FToken::Identifier(String::from(&rest[..end])));
+            rest = &rest[end..];
+        } else {
+            return Err(SystemFParseError::StrayCharacter(ch));
+        }
+    }
+    Ok(tokens)
+}
+
+struct FParser {
+    tokens: Vec<FToken>,
+    position: usize,
+}
+
+impl FParser {
+    fn peek(&self) -> Option<&FToken> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<FToken, SystemFParseError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(SystemFParseError::PrematureEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: FToken, name: &'static str) -> Result<(), SystemFParseError> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(SystemFParseError::Expected(name, token)),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, SystemFParseError> {
+        match self.next()? {
+            FToken::Identifier(id) => Ok(id),
+            token => Err(SystemFParseError::Expected("an identifier", token)),
+        }
+    }
+
+    // term ::= 'λ' id ':' type '.' term | 'Λ' id '.' term | application
+    fn term(&mut self) -> Result<SystemFTerm, SystemFParseError> {
+        match self.peek() {
+            Some(FToken::Lambda) | Some(FToken::BigLambda) => self.abstraction(),
+            _ => self.application(),
+        }
+    }
+
+    fn abstraction(&mut self) -> Result<SystemFTerm, SystemFParseError> {
+        match self.next()? {
+            FToken::Lambda => {
+                let bound_variable = self.identifier()?;
+                self.expect(FToken::Colon, "`:`")?;
+                let variable_type = self.parse_type()?;
+                self.expect(FToken::Dot, "`.`")?;
+                Ok(SystemFTerm::Abstraction {
+                    bound_variable,
+                    variable_type,
+                    return_term: Box::new(self.term()?),
+                })
+            }
+            _ => {
+                let type_variable = self.identifier()?;
+                self.expect(FToken::Dot, "`.`")?;
+                Ok(SystemFTerm::TypeAbstraction {
+                    type_variable,
+                    return_term: Box::new(self.term()?),
+                })
+            }
+        }
+    }
+
+    // application ::= atom (atom | '[' type ']')* abstraction?
+    fn application(&mut self) -> Result<SystemFTerm, SystemFParseError> {
+        let mut term = self.atom()?;
+        loop {
+            term = match self.peek() {
+                Some(FToken::LBracket) => {
+                    self.position += 1;
+                    let argument = self.parse_type()?;
+                    self.expect(FToken::RBracket, "`]`")?;
+                    SystemFTerm::TypeApplication {
+                        function: Box::new(term),
+                        argument,
+                    }
+                }
+                Some(FToken::Lambda) | Some(FToken::BigLambda) => SystemFTerm::Application {
+                    function: Box::new(term),
+                    argument: Box::new(self.abstraction()?),
+                },
+                Some(FToken::Identifier(_)) | Some(FToken::LParen) => SystemFTerm::Application {
+                    function: Box::new(term),
+                    argument: Box::new(self.atom()?),
+                },
+                _ => break Ok(term),
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<SystemFTerm, SystemFParseError> {
+        match self.next()? {
+            FToken::Identifier(id) => Ok(SystemFTerm::Variable(id)),
+            FToken::LParen => {
+                let term = self.term()?;
+                self.expect(FToken::RParen, "`)`")?;
+                Ok(term)
+            }
+            token => Err(SystemFParseError::Expected("a term", token)),
+        }
+    }
+
+    // type ::= '∀' id '.' type | type_atom ('→' type)?
+    fn parse_type(&mut self) -> Result<Type, SystemFParseError> {
+        if let Some(FToken::Forall) = self.peek() {
+            self.position += 1;
+            let type_variable = self.identifier()?;
+            self.expect(FToken::Dot, "`.`")?;
+            return Ok(Type::forall(type_variable, self.parse_type()?));
+        }
+        let domain = match self.next()? {
+            FToken::Identifier(id) => Type::Variable(id),
+            FToken::LParen => {
+                let inner = self.parse_type()?;
+                self.expect(FToken::RParen, "`)`")?;
+                inner
+            }
+            token => return Err(SystemFParseError::Expected("a type", token)),
+        };
+        if let Some(FToken::Arrow) = self.peek() {
+            self.position += 1;
+            Ok(Type::function(domain, self.parse_type()?))
+        } else {
+            Ok(domain)
+        }
+    }
+}
+
+fn parse_whole<T>(
+    code: &str,
+    parse: fn(&mut FParser) -> Result<T, SystemFParseError>,
+) -> Result<T, SystemFParseError> {
+    let mut parser = FParser {
+        tokens: tokenize(code)?,
+        position: 0,
+    };
+    let parsed = parse(&mut parser)?;
+    match parser.next() {
+        Err(SystemFParseError::PrematureEnd) => Ok(parsed),
+        Ok(token) => Err(SystemFParseError::Expected("the end of input", token)),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn parse_term(code: &str) -> Result<SystemFTerm, SystemFParseError> {
+    parse_whole(code, FParser::term)
+}
+
+pub fn parse_type(code: &str) -> Result<Type, SystemFParseError> {
+    parse_whole(code, FParser::parse_type)
+}
+
+impl std::str::FromStr for SystemFTerm {
+    type Err = SystemFParseError;
+
+    fn from_str(code: &str) -> Result<SystemFTerm, SystemFParseError> {
+        parse_term(code)
+    }
+}
+
+impl std::str::FromStr for Type {
+    type Err = SystemFParseError;
+
+    fn from_str(code: &str) -> Result<Type, SystemFParseError> {
+        parse_type(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use crate::rewrite::{Beta, Rewriter, Strategy};
+
+    const POLYMORPHIC_IDENTITY: &str = "Λa. λx: a. x";
+    const CHURCH_TWO: &str = "Λa. λf: a → a. λx: a. f (f x)";
+
+    fn term(code: &str) -> SystemFTerm {
+        parse_term(code).unwrap()
+    }
+
+    fn ty(code: &str) -> Type {
+        parse_type(code).unwrap()
+    }
+
+    #[test]
+    fn types_well_typed_terms() {
+        let cases = [
+            ("λx: o. x", "o → o"),
+            (POLYMORPHIC_IDENTITY, "∀a. a → a"),
+            (CHURCH_TWO, "∀a. (a → a) → a → a"),
+            ("(Λa. λx: a. x) [o → o]", "(o → o) → o → o"),
+            (
+                "λn: ∀a. (a → a) → a → a. n [o]",
+                "(∀a. (a → a) → a → a) → (o → o) → o → o",
+            ),
+        ];
+        for (code, expected) in cases {
+            let found = term(code).type_of().unwrap();
+            assert!(found.alpha_equivalent(&ty(expected)), "{}: {}", code, found);
+        }
+        assert!(term(POLYMORPHIC_IDENTITY).check(&ty("∀b. b → b")).is_ok());
+    }
+
+    #[test]
+    fn rejects_ill_typed_terms() {
+        assert_eq!(
+            term("λx: o. y").type_of(),
+            Err(TypeError::UnboundVariable(String::from("y")))
+        );
+        assert!(matches!(
+            term("λx: o. x x").type_of(),
+            Err(TypeError::NotAFunction(_))
+        ));
+        assert!(matches!(
+            term("λx: o. x [o]").type_of(),
+            Err(TypeError::NotPolymorphic(_))
+        ));
+        assert!(matches!(
+            term("λf: o → o. λx: p. f x").type_of(),
+            Err(TypeError::Mismatch { .. })
+        ));
+        assert!(term(POLYMORPHIC_IDENTITY).check(&ty("o → o")).is_err());
+        assert!(term("λx: o. x").typed().is_ok());
+        assert!(term("λx: o. x x").typed().is_err());
+    }
+
+    #[test]
+    fn type_substitution_renames_capturing_binders() {
+        // Substituting b for a under ∀b must not let the b refer to the ∀.
+        let body = ty("∀b. a → b");
+        let result = body.substitute("a", &ty("b"));
+        assert!(result.alpha_equivalent(&ty("∀c. b → c")), "{}", result);
+        match &result {
+            Type::Forall { type_variable, .. } => assert_ne!(type_variable, "b"),
+            other => panic!("expected a ∀, got {}", other),
+        }
+        // A Λ binding the variable itself stops the substitution, and one
+        // binding a name the replacement uses is renamed, as in types.
+        let bound = term("Λa. Λb. λx: a. λy: b. x");
+        assert_eq!(bound.substitute_type("a", &ty("b")), bound);
+        let open = term("Λb. λx: a. λy: b. x").substitute_type("a", &ty("b"));
+        assert!(open
+            .type_of()
+            .unwrap()
+            .alpha_equivalent(&ty("∀c. b → c → b")));
+        assert!(term("(Λa. Λb. λx: a. λy: b. x) [b]")
+            .type_of()
+            .unwrap()
+            .alpha_equivalent(&ty("∀c. b → c → b")));
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        let terms = [
+            POLYMORPHIC_IDENTITY,
+            CHURCH_TWO,
+            "(Λa. λx: a. x) [o → o]",
+            "λf: (o → o) → o. λg: o → o. f g",
+            "λn: ∀a. (a → a) → a → a. n [o] (λx: o. x)",
+            "(λx: o. x) ((λy: o. y) z)",
+            "f [∀a. a] [o]",
+        ];
+        for code in terms {
+            let parsed = term(code);
+            let printed = parsed.to_string();
+            assert_eq!(term(&printed), parsed, "{} printed as {}", code, printed);
+        }
+        for code in [
+            "o",
+            "(o → o) → o",
+            "o → o → o",
+            "∀a. ∀b. (a → b) → a",
+            "(∀a. a) → o",
+        ] {
+            let parsed = ty(code);
+            assert_eq!(ty(&parsed.to_string()), parsed, "{}", code);
+        }
+        assert_eq!(term("/\\a. \\x: a -> a. x"), term("Λa. λx: a → a. x"));
+        assert_eq!(ty("forall a. a -> a"), ty("∀a. a → a"));
+    }
+
+    #[test]
+    fn erasure_drops_types() {
+        assert_eq!(term(CHURCH_TWO).erase(), parse("λf. λx. f (f x)").unwrap());
+        assert_eq!(
+            term("(Λa. λx: a. x) [o] y").erase(),
+            parse("(λx. x) y").unwrap()
+        );
+        assert_eq!(term("Λa. Λb. z [a] [b]").erase(), parse("z").unwrap());
+    }
+
+    #[test]
+    fn well_typed_terms_normalize() {
+        let nat = "∀a. (a → a) → a → a";
+        let plus = format!(
+            "λm: {nat}. λn: {nat}. Λa. λf: a → a. λx: a. m [a] f (n [a] f x)",
+            nat = nat
+        );
+        let two = CHURCH_TWO;
+        let three = "Λa. λf: a → a. λx: a. f (f (f x))";
+        let sum = term(&format!("({}) ({}) ({})", plus, two, three));
+        let typed = sum.typed().unwrap();
+        assert!(typed.term_type().alpha_equivalent(&ty(nat)));
+        assert!(typed
+            .normalize()
+            .alpha_equivalent(&parse("λf. λx. f (f (f (f (f x))))").unwrap()));
+
+        // Self-application types in System F through a ∀, and still stops.
+        let polymorphic_self = term("(λx: ∀a. a → a. x [∀a. a → a] x) (Λa. λy: a. y)")
+            .typed()
+            .unwrap();
+        assert!(polymorphic_self
+            .normalize()
+            .alpha_equivalent(&parse("λy. y").unwrap()));
+    }
+
+    // normalize has no step limit because only well-typed terms reach it,
+    // and those are strongly normalizing. The terms that loop forever once
+    // erased are exactly the ones the checker has to turn away.
+    #[test]
+    fn divergent_terms_are_not_well_typed() {
+        for code in [
+            "λx: o → o. x x",
+            "(λx: o → o. x x) (λx: o → o. x x)",
+            "λx: ∀a. a. x x",
+            "λx: ∀a. a → a. x x",
+        ] {
+            assert!(term(code).typed().is_err(), "{}", code);
+        }
+        // Ω is one β-step from itself, so it has no normal form to find.
+        let omega = parse("(λx. x x) (λx. x x)").unwrap();
+        let rewriter = Rewriter::new(Strategy::TopDown, 100).rule(Beta);
+        assert!(rewriter.normalize(&omega).is_err());
+    }
+}
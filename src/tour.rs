@@ -0,0 +1,214 @@
+use crate::repl::{Outcome, Session};
+use crate::{parse, LambdaTerm};
+use std::io::{self, BufRead, Write};
+
+pub struct Exercise {
+    pub prompt: &'static str,
+    pub hint: &'static str,
+    pub check: fn(&LambdaTerm) -> bool,
+}
+
+pub struct Lesson {
+    pub title: &'static str,
+    pub text: &'static str,
+    pub exercise: Exercise,
+}
+
+fn alpha_equivalent_to(term: &LambdaTerm, expected: &str) -> bool {
+    match parse(expected) {
+        Ok(expected) => term.alpha_equivalent(&expected),
+        Err(_) => false,
+    }
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Abstractions",
+        text: "\
+A lambda term is a variable, an application `M N`, or an abstraction `λx. M`,
+the anonymous function taking `x` to `M`. You can type `\\` in place of `λ`.",
+        exercise: Exercise {
+            prompt: "Write the identity function, which returns its argument unchanged.",
+            hint: "An abstraction whose body is just its bound variable: λx. ...",
+            check: |term| alpha_equivalent_to(term, "λx. x"),
+        },
+    },
+    Lesson {
+        title: "Application",
+        text: "\
+Application associates to the left, so `f x y` means `(f x) y`: first `f` is
+applied to `x`, then the result is applied to `y`. An abstraction's body
+extends as far right as possible, so `λx. x y` is `λx. (x y)`.",
+        exercise: Exercise {
+            prompt: "Write the term applying `f` to `x` and then the result to `y`.",
+            hint: "Left associativity means you don't need any parentheses.",
+            check: |term| alpha_equivalent_to(term, "(f x) y"),
+        },
+    },
+    Lesson {
+        title: "Free and bound variables",
+        text: "\
+An occurrence of `x` inside `λx. M` is bound by that abstraction. Occurrences
+not under any binder for their name are free: in `λx. x y`, `x` is bound and
+`y` is free.",
+        exercise: Exercise {
+            prompt: "Write a term in which `x` occurs free and `y` is bound.",
+            hint: "Try applying an abstraction over y to x, or the other way around.",
+            check: |term| {
+                let free = term.free_variables();
+                free.contains("x") && !free.contains("y") && term.bound_variables().contains("y")
+            },
+        },
+    },
+    Lesson {
+        title: "α-equivalence",
+        text: "\
+The names of bound variables don't matter: `λx. x` and `λy. y` are
+α-equivalent, and the crate treats them as the same term.",
+        exercise: Exercise {
+            prompt: "Write a term α-equivalent to `λa. λb. a` that binds neither `a` nor `b`.",
+            hint: "Rename both binders consistently, e.g. to p and q.",
+            check: |term| {
+                let bound = term.bound_variables();
+                alpha_equivalent_to(term, "λa. λb. a")
+                    && !bound.contains("a")
+                    && !bound.contains("b")
+            },
+        },
+    },
+    Lesson {
+        title: "β-reduction",
+        text: "\
+Applying an abstraction substitutes the argument for its bound variable:
+`(λx. M) N` β-reduces to `M` with every free `x` replaced by `N`.",
+        exercise: Exercise {
+            prompt: "What does `(λx. x x) y` β-reduce to?",
+            hint: "Replace each x in the body `x x` with y.",
+            check: |term| alpha_equivalent_to(term, "y y"),
+        },
+    },
+    Lesson {
+        title: "Variable capture",
+        text: "\
+Substitution must not capture free variables. Reducing `(λx. λy. x) y` naively
+gives `λy. y`, which wrongly binds the free `y`. Rename the inner binder first.",
+        exercise: Exercise {
+            prompt: "What does `(λx. λy. x) y` β-reduce to?",
+            hint: "Rename λy to λz before substituting: (λx. λz. x) y.",
+            check: |term| alpha_equivalent_to(term, "λz. y"),
+        },
+    },
+    Lesson {
+        title: "η-reduction",
+        text: "\
+`λx. f x` behaves exactly like `f` whenever `x` is not free in `f`, so it
+η-reduces to `f`.",
+        exercise: Exercise {
+            prompt: "η-reduce `λx. g h x`.",
+            hint: "The function being wrapped is `g h`.",
+            check: |term| alpha_equivalent_to(term, "g h"),
+        },
+    },
+    Lesson {
+        title: "De Bruijn indices",
+        text: "\
+De Bruijn notation drops binder names: each variable is replaced by the number
+of abstractions between it and its binder, counting from 1. `λx. λy. x` becomes
+`λ λ 2`. α-equivalent terms have the same de Bruijn form.",
+        exercise: Exercise {
+            prompt: "Write a named term whose de Bruijn form is `λ λ 1 2`.",
+            hint: "1 refers to the innermost binder and 2 to the one outside it.",
+            check: |term| alpha_equivalent_to(term, "λa. λb. b a"),
+        },
+    },
+    Lesson {
+        title: "Church numerals",
+        text: "\
+The Church numeral n applies a function n times: `0 = λf. λx. x`,
+`1 = λf. λx. f x`, `2 = λf. λx. f (f x)`. Try `:set numerals sugar` to have
+them printed as digits.",
+        exercise: Exercise {
+            prompt: "Write the Church numeral 3.",
+            hint: "Apply f to x three times, nesting to the right.",
+            check: |term| alpha_equivalent_to(term, "λf. λx. f (f (f x))"),
+        },
+    },
+    Lesson {
+        title: "Church booleans",
+        text: "\
+Booleans choose between two arguments: `true = λt. λf. t` and
+`false = λt. λf. f`, so `b M N` acts as `if b then M else N`.",
+        exercise: Exercise {
+            prompt: "Write `not`, which takes a boolean and returns the other one.",
+            hint: "λb. b false true, with false and true written out.",
+            check: |term| alpha_equivalent_to(term, "λb. b (λt. λf. f) (λt. λf. t)"),
+        },
+    },
+];
+
+const COMMANDS: &str = "\
+answer with a term, or use :hint, :skip or :quit (other REPL commands such as
+:set work as usual)";
+
+#[derive(Default)]
+pub struct Tour {
+    pub session: Session,
+}
+
+impl Tour {
+    pub fn new() -> Tour {
+        Tour::default()
+    }
+
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<()> {
+        let mut lines = input.lines();
+        writeln!(output, "{}\n", COMMANDS)?;
+        for (number, lesson) in LESSONS.iter().enumerate() {
+            writeln!(
+                output,
+                "{}/{}: {}\n\n{}\n\n{}",
+                number + 1,
+                LESSONS.len(),
+                lesson.title,
+                lesson.text,
+                lesson.exercise.prompt
+            )?;
+            loop {
+                write!(output, "{}", self.session.prompt())?;
+                output.flush()?;
+                let line = match lines.next() {
+                    Some(line) => line?,
+                    None => return writeln!(output),
+                };
+                let line = line.trim();
+                match line {
+                    "" => continue,
+                    ":hint" => writeln!(output, "{}", lesson.exercise.hint)?,
+                    ":skip" => {
+                        writeln!(output)?;
+                        break;
+                    }
+                    ":q" | ":quit" => return Ok(()),
+                    _ if line.starts_with(':') => match self.session.execute(line) {
+                        Outcome::Output(text) if text.is_empty() => (),
+                        Outcome::Output(text) => writeln!(output, "{}", text)?,
+                        Outcome::Quit => return Ok(()),
+                    },
+                    _ => match parse(line) {
+                        Ok(term) if (lesson.exercise.check)(&term) => {
+                            writeln!(output, "correct!\n")?;
+                            break;
+                        }
+                        Ok(term) => writeln!(
+                            output,
+                            "{} isn't quite right, try again",
+                            term.display_with(&self.session.print_options)
+                        )?,
+                        Err(err) => writeln!(output, "error: {:?}", err)?,
+                    },
+                }
+            }
+        }
+        writeln!(output, "that's the end of the tour!")
+    }
+}
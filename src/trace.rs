@@ -0,0 +1,147 @@
+use crate::path::{Path, Step};
+use crate::rewrite::{RewriteError, Rewriter};
+use crate::{Backticks, LambdaTerm, Quoting};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+// How the redex about to be contracted is picked out in each printed term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    // ANSI underline.
+    Underline,
+    // ANSI bold yellow.
+    Color,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+    pub highlight: Highlight,
+    // Traces longer than twice this print only the first and last `keep`
+    // steps, with a line saying how many were left out between them.
+    pub keep: usize,
+}
+
+impl Default for TraceOptions {
+    fn default() -> TraceOptions {
+        TraceOptions {
+            highlight: Highlight::None,
+            keep: 20,
+        }
+    }
+}
+
+// Prints `term` as Display does, with the subterm at `path` wrapped in
+// `open` and `close`.
+pub fn mark(term: &LambdaTerm, path: &Path, open: &str, close: &str) -> String {
+    fn mark_mut(term: &LambdaTerm, path: Option<&[Step]>, markers: (&str, &str), out: &mut String) {
+        if path == Some(&[]) {
+            out.push_str(markers.0);
+            mark_mut(term, None, markers, out);
+            out.push_str(markers.1);
+            return;
+        }
+        let below = |step: Step| match path {
+            Some([first, rest @ ..]) if *first == step => Some(rest),
+            _ => None,
+        };
+        match term {
+            LambdaTerm::Variable(id) => {
+                let _ = Backticks.write_identifier(out, id);
+            }
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => {
+                out.push('λ');
+                let _ = Backticks.write_identifier(out, bound_variable);
+                out.push_str(". ");
+                mark_mut(return_term, below(Step::Body), markers, out);
+            }
+            LambdaTerm::Application { function, argument } => {
+                let wrapped = matches!(**function, LambdaTerm::Abstraction { .. });
+                if wrapped {
+                    out.push('(');
+                }
+                mark_mut(function, below(Step::Function), markers, out);
+                if wrapped {
+                    out.push(')');
+                }
+                out.push(' ');
+                let wrapped = !matches!(**argument, LambdaTerm::Variable(_));
+                if wrapped {
+                    out.push('(');
+                }
+                mark_mut(argument, below(Step::Argument), markers, out);
+                if wrapped {
+                    out.push(')');
+                }
+            }
+        }
+    }
+    let mut out = String::new();
+    mark_mut(term, Some(&path.0), (open, close), &mut out);
+    out
+}
+
+// Rewrites `term` to normal form, writing one numbered line per term along
+// the way, each naming the rule that produced it. Gives the normal form, or
+// the rewriter's error once its step limit is reached.
+pub fn write_trace<W: Write>(
+    out: &mut W,
+    rewriter: &Rewriter,
+    term: &LambdaTerm,
+    options: &TraceOptions,
+) -> io::Result<Result<LambdaTerm, RewriteError>> {
+    let (open, close) = match options.highlight {
+        Highlight::None => ("", ""),
+        Highlight::Underline => ("\x1b[4m", "\x1b[24m"),
+        Highlight::Color => ("\x1b[1;33m", "\x1b[0m"),
+    };
+    // Lines past the first `keep` wait here until it's clear whether they
+    // are among the last `keep`.
+    let mut tail = VecDeque::new();
+    let mut elided = 0;
+    let mut emit = |step: usize, line: String, out: &mut W| {
+        if step < options.keep {
+            return writeln!(out, "{}", line);
+        }
+        tail.push_back(line);
+        if tail.len() > options.keep {
+            tail.pop_front();
+            elided += 1;
+        }
+        Ok(())
+    };
+    let mut term = term.clone();
+    let mut rule = String::new();
+    let mut step = 0;
+    let result = loop {
+        let next = rewriter.step(&term);
+        let mut line = format!("{:>4} {:<2}", step, rule);
+        match &next {
+            Some(rewritten) => line.push_str(&mark(&term, &rewritten.path, open, close)),
+            None => {
+                let _ = write!(line, "{}", term);
+            }
+        }
+        emit(step, line, out)?;
+        match next {
+            Some(_) if step == rewriter.limit => break Err(RewriteError::StepLimit(step)),
+            Some(rewritten) => {
+                rule = String::from(rewritten.rule);
+                term = rewritten.term;
+                step += 1;
+            }
+            None => break Ok(term),
+        }
+    };
+    if elided > 0 {
+        writeln!(out, "     … {} steps elided", elided)?;
+    }
+    for line in tail {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,150 @@
+use crate::path::{Path, Step};
+use crate::LambdaTerm;
+
+// Each method defaults to walking into the subterms, so an analysis only
+// overrides the cases it cares about, calling walk_term (or the default
+// directly) to keep descending.
+pub trait TermVisitor {
+    fn visit_term(&mut self, term: &LambdaTerm) {
+        walk_term(self, term)
+    }
+
+    fn visit_variable(&mut self, _id: &str) {}
+
+    fn visit_abstraction(&mut self, _bound_variable: &str, return_term: &LambdaTerm) {
+        self.visit_term(return_term)
+    }
+
+    fn visit_application(&mut self, function: &LambdaTerm, argument: &LambdaTerm) {
+        self.visit_term(function);
+        self.visit_term(argument);
+    }
+}
+
+pub fn walk_term<V: TermVisitor + ?Sized>(visitor: &mut V, term: &LambdaTerm) {
+    match term {
+        LambdaTerm::Variable(id) => visitor.visit_variable(id),
+        LambdaTerm::Abstraction {
+            bound_variable,
+            return_term,
+        } => visitor.visit_abstraction(bound_variable, return_term),
+        LambdaTerm::Application { function, argument } => {
+            visitor.visit_application(function, argument)
+        }
+    }
+}
+
+impl LambdaTerm {
+    pub fn accept<V: TermVisitor + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_term(self)
+    }
+
+    // Folds bottom up: each abstraction gets its binder and the folded body,
+    // each application its folded function and argument.
+    pub fn fold<T, V, A, P>(&self, mut variable: V, mut abstraction: A, mut application: P) -> T
+    where
+        V: FnMut(&str) -> T,
+        A: FnMut(&str, T) -> T,
+        P: FnMut(T, T) -> T,
+    {
+        fn fold_mut<T>(
+            term: &LambdaTerm,
+            variable: &mut dyn FnMut(&str) -> T,
+            abstraction: &mut dyn FnMut(&str, T) -> T,
+            application: &mut dyn FnMut(T, T) -> T,
+        ) -> T {
+            match term {
+                LambdaTerm::Variable(id) => variable(id),
+                LambdaTerm::Abstraction {
+                    bound_variable,
+                    return_term,
+                } => {
+                    let body = fold_mut(return_term, variable, abstraction, application);
+                    abstraction(bound_variable, body)
+                }
+                LambdaTerm::Application { function, argument } => {
+                    let function = fold_mut(function, variable, abstraction, application);
+                    let argument = fold_mut(argument, variable, abstraction, application);
+                    application(function, argument)
+                }
+            }
+        }
+        fold_mut(self, &mut variable, &mut abstraction, &mut application)
+    }
+
+    // Rebuilds this node with `f` applied to its immediate subterms, leaving
+    // variables as they are. Recursing through `f` gives a full rewrite.
+    pub fn map_subterms<F: FnMut(&LambdaTerm) -> LambdaTerm>(&self, mut f: F) -> LambdaTerm {
+        match self {
+            LambdaTerm::Variable(_) => self.clone(),
+            LambdaTerm::Abstraction {
+                bound_variable,
+                return_term,
+            } => LambdaTerm::Abstraction {
+                bound_variable: bound_variable.clone(),
+                return_term: Box::new(f(return_term)),
+            },
+            LambdaTerm::Application { function, argument } => LambdaTerm::Application {
+                function: Box::new(f(function)),
+                argument: Box::new(f(argument)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Pre,
+    Post,
+}
+
+pub struct Subterms<'a> {
+    order: Order,
+    // Post-order entries are visited twice: first to push their children,
+    // then, once those are done, to be yielded.
+    stack: Vec<(&'a LambdaTerm, Path, bool)>,
+}
+
+impl<'a> Iterator for Subterms<'a> {
+    type Item = (Path, &'a LambdaTerm);
+
+    fn next(&mut self) -> Option<(Path, &'a LambdaTerm)> {
+        loop {
+            let (term, path, expanded) = self.stack.pop()?;
+            if expanded || self.order == Order::Pre {
+                if self.order == Order::Pre {
+                    self.push_children(term, &path);
+                }
+                return Some((path, term));
+            }
+            self.stack.push((term, path.clone(), true));
+            self.push_children(term, &path);
+        }
+    }
+}
+
+impl<'a> Subterms<'a> {
+    fn push_children(&mut self, term: &'a LambdaTerm, path: &Path) {
+        let child = |step, term| (term, path.child(step), false);
+        match term {
+            LambdaTerm::Variable(_) => (),
+            LambdaTerm::Abstraction { return_term, .. } => {
+                self.stack.push(child(Step::Body, &**return_term))
+            }
+            LambdaTerm::Application { function, argument } => self.stack.extend([
+                child(Step::Argument, &**argument),
+                child(Step::Function, &**function),
+            ]),
+        }
+    }
+}
+
+impl LambdaTerm {
+    // Every subterm, this one included, with the path that leads to it.
+    pub fn iter_subterms(&self, order: Order) -> Subterms<'_> {
+        Subterms {
+            order,
+            stack: vec![(self, Path::default(), false)],
+        }
+    }
+}